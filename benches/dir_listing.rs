@@ -0,0 +1,48 @@
+// Listing pages of a directory of ENTRIES files, requested from a running
+// server the way the integration tests do, since the server is a binary
+// with no library to call `generate_directory_listing` through. A request
+// costs a connection on top of the listing, which at this size is a small
+// part of it.
+//
+//     cargo bench --bench dir_listing
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const ENTRIES: usize = 1000;
+
+// Names of varying length and sizes of varying magnitude, with a few
+// directories among them, so that sorting and formatting do real work.
+fn synthesize_directory() -> tempfile::TempDir {
+    let root = tempfile::tempdir().unwrap();
+    for i in 0..ENTRIES {
+        if i % 10 == 0 {
+            std::fs::create_dir(root.path().join(format!("dir {}", i))).unwrap();
+        } else {
+            std::fs::write(root.path().join(format!("file-{}{}.txt", i, "x".repeat(i % 17))), vec![b'x'; i * 37 % 4096]).unwrap();
+        }
+    }
+    root
+}
+
+fn dir_listing(c: &mut Criterion) {
+    let root = synthesize_directory();
+    let mut group = c.benchmark_group("dir_listing");
+    group.throughput(Throughput::Elements(ENTRIES as u64));
+
+    // Every request reads the directory afresh.
+    let server = common::start_server(&["--root", root.path().to_str().unwrap(), "--listing-cache-ttl", "0"]);
+    assert!(common::get(&server.addr, "/").starts_with("HTTP/1.1 200"));
+    group.bench_function("uncached", |b| b.iter(|| common::get(&server.addr, "/")));
+    group.bench_function("uncached_by_size", |b| b.iter(|| common::get(&server.addr, "/?sort=size&order=desc")));
+    drop(server);
+
+    // Entries come from the listing cache; only sorting and rendering remain.
+    let server = common::start_server(&["--root", root.path().to_str().unwrap()]);
+    group.bench_function("cached", |b| b.iter(|| common::get(&server.addr, "/")));
+    group.finish();
+}
+
+criterion_group!(benches, dir_listing);
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+// Raw downloads of files from 1 KiB to 1 GiB, end to end over loopback: a
+// client in this process requests each file from a running server and reads
+// the response to the end. The server is a binary, so it runs as a child
+// process, started the way the integration tests start it.
+//
+// The files are sparse, so reading them costs the kernel no disk I/O and
+// what is measured is the server's own reading, copying and writing.
+//
+//     cargo bench --bench file_serve
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const SIZES: &[(&str, u64)] = &[("1KiB", 1 << 10), ("1MiB", 1 << 20), ("100MiB", 100 << 20), ("1GiB", 1 << 30)];
+
+// Bytes of the whole response, head included, read into a reused buffer
+// rather than kept.
+fn download(addr: &str, target: &str, buffer: &mut [u8]) -> u64 {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes()).unwrap();
+    let mut received = 0;
+    loop {
+        match stream.read(buffer).unwrap() {
+            0 => return received,
+            read => received += read as u64,
+        }
+    }
+}
+
+fn file_serve(c: &mut Criterion) {
+    let root = tempfile::tempdir().unwrap();
+    for (name, size) in SIZES {
+        std::fs::File::create(root.path().join(name)).unwrap().set_len(*size).unwrap();
+    }
+    let server = common::start_server(&["--root", root.path().to_str().unwrap()]);
+    let mut buffer = vec![0; 256 * 1024];
+
+    let mut group = c.benchmark_group("file_serve");
+    for (name, size) in SIZES {
+        let target = format!("/{}?raw=1", name);
+        assert!(download(&server.addr, &target, &mut buffer) > *size);
+        group.throughput(Throughput::Bytes(*size));
+        // Criterion's fewest samples, which for a gigabyte is plenty.
+        if *size >= 100 << 20 {
+            group.sample_size(10);
+        }
+        group.bench_with_input(BenchmarkId::from_parameter(name), &target, |b, target| {
+            b.iter(|| download(&server.addr, target, &mut buffer))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, file_serve);
+criterion_main!(benches);
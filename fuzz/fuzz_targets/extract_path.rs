@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::path::{Component, Path};
+
+// The server is a binary, so its module is built into the target directly.
+#[path = "../../src/url_path.rs"]
+#[allow(dead_code)]
+mod url_path;
+
+fuzz_target!(|data: &[u8]| {
+    // Request heads reach `extract_path` as text, with invalid UTF-8 replaced.
+    let target = String::from_utf8_lossy(data);
+    let path = url_path::extract_path(&target);
+
+    // Nothing but the root and plain names, so joining it to a document root
+    // can only lead inside it.
+    assert!(path.is_absolute(), "{:?} from {:?}", path, target);
+    assert!(
+        path.components().all(|component| matches!(component, Component::RootDir | Component::Normal(_))),
+        "{:?} from {:?}",
+        path,
+        target
+    );
+    let root = Path::new("/srv/www");
+    let joined = root.join(path.strip_prefix("/").unwrap());
+    assert!(joined.starts_with(root), "{:?} escapes the root", joined);
+    assert!(joined.components().count() >= root.components().count());
+});
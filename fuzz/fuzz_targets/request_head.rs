@@ -0,0 +1,53 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The server is a binary, so its module is built into the target directly.
+#[path = "../../src/request.rs"]
+#[allow(dead_code)]
+mod request;
+
+const LIMIT: u64 = 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+    runtime.block_on(async {
+        let mut reader = data;
+        let (head, leftover) = match request::read_head(&mut reader).await {
+            Ok(Some((head, leftover, _))) => (head, leftover),
+            Ok(None) => {
+                assert!(data.is_empty());
+                return;
+            }
+            Err(_) => return,
+        };
+        // A head is only accepted whole, and every line of it ends in CRLF.
+        let head_len = data.len() - leftover.len() - reader.len();
+        let raw_head = &data[..head_len];
+        assert!(raw_head.ends_with(b"\r\n\r\n"), "{:?}", raw_head);
+        assert!(!raw_head.windows(2).any(|pair| pair[1] == b'\n' && pair[0] != b'\r'), "bare LF in {:?}", raw_head);
+        for line in head.split("\r\n").skip(1).take_while(|line| !line.is_empty()) {
+            let (name, _) = line.split_once(':').expect("header line without a colon");
+            assert!(!name.is_empty() && name.bytes().all(|byte| byte.is_ascii_graphic()), "{:?}", line);
+        }
+
+        let content_length = request::extract_header(&head, "Content-Length");
+        let transfer_encoding = request::extract_header(&head, "Transfer-Encoding");
+        let mut body = match request::Body::new(content_length, transfer_encoding, leftover.clone(), LIMIT) {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+        if transfer_encoding.is_none() {
+            let length = content_length.map(|length| length.trim().parse::<u64>().expect("invalid Content-Length accepted"));
+            assert!(length.unwrap_or(0) <= LIMIT);
+        }
+        // The body never yields more than was sent after the head.
+        let sent = leftover.len() + reader.len();
+        let mut received = 0;
+        while let Ok(Some(chunk)) = body.next_chunk(&mut reader).await {
+            received += chunk.len();
+            assert!(received <= sent, "{} body bytes from {} sent", received, sent);
+            assert!(received as u64 <= LIMIT);
+        }
+    });
+});
@@ -0,0 +1,209 @@
+// The JSON documents the server answers scripts with, in place of the HTML
+// pages browsers get. Field names and types are part of the interface; add
+// to them rather than change them.
+use crate::listing::Summary;
+use serde::Serialize;
+use std::time::SystemTime;
+
+// A directory listing, for a request that prefers `application/json`.
+#[derive(Serialize)]
+pub struct Listing {
+    // The directory's URL path, ending in `/`.
+    pub path: String,
+    // Href of the parent directory; null at the root.
+    pub parent: Option<String>,
+    pub entries: Vec<Entry>,
+    pub summary: Summary,
+    // Set when the directory has more than MAX_SORTED_ENTRIES entries and
+    // only that many were listed.
+    pub truncated: bool,
+}
+
+// A page of a listing, from `/api/v1/ls`. Its `truncated` is also set when
+// more entries follow the page.
+#[derive(Serialize)]
+pub struct ListingPage {
+    #[serde(flatten)]
+    pub listing: Listing,
+    // Of the first entry on the page, among all that match.
+    pub offset: usize,
+    // Entries matching the query, up to MAX_SORTED_ENTRIES.
+    pub total: usize,
+}
+
+#[derive(Serialize)]
+pub struct Entry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: EntryType,
+    // In bytes, for files; null for directories and unreadable entries.
+    pub size: Option<u64>,
+    // RFC 3339, in UTC.
+    pub mtime: Option<String>,
+    // Percent-encoded, under the base URL; directories end in `/`.
+    pub href: String,
+}
+
+// What a symbolic link points to decides between the two.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    Directory,
+    File,
+}
+
+// One path, from `/api/v1/stat`.
+#[derive(Serialize)]
+pub struct Stat {
+    // The URL path asked about.
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: StatType,
+    // The remaining fields describe what a symbolic link points to, and are
+    // null when that is missing. `size` is null for directories too.
+    pub size: Option<u64>,
+    pub mtime: Option<String>,
+    // When the inode last changed; null off unix.
+    pub ctime: Option<String>,
+    // Permission bits, such as 420 for `0644`; null off unix.
+    pub mode: Option<u32>,
+    // What a symbolic link contains, as it is on disk; null for the rest.
+    pub target: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatType {
+    Directory,
+    File,
+    Symlink,
+}
+
+impl Stat {
+    // `metadata` is the followed one, `link` what a symbolic link at the path
+    // contains, when there is one.
+    pub fn new(path: String, metadata: Option<&std::fs::Metadata>, link: Option<&std::path::Path>) -> Self {
+        let kind = match (link, metadata) {
+            (Some(_), _) => StatType::Symlink,
+            (None, Some(metadata)) if metadata.is_dir() => StatType::Directory,
+            (None, _) => StatType::File,
+        };
+        Stat {
+            path,
+            kind,
+            size: metadata.filter(|metadata| !metadata.is_dir()).map(|metadata| metadata.len()),
+            mtime: metadata.and_then(|metadata| metadata.modified().ok()).map(timestamp),
+            ctime: metadata.and_then(changed).map(timestamp),
+            mode: metadata.and_then(crate::listing::UnixDetails::of).map(|unix| unix.mode & 0o7777),
+            target: link.map(|target| target.to_string_lossy().to_string()),
+        }
+    }
+}
+
+// The digest of a file, from `/api/v1/hash`. `size` and `mtime` are those
+// the file had when hashing began.
+#[derive(Serialize)]
+pub struct Checksum {
+    pub path: String,
+    pub algo: &'static str,
+    // Lowercase hex.
+    pub digest: String,
+    pub size: u64,
+    pub mtime: Option<String>,
+}
+
+// The tree under a directory, from `/api/v1/tree`.
+#[derive(Serialize)]
+pub struct Tree {
+    // The directory's URL path, ending in `/`.
+    pub path: String,
+    // Levels of directories read, which is the one asked for unless that is
+    // more than the server allows.
+    pub depth: usize,
+    // The directory itself, named "/" at the root.
+    pub root: TreeNode,
+}
+
+#[derive(Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: EntryType,
+    pub size: Option<u64>,
+    pub mtime: Option<String>,
+    // Directories only: their entries by name, and whether any were left
+    // out, because the directory is below `depth`, was already listed
+    // elsewhere through a symbolic link, could not be read, or the node
+    // limit was reached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeNode>>,
+}
+
+// One matching line, from `/api/v1/grep`.
+#[derive(Serialize)]
+pub struct GrepMatch {
+    // The file's URL path.
+    pub path: String,
+    // Counted from 1.
+    pub line_number: usize,
+    // Without its line ending, and cut short when very long. Bytes that are
+    // not UTF-8 are replaced.
+    pub line: String,
+}
+
+// RFC 3339, in UTC, to the second.
+pub fn timestamp(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+#[cfg(unix)]
+fn changed(metadata: &std::fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    SystemTime::UNIX_EPOCH.checked_add(std::time::Duration::new(seconds, metadata.ctime_nsec() as u32))
+}
+
+#[cfg(not(unix))]
+fn changed(_metadata: &std::fs::Metadata) -> Option<SystemTime> {
+    None
+}
+
+// Whether an `Accept` header ranks `application/json` above `text/html`,
+// wildcards included. Ties, such as `*/*` or no header at all, go to HTML,
+// which is what browsers are served.
+pub fn prefers_json(accept: Option<&str>) -> bool {
+    let Some(accept) = accept else {
+        return false;
+    };
+    quality(accept, "application", "json") > quality(accept, "text", "html")
+}
+
+// The q-value `accept` gives `kind/subtype`: that of the most specific range
+// matching it, or 0 when none does.
+fn quality(accept: &str, kind: &str, subtype: &str) -> f32 {
+    let mut best: Option<(u8, f32)> = None;
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let Some((range_kind, range_subtype)) = params.next().and_then(|media| media.trim().split_once('/')) else {
+            continue;
+        };
+        let specificity = match (range_kind.trim(), range_subtype.trim()) {
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => 2,
+            (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+            ("*", "*") => 0,
+            _ => continue,
+        };
+        let q = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .and_then(|(_, value)| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        if best.is_none_or(|(best_specificity, _)| specificity > best_specificity) {
+            best = Some((specificity, q));
+        }
+    }
+    best.map(|(_, q)| q).unwrap_or(0.0)
+}
@@ -0,0 +1,248 @@
+use crate::directory_config::{self, DirectoryConfig};
+use async_compression::tokio::write::GzipEncoder;
+use async_zip::base::write::ZipFileWriter;
+use async_zip::{Compression, ZipEntryBuilder};
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::fs;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Zip,
+    TarGz,
+}
+
+impl Format {
+    // Parses the value of the `?download=` query parameter.
+    pub fn from_query(value: &str) -> Option<Self> {
+        match value {
+            "zip" => Some(Format::Zip),
+            "tar.gz" | "tgz" => Some(Format::TarGz),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Zip => "application/zip",
+            Format::TarGz => "application/gzip",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+        }
+    }
+}
+
+// `directory` holds the settings of `dir`, which keep out of the archive what
+// they keep from being sent. Returns the size of the archive, for the audit
+// log.
+pub async fn write_archive<W: AsyncWrite + Unpin + Send + 'static>(writer: W, dir: &Path, directory: DirectoryConfig, format: Format) -> io::Result<u64> {
+    let writer = Counted { inner: writer, count: 0 };
+    let writer = match format {
+        Format::Zip => write_zip(writer, dir, directory).await?,
+        Format::TarGz => write_tar_gz(writer, dir, directory).await?,
+    };
+    Ok(writer.count)
+}
+
+// Counts the bytes written through it.
+struct Counted<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for Counted<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.count += written as u64;
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+// The Content-Disposition value offering the archive of `dir` under the
+// directory's name. A directory name can hold anything but a slash, so the
+// plain `filename` keeps only printable ASCII, and a name that needs more
+// is given again in full as RFC 6266's `filename*`.
+pub fn content_disposition(dir: &Path, extension: &str) -> String {
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    let name = format!("{}.{}", name.replace(|c: char| c.is_control(), "_"), extension);
+    let plain: String = name.chars().map(|c| if c.is_ascii() && c != '"' && c != '\\' { c } else { '_' }).collect();
+    if name.is_ascii() {
+        format!("attachment; filename=\"{}\"", plain)
+    } else {
+        format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", plain, utf8_percent_encode(&name, ATTR_CHAR))
+    }
+}
+
+// What RFC 5987 lets through unencoded in an extended parameter value.
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+// Every regular file and directory below `dir`, as (path on disk, path inside
+// the archive). Symlinks are skipped entirely so an archive can never pull in
+// files from outside the requested tree, and so are files that the settings
+// of their directory keep from being served, by extension or size. The walk
+// is depth-first and only keeps the pending directories, with their
+// settings, in memory.
+struct Walker {
+    pending: Vec<(PathBuf, String, DirectoryConfig)>,
+    current: Option<(fs::ReadDir, String, DirectoryConfig)>,
+}
+
+enum WalkEntry {
+    Dir(String, std::fs::Metadata),
+    File(PathBuf, String, std::fs::Metadata),
+}
+
+impl Walker {
+    fn new(dir: &Path, directory: DirectoryConfig) -> Self {
+        Walker { pending: vec![(dir.to_path_buf(), String::new(), directory)], current: None }
+    }
+
+    async fn next(&mut self) -> Option<WalkEntry> {
+        loop {
+            if self.current.is_none() {
+                let (dir, prefix, directory) = self.pending.pop()?;
+                match fs::read_dir(&dir).await {
+                    Ok(entries) => self.current = Some((entries, prefix, directory)),
+                    Err(e) => tracing::error!("Skipping unreadable directory {}: {}", dir.display(), e),
+                }
+                continue;
+            }
+
+            let (entries, prefix, directory) = self.current.as_mut()?;
+            let entry = match entries.next_entry().await {
+                Ok(Some(entry)) => entry,
+                _ => {
+                    self.current = None;
+                    continue;
+                }
+            };
+            // Directory settings are for the server, not for its visitors.
+            if entry.file_name() == directory_config::FILE_NAME {
+                continue;
+            }
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            let metadata = match fs::symlink_metadata(entry.path()).await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                let below = directory.for_subdirectory(&entry.path()).await;
+                self.pending.push((entry.path(), format!("{}/", name), below));
+                return Some(WalkEntry::Dir(format!("{}/", name), metadata));
+            } else if metadata.is_file() && directory.serves_extension(&entry.file_name().to_string_lossy()) {
+                if directory.max_file_size.is_some_and(|max_file_size| metadata.len() > max_file_size) {
+                    continue;
+                }
+                return Some(WalkEntry::File(entry.path(), name, metadata));
+            }
+        }
+    }
+}
+
+fn with_mtime(builder: ZipEntryBuilder, metadata: &std::fs::Metadata) -> ZipEntryBuilder {
+    match metadata.modified() {
+        Ok(modified) => {
+            let modified: DateTime<Utc> = modified.into();
+            builder.last_modification_date((&modified).into())
+        }
+        Err(_) => builder,
+    }
+}
+
+async fn write_zip<W: AsyncWrite + Unpin>(writer: W, dir: &Path, directory: DirectoryConfig) -> io::Result<W> {
+    let mut zip = ZipFileWriter::with_tokio(writer);
+    let mut walker = Walker::new(dir, directory);
+
+    while let Some(entry) = walker.next().await {
+        match entry {
+            WalkEntry::Dir(name, metadata) => {
+                let builder = with_mtime(ZipEntryBuilder::new(name.into(), Compression::Stored), &metadata);
+                zip.write_entry_whole(builder, &[]).await.map_err(io::Error::other)?;
+            }
+            WalkEntry::File(path, name, metadata) => {
+                let file = match fs::File::open(&path).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        tracing::error!("Skipping unreadable file {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                let builder = with_mtime(ZipEntryBuilder::new(name.into(), Compression::Deflate), &metadata);
+
+                let mut entry_writer = zip.write_entry_stream(builder).await.map_err(io::Error::other)?;
+                futures_lite::io::copy(file.compat(), &mut entry_writer).await?;
+                entry_writer.close().await.map_err(io::Error::other)?;
+            }
+        }
+    }
+
+    let mut writer = zip.close().await.map_err(io::Error::other)?.into_inner();
+    writer.shutdown().await?;
+    Ok(writer)
+}
+
+// Entry paths are relative to `dir`. The gzip encoder sits between the tar
+// builder and the socket, so compressed output is written out as each entry
+// is appended rather than collected first.
+async fn write_tar_gz<W: AsyncWrite + Unpin + Send + 'static>(writer: W, dir: &Path, directory: DirectoryConfig) -> io::Result<W> {
+    let mut tar = tokio_tar::Builder::new(GzipEncoder::new(writer));
+    let mut walker = Walker::new(dir, directory);
+
+    while let Some(entry) = walker.next().await {
+        match entry {
+            WalkEntry::Dir(name, _) => {
+                let path = dir.join(&name);
+                tar.append_dir(name.trim_end_matches('/'), path).await?;
+            }
+            WalkEntry::File(path, name, _) => {
+                let mut file = match fs::File::open(&path).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        tracing::error!("Skipping unreadable file {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                tar.append_file(name, &mut file).await?;
+            }
+        }
+    }
+
+    let mut encoder = tar.into_inner().await?;
+    encoder.shutdown().await?;
+    Ok(encoder.into_inner())
+}
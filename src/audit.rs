@@ -0,0 +1,111 @@
+use chrono::Utc;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Statuses worth an audit record: authentication failures, denials, misses
+// and rate limiting. Other responses are only audited when they send a file,
+// and only to an audit file.
+const AUDITED_STATUSES: &[u16] = &[401, 403, 404, 421, 429];
+
+// What an audit record says about one request.
+pub struct Record<'a> {
+    pub remote_ip: &'a str,
+    // The user name the request gave, when authentication is configured.
+    pub user: Option<&'a str>,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub status: u16,
+    // Body bytes sent, for files.
+    pub bytes: Option<u64>,
+    pub rule: &'a str,
+}
+
+// One JSON object per line, either appended to a dedicated file or logged as
+// a warning, which goes to stderr.
+pub struct AuditLog {
+    file: Option<Mutex<AuditFile>>,
+}
+
+struct AuditFile {
+    path: PathBuf,
+    file: File,
+    // Where the last record ended. The file is opened for appending, so the
+    // server itself never overwrites a record; finding it shorter than this
+    // means someone else truncated it, which is reported.
+    end: u64,
+}
+
+impl AuditLog {
+    // The file is opened once at startup, before privileges are dropped, and
+    // created readable by its owner only.
+    pub fn open(path: Option<&Path>) -> io::Result<Self> {
+        let file = match path {
+            Some(path) => {
+                let mut options = OpenOptions::new();
+                options.create(true).append(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                let file = options.open(path)?;
+                let end = file.metadata()?.len();
+                Some(Mutex::new(AuditFile { path: path.to_path_buf(), file, end }))
+            }
+            None => None,
+        };
+        Ok(AuditLog { file })
+    }
+
+    pub fn record(&self, record: &Record) {
+        if AUDITED_STATUSES.contains(&record.status) {
+            self.write(record);
+        }
+    }
+
+    // A file that was sent. Every one is recorded, which would drown the
+    // normal log, so this needs an audit file.
+    pub fn record_access(&self, record: &Record) {
+        if self.file.is_some() {
+            self.write(record);
+        }
+    }
+
+    fn write(&self, record: &Record) {
+        match &self.file {
+            Some(file) => {
+                let line = serde_json::json!({
+                    "timestamp": Utc::now().to_rfc3339(),
+                    "remote_ip": record.remote_ip,
+                    "user": record.user,
+                    "method": record.method,
+                    "path": record.path,
+                    "status": record.status,
+                    "bytes": record.bytes,
+                    "rule": record.rule,
+                })
+                .to_string();
+                let mut file = file.lock().unwrap();
+                let len = file.file.metadata().map(|metadata| metadata.len()).unwrap_or(file.end);
+                if len < file.end {
+                    tracing::warn!("audit log {} was truncated from {} to {} bytes", file.path.display(), file.end, len);
+                }
+                // One write per record, so that records never interleave.
+                match file.file.write_all(format!("{}\n", line).as_bytes()) {
+                    Ok(()) => file.end = len + line.len() as u64 + 1,
+                    Err(e) => tracing::error!("Failed to write audit log: {}", e),
+                }
+            }
+            // Fields that are None are left out.
+            None => tracing::warn!(
+                kind = "audit",
+                remote_ip = record.remote_ip,
+                user = record.user,
+                method = record.method,
+                path = record.path,
+                status = record.status,
+                bytes = record.bytes,
+                rule = record.rule,
+            ),
+        }
+    }
+}
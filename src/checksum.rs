@@ -0,0 +1,125 @@
+// Digests of files for `/api/v1/hash`, so that a transfer can be checked
+// without downloading the file a second time. Hashing a large file takes a
+// while, so digests are cached by path, size, modification time and
+// algorithm, and a request for a digest that is already being computed waits
+// for it rather than reading the file again.
+use lru::LruCache;
+use sha2::Digest;
+use std::io::{self, Read};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::sync::OnceCell;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+}
+
+impl Algorithm {
+    // As `?algo=` names it.
+    pub fn from_query(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(Algorithm::Sha256),
+            "sha1" => Some(Algorithm::Sha1),
+            "md5" => Some(Algorithm::Md5),
+            "blake3" => Some(Algorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Md5 => "md5",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    algorithm: Algorithm,
+}
+
+pub struct Checksums {
+    // A cell is filled by the first request for its key; the others await
+    // it. One whose hashing failed is left empty for the next to try again.
+    digests: Mutex<LruCache<Key, Arc<OnceCell<String>>>>,
+}
+
+impl Checksums {
+    pub fn new(capacity: usize) -> Self {
+        Checksums { digests: Mutex::new(LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap())) }
+    }
+
+    // The digest of the file at `path`, in lowercase hex. Without a
+    // modification time to tell versions of the file apart, it is neither
+    // cached nor shared.
+    pub async fn digest(&self, path: &Path, metadata: &std::fs::Metadata, algorithm: Algorithm) -> io::Result<String> {
+        let Ok(modified) = metadata.modified() else {
+            return hash_file(path.to_path_buf(), algorithm).await;
+        };
+        let key = Key { path: path.to_path_buf(), size: metadata.len(), modified, algorithm };
+        let cell = self.digests.lock().unwrap().get_or_insert(key, Default::default).clone();
+        cell.get_or_try_init(|| hash_file(path.to_path_buf(), algorithm)).await.cloned()
+    }
+}
+
+async fn hash_file(path: PathBuf, algorithm: Algorithm) -> io::Result<String> {
+    tokio::task::spawn_blocking(move || hash(&path, algorithm)).await.map_err(io::Error::other)?
+}
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha1(sha1_smol::Sha1),
+    Md5(md5::Md5),
+    Blake3(Box<blake3::Hasher>),
+}
+
+// Reads the file in pieces, so that its size does not matter.
+fn hash(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = match algorithm {
+        Algorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+        Algorithm::Sha1 => Hasher::Sha1(sha1_smol::Sha1::new()),
+        Algorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+        Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+    };
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        match &mut hasher {
+            Hasher::Sha256(hasher) => hasher.update(&buffer[..read]),
+            Hasher::Sha1(hasher) => hasher.update(&buffer[..read]),
+            Hasher::Md5(hasher) => hasher.update(&buffer[..read]),
+            Hasher::Blake3(hasher) => {
+                hasher.update(&buffer[..read]);
+            }
+        }
+    }
+    let digest = match hasher {
+        Hasher::Sha256(hasher) => hex(&hasher.finalize()),
+        Hasher::Sha1(hasher) => hasher.digest().to_string(),
+        Hasher::Md5(hasher) => hex(&hasher.finalize()),
+        Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+    };
+    Ok(digest)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,84 @@
+// `Transfer-Encoding: chunked` for response bodies whose length is not known
+// when the head is sent: streamed listings and archives.
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncWrite;
+
+const LAST_CHUNK: &[u8] = b"0\r\n\r\n";
+
+// Chunked bodies are HTTP/1.1; an HTTP/1.0 client gets the body unframed and
+// its end is marked by closing the connection.
+pub fn accepted_by(request: &str) -> bool {
+    let version = request.lines().next().and_then(|line| line.rsplit(' ').next()).unwrap_or("");
+    version.starts_with("HTTP/") && version != "HTTP/1.0"
+}
+
+// `data` framed as one chunk. Empty data would read as the last chunk, so it
+// frames to nothing.
+pub fn chunk(data: &[u8]) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut framed = format!("{:x}\r\n", data.len()).into_bytes();
+    framed.extend_from_slice(data);
+    framed.extend_from_slice(b"\r\n");
+    framed
+}
+
+// Frames every write as a chunk. Shutting the writer down sends the last
+// chunk before shutting down `inner`; a body that ends any other way (an
+// error half way through an archive) is left unterminated, which tells the
+// client it is incomplete.
+pub struct ChunkedWriter<W> {
+    inner: W,
+    // Framed bytes `inner` has not taken yet.
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin> ChunkedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        ChunkedWriter { inner, pending: Vec::new(), finished: false }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for ChunkedWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        this.pending = chunk(buf);
+        // The chunk is accepted whole; whatever `inner` does not take now goes
+        // out before the next write, flush or shutdown.
+        let _ = this.poll_pending(cx)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        if !this.finished {
+            this.pending.extend_from_slice(LAST_CHUNK);
+            this.finished = true;
+            ready!(this.poll_pending(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
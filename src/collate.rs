@@ -0,0 +1,68 @@
+// Name comparison by the Unicode Collation Algorithm, with the CLDR root
+// order: letters with diacritics sort with their base letter (`ä` beside
+// `a`, not after `z`), case only decides between names that are otherwise
+// equal, and names in different scripts still compare consistently.
+//
+// Natural ordering splits names into runs of ASCII digits and of everything
+// else: digit runs compare by value, as `natural_cmp` does, the rest by
+// collation, and a digit run comes before anything else. Letting collation
+// compare a digit run with other text would make the order cyclic, as
+// collation knows digits of other scripts by their value too.
+use feruca::Collator;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+thread_local! {
+    // A collator keeps scratch buffers and needs `&mut` to compare, so each
+    // thread keeps one for every sort it runs rather than building one per
+    // comparison.
+    static COLLATOR: RefCell<Collator> = RefCell::new(Collator::default());
+}
+
+pub fn compare(a: &str, b: &str, natural: bool) -> Ordering {
+    COLLATOR.with_borrow_mut(|collator| {
+        if !natural {
+            return collator.collate(a, b);
+        }
+        let (mut a_runs, mut b_runs) = (runs(a), runs(b));
+        loop {
+            let ordering = match (a_runs.next(), b_runs.next()) {
+                (None, None) => return collator.collate(a, b),
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(x), Some(y)) => match (is_number(x), is_number(y)) {
+                    (true, true) => compare_numbers(x, y),
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => collator.collate(x, y),
+                },
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+    })
+}
+
+// `name` cut wherever it changes between ASCII digits and anything else.
+fn runs(name: &str) -> impl Iterator<Item = &str> {
+    let mut rest = name;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest.find(|c: char| c.is_ascii_digit() != first.is_ascii_digit()).unwrap_or(rest.len());
+        let (run, after) = rest.split_at(end);
+        rest = after;
+        Some(run)
+    })
+}
+
+fn is_number(run: &str) -> bool {
+    run.starts_with(|c: char| c.is_ascii_digit())
+}
+
+// Without leading zeros the longer run is the larger number, and runs of the
+// same length compare digit by digit.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
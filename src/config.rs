@@ -0,0 +1,1459 @@
+use crate::cors::Cors;
+use crate::metrics;
+use crate::timestamps::{self, Timestamps};
+use clap::Parser;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+// Configuration file read when `--config` is not given, if it exists.
+const DEFAULT_CONFIG_PATH: &str = "gredl.toml";
+
+// Environment variables named this prefix plus the upper-snake config key
+// (`GREDL_BIND`, `GREDL_FILE_CACHE_TTL`, ...) override the configuration file.
+const ENV_PREFIX: &str = "GREDL_";
+
+// Documented example configuration, printed by `--print-default-config`. Its
+// values are the ones `Config::default()` holds.
+pub const EXAMPLE_CONFIG: &str = include_str!("../gredl.example.toml");
+
+/// A small web file browser.
+///
+/// Settings come from the configuration file first, then from `GREDL_*`
+/// environment variables; flags given on the command line override both.
+#[derive(Parser, Clone)]
+#[command(version, about)]
+pub struct Cli {
+    /// Configuration file to read [default: ./gredl.toml, if present].
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Print a documented example configuration file and exit.
+    #[arg(long)]
+    pub print_default_config: bool,
+
+    /// Address to listen on, as `IP`, `IP:PORT` (`[::1]:8080` for IPv6) or
+    /// `unix:PATH`; repeat to listen on several [default: 127.0.0.1].
+    #[arg(long)]
+    pub bind: Vec<String>,
+
+    /// Port for bind addresses that do not name one [default: 8080].
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Directory to serve [default: the current directory].
+    #[arg(long)]
+    pub root: Option<PathBuf>,
+
+    /// URL path the server is mounted under behind a reverse proxy (e.g. /files) [default: none].
+    #[arg(long)]
+    pub base_url: Option<String>,
+
+    /// Serve DIR to requests for HOST, as `HOST=DIR` (repeatable); other hosts get --root.
+    #[arg(long = "vhost", value_parser = parse_mapping::<PathBuf>)]
+    pub vhosts: Vec<(String, PathBuf)>,
+
+    /// Answer requests for hosts not listed with --vhost with 421 instead of serving --root.
+    #[arg(long)]
+    pub strict_vhosts: bool,
+
+    /// Serve DIR under the URL prefix PREFIX instead of from the root, as `PREFIX=DIR` (repeatable).
+    #[arg(long = "alias", value_parser = parse_mapping::<PathBuf>)]
+    pub aliases: Vec<(String, PathBuf)>,
+
+    /// Content type for files with an extension, as `EXT=TYPE`, over the built-in one (repeatable).
+    #[arg(long = "mime", value_parser = parse_mapping::<String>)]
+    pub mime: Vec<(String, String)>,
+
+    /// Cache-Control of files with an extension, as `EXT=VALUE` (repeatable); other files get `no-cache`.
+    #[arg(long = "cache-policy", value_parser = parse_mapping::<String>)]
+    pub cache_policy: Vec<(String, String)>,
+
+    /// File served in place of a directory's listing; repeat to try several in order [default: index.html].
+    #[arg(long = "index")]
+    pub index_files: Vec<String>,
+
+    /// List directories even when they contain an index file.
+    #[arg(long)]
+    pub no_index: bool,
+
+    /// Answer requests for missing paths with this file (relative to the root) instead of 404, for single-page apps.
+    #[arg(long)]
+    pub spa_fallback: Option<PathBuf>,
+
+    /// Serve the site read-only over WebDAV under /_dav/, for mounting it as a network drive.
+    #[arg(long)]
+    pub webdav: bool,
+
+    /// Accept changes to the site: PUT of a directory, and WebDAV writes with --webdav. Read-only directories still refuse them.
+    #[arg(long)]
+    pub allow_write: bool,
+
+    /// List dotfiles by default; `?hidden=0` still hides them for one listing.
+    #[arg(long)]
+    pub show_hidden: bool,
+
+    /// Show permissions, owner and group in listings and file info by default (unix only); `?details=0` still hides them.
+    #[arg(long)]
+    pub show_permissions: bool,
+
+    /// Show modification times in listings as "3 hours ago" by default; `?times=absolute` still shows dates.
+    #[arg(long)]
+    pub relative_times: bool,
+
+    /// Show the total size of each subdirectory in listings by default; `?du=0` still leaves them out.
+    #[arg(long)]
+    pub recursive_sizes: bool,
+
+    /// Levels of directories a recursive size reads [default: 16].
+    #[arg(long)]
+    pub recursive_size_depth: Option<usize>,
+
+    /// Entries a recursive size looks at before giving up [default: 100000].
+    #[arg(long)]
+    pub recursive_size_entries: Option<usize>,
+
+    /// Levels of directories a search reads below the one it starts in [default: 16].
+    #[arg(long)]
+    pub search_max_depth: Option<usize>,
+
+    /// Matches a search lists before it stops [default: 1000].
+    #[arg(long)]
+    pub search_max_results: Option<usize>,
+
+    /// Matching lines a content search returns before it stops [default: 1000].
+    #[arg(long)]
+    pub grep_max_matches: Option<usize>,
+
+    /// Largest file, in bytes, a content search reads [default: 1 MiB].
+    #[arg(long)]
+    pub grep_max_file_size: Option<u64>,
+
+    /// Entries a tree from /api/v1/tree holds before it stops [default: 10000].
+    #[arg(long)]
+    pub tree_max_nodes: Option<usize>,
+
+    /// Leave out the README.md or README.txt shown beneath listings.
+    #[arg(long)]
+    pub hide_readme: bool,
+
+    /// Sort names in listings character by character, so `file10` comes before `file2`.
+    #[arg(long)]
+    pub lexicographic_sort: bool,
+
+    /// Sort names in listings with upper case before lower case, so `README` comes before `apple`.
+    #[arg(long)]
+    pub case_sensitive_sort: bool,
+
+    /// Sort directories in among the files in listings by default; `?dirs_first=1` still groups them.
+    #[arg(long)]
+    pub mix_dirs: bool,
+
+    /// Permissions for unix socket files, in octal (e.g. 0660) [default: from the umask].
+    #[arg(long, value_parser = parse_octal)]
+    pub unix_socket_mode: Option<u32>,
+
+    /// Log every request.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Log clients by the host name a reverse DNS lookup finds for them.
+    #[arg(long)]
+    pub resolve_hostnames: bool,
+
+    /// Time zone for dates in listings: "UTC", "local" or an IANA name like
+    /// "Europe/Berlin" [default: local].
+    #[arg(long)]
+    pub timezone: Option<String>,
+
+    /// strftime format for dates in listings [default: "%Y-%m-%d %H:%M:%S"].
+    #[arg(long)]
+    pub date_format: Option<String>,
+
+    /// Upper bounds in seconds of the request latency histogram's buckets,
+    /// comma-separated [default: 0.005,0.01,0.025,0.05,0.1,0.25,0.5,1,2.5,5,10].
+    #[arg(long, value_delimiter = ',')]
+    pub histogram_buckets: Vec<f64>,
+
+    /// Compare names in listings by code point or by Unicode collation, which sorts `ä` with `a`
+    /// [default: codepoint].
+    #[arg(long, value_enum)]
+    pub collation: Option<NameCollation>,
+
+    /// Show sizes in binary (KiB, MiB) or decimal (kB, MB) units
+    /// [default: binary].
+    #[arg(long, value_enum)]
+    pub size_units: Option<SizeUnits>,
+
+    /// Write log lines as plain text or as JSON objects [default: plain].
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Number of accept loops, each on its own SO_REUSEPORT listener [default: 1].
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub workers: Option<u64>,
+
+    /// User to switch to after binding (name or uid).
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Group to switch to after binding (name or gid).
+    #[arg(long)]
+    pub group: Option<String>,
+
+    /// Confine the process to the document root (Linux only).
+    #[arg(long)]
+    pub sandbox: bool,
+
+    /// Write the server's process id to this file and lock it against a second instance.
+    #[arg(long)]
+    pub pid_file: Option<PathBuf>,
+
+    /// Fork into the background once the listeners are bound (unix only).
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Largest accepted request body, in bytes [default: 100 MiB].
+    #[arg(long)]
+    pub max_body_size: Option<u64>,
+
+    /// Only serve files with these extensions, e.g. `.html,.css,.js` [default: any].
+    #[arg(long = "allow-ext", value_delimiter = ',')]
+    pub allowed_extensions: Vec<String>,
+
+    /// Never serve files with these extensions, e.g. `.env,.pem,.key`.
+    #[arg(long = "deny-ext", value_delimiter = ',')]
+    pub denied_extensions: Vec<String>,
+
+    /// Refuse to send files larger than this many bytes [default: no limit].
+    #[arg(long)]
+    pub max_file_size: Option<u64>,
+
+    /// Seconds a client may take to send the request head [default: 10].
+    #[arg(long)]
+    pub header_timeout: Option<u64>,
+
+    /// Seconds after which a connection is closed regardless of progress [default: 3600].
+    #[arg(long)]
+    pub request_timeout: Option<u64>,
+
+    /// Seconds in-flight requests may take to finish after SIGINT/SIGTERM [default: 30].
+    #[arg(long, alias = "shutdown-timeout")]
+    pub shutdown_grace: Option<u64>,
+
+    /// Number of small files kept in the in-memory file cache [default: 256].
+    #[arg(long)]
+    pub file_cache_entries: Option<usize>,
+
+    /// Largest file, in bytes, that is kept in the file cache [default: 1 MiB].
+    #[arg(long)]
+    pub file_cache_max_size: Option<u64>,
+
+    /// Seconds a cached file is served before it is re-read [default: 30].
+    #[arg(long)]
+    pub file_cache_ttl: Option<u64>,
+
+    /// Seconds a cached directory listing is served before it is re-read [default: 5].
+    #[arg(long)]
+    pub listing_cache_ttl: Option<u64>,
+
+    /// Write audit records to this file instead of stderr, including one for every file sent.
+    #[arg(long, alias = "audit-log")]
+    pub audit_log_path: Option<PathBuf>,
+
+    /// Require HTTP Basic authentication, or a session from the /_login form, against the accounts in this TOML file.
+    #[arg(long)]
+    pub users_file: Option<PathBuf>,
+
+    /// Export request traces over OTLP/gRPC to this collector, e.g. `http://localhost:4317`.
+    #[arg(long)]
+    pub otel_endpoint: Option<String>,
+
+    /// Origin allowed to make cross-origin requests, or `*` (repeatable).
+    #[arg(long = "cors-origin")]
+    pub cors_origins: Vec<String>,
+
+    /// Allow credentialed cross-origin requests (explicit origins only).
+    #[arg(long)]
+    pub cors_credentials: bool,
+
+    /// Seconds browsers may cache a CORS preflight response [default: 600].
+    #[arg(long)]
+    pub cors_max_age: Option<u64>,
+
+    /// Leave out the X-Content-Type-Options, X-Frame-Options and Referrer-Policy headers.
+    #[arg(long)]
+    pub no_security_headers: bool,
+
+    /// Content-Security-Policy of the generated HTML pages; empty to send none [default: default-src 'self'; style-src 'unsafe-inline'].
+    #[arg(long)]
+    pub csp: Option<String>,
+
+    /// Header added to every response, as `NAME=VALUE` (repeatable).
+    #[arg(long = "header", value_parser = parse_mapping::<String>)]
+    pub headers: Vec<(String, String)>,
+}
+
+// Settings the server runs with. The configuration file and the environment
+// are deserialized straight into this struct, keys named like the fields;
+// command line flags are applied on top and the result is validated by
+// `Config::load`, and again by `Config::reload` on SIGHUP.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    #[serde(deserialize_with = "one_or_many")]
+    pub bind: Vec<String>,
+    pub port: u16,
+    #[serde(deserialize_with = "octal", skip_serializing)]
+    pub unix_socket_mode: Option<u32>,
+    pub root: PathBuf,
+    pub base_url: String,
+    // Keyed by host name, lowercased by validation.
+    pub vhosts: BTreeMap<String, VirtualHost>,
+    pub strict_vhosts: bool,
+    // URL prefixes (normalized by validation) mapped to directories.
+    pub aliases: BTreeMap<String, PathBuf>,
+    // Extension (lowercased and without the dot after validation) to content
+    // type, over the built-in table.
+    pub mime: BTreeMap<String, String>,
+    // Extension, normalized like those of `mime`, to the Cache-Control of
+    // files that have it.
+    pub cache_policy: BTreeMap<String, String>,
+    pub index_files: Vec<String>,
+    pub serve_index: bool,
+    // Relative to the root of the host being served.
+    pub spa_fallback: Option<PathBuf>,
+    // Whether the site is also served over WebDAV, under DAV_PATH.
+    pub webdav: bool,
+    // Whether requests may change the site at all; where they may is then up
+    // to `read_only`. Off, the server only ever reads.
+    pub allow_write: bool,
+    // Whether listings include dotfiles when the request does not say.
+    pub show_hidden: bool,
+    // Whether listings and file info pages show `ls -l` style permissions,
+    // owner and group when the request does not say.
+    pub show_permissions: bool,
+    // Whether listings give modification times as how long ago they were.
+    pub relative_times: bool,
+    // Whether listings show the directory's README beneath them.
+    pub show_readme: bool,
+    // Whether listings give subdirectories' total sizes, walking them within
+    // the two limits.
+    pub recursive_sizes: bool,
+    pub recursive_size_depth: usize,
+    pub recursive_size_entries: usize,
+    pub search_max_depth: usize,
+    pub search_max_results: usize,
+    // Limits of `/api/v1/grep`, which also goes no deeper than
+    // `search_max_depth`.
+    pub grep_max_matches: usize,
+    pub grep_max_file_size: u64,
+    // Entries in a tree from `/api/v1/tree`, which reads no more than
+    // `search_max_depth` levels below where it starts either.
+    pub tree_max_nodes: usize,
+    pub timezone: String,
+    pub date_format: String,
+    pub size_units: SizeUnits,
+    // Compare digit runs in names by value when sorting listings.
+    pub natural_sort: bool,
+    // Compare names in listings after Unicode case folding.
+    pub case_insensitive_sort: bool,
+    pub collation: NameCollation,
+    // Whether listings put directories before files when the request does
+    // not say.
+    pub group_dirs_first: bool,
+    pub verbose: bool,
+    // Whether access lines name TCP clients by the host name their address
+    // resolves to, when it resolves within dns::TIMEOUT.
+    pub resolve_hostnames: bool,
+    pub log_format: LogFormat,
+    // Upper bounds of the latency histogram on the metrics endpoint, in
+    // seconds and increasing after validation.
+    pub histogram_buckets: Vec<f64>,
+    pub workers: usize,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub sandbox: bool,
+    pub pid_file: Option<PathBuf>,
+    pub daemon: bool,
+    pub max_body_size: u64,
+    pub max_file_size: Option<u64>,
+    // Lowercased and without the leading dot after validation. An empty
+    // allow list allows every extension.
+    pub allowed_extensions: Vec<String>,
+    pub denied_extensions: Vec<String>,
+    #[serde(deserialize_with = "seconds", serialize_with = "as_seconds")]
+    pub header_timeout: Duration,
+    #[serde(deserialize_with = "seconds", serialize_with = "as_seconds")]
+    pub request_timeout: Duration,
+    #[serde(deserialize_with = "seconds", serialize_with = "as_seconds")]
+    pub shutdown_grace: Duration,
+    pub file_cache_entries: usize,
+    pub file_cache_max_size: u64,
+    #[serde(deserialize_with = "seconds", serialize_with = "as_seconds")]
+    pub file_cache_ttl: Duration,
+    #[serde(deserialize_with = "seconds", serialize_with = "as_seconds")]
+    pub listing_cache_ttl: Duration,
+    pub audit_log_path: Option<PathBuf>,
+    pub users_file: Option<PathBuf>,
+    pub otel_endpoint: Option<String>,
+    pub cors_origins: Vec<String>,
+    pub cors_credentials: bool,
+    pub cors_max_age: u64,
+    pub security_headers: bool,
+    pub csp: String,
+    // Added to every response, in place of a security header of the same
+    // name; see `add_server_headers`.
+    pub headers: BTreeMap<String, String>,
+    // Headers for URL paths under a prefix (normalized by validation), over
+    // `headers`; see `headers_for`.
+    pub path_headers: Vec<PathHeaders>,
+    // Where each setting that is not a default came from, for `--verbose`.
+    #[serde(skip)]
+    pub sources: Vec<(String, String)>,
+    // `bind` and `port` resolved to listen addresses by validation.
+    #[serde(skip)]
+    pub addrs: Vec<BindAddr>,
+    // Built from the `cors_*` settings by validation.
+    #[serde(skip)]
+    pub cors: Cors,
+    // Built from `timezone` and `date_format` by validation.
+    #[serde(skip)]
+    pub timestamps: Timestamps,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: vec![Ipv4Addr::LOCALHOST.to_string()],
+            port: 8080,
+            unix_socket_mode: None,
+            root: PathBuf::from("."),
+            base_url: String::new(),
+            vhosts: BTreeMap::new(),
+            strict_vhosts: false,
+            aliases: BTreeMap::new(),
+            mime: BTreeMap::new(),
+            cache_policy: BTreeMap::new(),
+            index_files: vec!["index.html".to_string()],
+            serve_index: true,
+            spa_fallback: None,
+            webdav: false,
+            allow_write: false,
+            show_hidden: false,
+            show_permissions: false,
+            relative_times: false,
+            show_readme: true,
+            recursive_sizes: false,
+            recursive_size_depth: 16,
+            recursive_size_entries: 100_000,
+            search_max_depth: 16,
+            search_max_results: 1000,
+            grep_max_matches: 1000,
+            grep_max_file_size: 1024 * 1024,
+            tree_max_nodes: 10_000,
+            timezone: "local".to_string(),
+            date_format: timestamps::DEFAULT_FORMAT.to_string(),
+            size_units: SizeUnits::Binary,
+            natural_sort: true,
+            case_insensitive_sort: true,
+            collation: NameCollation::Codepoint,
+            group_dirs_first: true,
+            verbose: false,
+            resolve_hostnames: false,
+            log_format: LogFormat::Plain,
+            histogram_buckets: metrics::DEFAULT_BUCKETS.to_vec(),
+            workers: 1,
+            user: None,
+            group: None,
+            sandbox: false,
+            pid_file: None,
+            daemon: false,
+            max_body_size: 100 * 1024 * 1024,
+            max_file_size: None,
+            allowed_extensions: Vec::new(),
+            denied_extensions: Vec::new(),
+            header_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(3600),
+            shutdown_grace: Duration::from_secs(30),
+            file_cache_entries: 256,
+            file_cache_max_size: 1024 * 1024,
+            file_cache_ttl: Duration::from_secs(30),
+            listing_cache_ttl: Duration::from_secs(5),
+            audit_log_path: None,
+            users_file: None,
+            otel_endpoint: None,
+            cors_origins: Vec::new(),
+            cors_credentials: false,
+            cors_max_age: 600,
+            security_headers: true,
+            csp: "default-src 'self'; style-src 'unsafe-inline'".to_string(),
+            headers: BTreeMap::new(),
+            path_headers: Vec::new(),
+            sources: Vec::new(),
+            addrs: Vec::new(),
+            cors: Cors::default(),
+            timestamps: Timestamps::default(),
+        }
+    }
+}
+
+// Durations are written as a number of seconds in the configuration file.
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_secs)
+}
+
+// `bind` may be a single address or a list of them.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+// File modes are written in octal, as a string ("0660") or a TOML octal
+// integer (0o660).
+fn octal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Mode {
+        Text(String),
+        Number(u32),
+    }
+    match Mode::deserialize(deserializer)? {
+        Mode::Text(text) => parse_octal(&text).map(Some).map_err(serde::de::Error::custom),
+        Mode::Number(mode) => Ok(Some(mode)),
+    }
+}
+
+fn parse_octal(text: &str) -> Result<u32, String> {
+    u32::from_str_radix(text.trim(), 8)
+        .ok()
+        .filter(|&mode| mode <= 0o7777)
+        .ok_or_else(|| format!("`{}` is not an octal file mode", text))
+}
+
+// Headers that describe how a response's body is sent, which only the server
+// can get right, so neither `headers` nor `path_headers` can add them.
+const FRAMING_HEADERS: &[&str] =
+    &["Connection", "Content-Encoding", "Content-Length", "Content-Range", "Content-Type", "Transfer-Encoding", "Upgrade"];
+
+// A key of `mime` or `cache_policy` as the tables are looked up by: without
+// a leading dot, in lower case. None when it is not an extension at all.
+fn extension_key(extension: &str) -> Option<String> {
+    let normalized = extension.trim_start_matches('.').to_lowercase();
+    (!normalized.is_empty() && !normalized.contains(['.', '/', '\\'])).then_some(normalized)
+}
+
+// `headers`, or those of a `path_headers` entry, checked and with their
+// values trimmed. `context` names them in errors.
+fn response_headers(headers: BTreeMap<String, String>, context: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut checked = BTreeMap::new();
+    for (name, value) in headers {
+        if name.is_empty() || !name.bytes().all(crate::request::is_token_char) {
+            return Err(format!("{}: `{}` is not a header name", context, name.escape_debug()));
+        }
+        if FRAMING_HEADERS.iter().any(|framing| framing.eq_ignore_ascii_case(&name)) {
+            return Err(format!("{}: `{}` is set by the server", context, name));
+        }
+        if checked.keys().any(|other: &String| other.eq_ignore_ascii_case(&name)) {
+            return Err(format!("{}: `{}` is given twice", context, name));
+        }
+        let value = value.trim().to_string();
+        if value.chars().any(char::is_control) {
+            return Err(format!("{}: the value of `{}` contains control characters", context, name));
+        }
+        checked.insert(name, value);
+    }
+    Ok(checked)
+}
+
+// `NAME=VALUE`, as taken by --vhost, --alias, --mime and --header.
+fn parse_mapping<T: for<'a> From<&'a str>>(text: &str) -> Result<(String, T), String> {
+    match text.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() && !value.is_empty() => Ok((name.trim().to_string(), T::from(value))),
+        _ => Err(format!("`{}` is not NAME=VALUE", text)),
+    }
+}
+
+fn as_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.as_secs().serialize(serializer)
+}
+
+impl Config {
+    // Layers the configuration file, the environment and the command line
+    // over the defaults, in that order.
+    pub fn load(cli: Cli) -> Result<Self, String> {
+        let mut config = Config::layer(cli, std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Loads the configuration again for a running server. Only settings that
+    // take effect per request can change this way; the rest were used to bind
+    // sockets, size caches or drop privileges at startup, so a different value
+    // for them is reported and the running one kept. Any error leaves the
+    // caller with `current`. Under `--sandbox` the configuration file is
+    // usually out of reach, which makes every reload fail that way.
+    pub fn reload(cli: &Cli, current: &Config) -> Result<Self, String> {
+        const RELOADABLE: &[&str] = &[
+            "index_files",
+            "serve_index",
+            "spa_fallback",
+            "show_hidden",
+            "show_permissions",
+            "relative_times",
+            "show_readme",
+            "recursive_sizes",
+            "recursive_size_depth",
+            "recursive_size_entries",
+            "search_max_depth",
+            "search_max_results",
+            "grep_max_matches",
+            "grep_max_file_size",
+            "tree_max_nodes",
+            "timezone",
+            "date_format",
+            "size_units",
+            "verbose",
+            "max_body_size",
+            "max_file_size",
+            "allowed_extensions",
+            "denied_extensions",
+            "mime",
+            "cache_policy",
+            "header_timeout",
+            "request_timeout",
+            "shutdown_grace",
+            "cors_origins",
+            "cors_credentials",
+            "cors_max_age",
+            "users_file",
+        ];
+
+        let mut loaded = Config::layer(cli.clone(), std::env::vars())?;
+        // The running root is canonical (or `/` inside a chroot), so compare
+        // against what the new one resolves to rather than how it is spelled.
+        if loaded.root.canonicalize().ok().as_deref() != Some(current.root.as_path()) {
+            tracing::warn!("reload: `root` cannot change without a restart, keeping {}", current.root.display());
+        }
+        loaded.root = current.root.clone();
+        loaded.validate()?;
+
+        let table = |config: &Config| match toml::Value::try_from(config) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => toml::Table::new(),
+        };
+        let (old, new) = (table(current), table(&loaded));
+        for (key, value) in &old {
+            if key != "root" && !RELOADABLE.contains(&key.as_str()) && new.get(key) != Some(value) {
+                tracing::warn!("reload: `{}` cannot change without a restart, keeping {}", key, value);
+            }
+        }
+        if loaded.unix_socket_mode != current.unix_socket_mode {
+            tracing::warn!("reload: `unix_socket_mode` cannot change without a restart");
+        }
+
+        let mut config = current.clone();
+        config.index_files = loaded.index_files;
+        config.serve_index = loaded.serve_index;
+        config.spa_fallback = loaded.spa_fallback;
+        config.show_hidden = loaded.show_hidden;
+        config.show_permissions = loaded.show_permissions;
+        config.relative_times = loaded.relative_times;
+        config.show_readme = loaded.show_readme;
+        config.recursive_sizes = loaded.recursive_sizes;
+        config.recursive_size_depth = loaded.recursive_size_depth;
+        config.recursive_size_entries = loaded.recursive_size_entries;
+        config.search_max_depth = loaded.search_max_depth;
+        config.search_max_results = loaded.search_max_results;
+        config.grep_max_matches = loaded.grep_max_matches;
+        config.grep_max_file_size = loaded.grep_max_file_size;
+        config.tree_max_nodes = loaded.tree_max_nodes;
+        config.timezone = loaded.timezone;
+        config.date_format = loaded.date_format;
+        config.timestamps = loaded.timestamps;
+        config.size_units = loaded.size_units;
+        config.verbose = loaded.verbose;
+        config.max_body_size = loaded.max_body_size;
+        config.max_file_size = loaded.max_file_size;
+        config.allowed_extensions = loaded.allowed_extensions;
+        config.denied_extensions = loaded.denied_extensions;
+        config.mime = loaded.mime;
+        config.cache_policy = loaded.cache_policy;
+        config.header_timeout = loaded.header_timeout;
+        config.request_timeout = loaded.request_timeout;
+        config.shutdown_grace = loaded.shutdown_grace;
+        config.cors_origins = loaded.cors_origins;
+        config.cors_credentials = loaded.cors_credentials;
+        config.cors_max_age = loaded.cors_max_age;
+        config.cors = loaded.cors;
+        config.users_file = loaded.users_file;
+        config.sources = loaded.sources;
+        Ok(config)
+    }
+
+    // The configuration file, the environment variables `vars` and the
+    // command line layered over the defaults, not yet validated.
+    fn layer(cli: Cli, vars: impl Iterator<Item = (String, String)>) -> Result<Self, String> {
+        let file = match &cli.config {
+            Some(path) => Some(path.clone()),
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => Some(PathBuf::from(DEFAULT_CONFIG_PATH)),
+            None => None,
+        };
+        let mut settings = toml::Table::new();
+        let mut sources = Vec::new();
+        if let Some(path) = &file {
+            for key in Config::read_file(path, &mut settings)? {
+                sources.push((key, path.display().to_string()));
+            }
+        }
+        for (key, variable) in Config::read_env(vars, &mut settings)? {
+            sources.push((key, variable));
+        }
+
+        let mut unknown = Vec::new();
+        let mut config: Config = serde_ignored::deserialize(toml::Value::Table(settings), |key| {
+            let key = key.to_string();
+            match sources.iter().rev().find(|(name, _)| *name == key) {
+                Some((_, source)) if source.starts_with(ENV_PREFIX) => {
+                    tracing::warn!("{}: no setting is called `{}`", source, key);
+                }
+                _ => tracing::warn!("{}: unknown key `{}`", file.as_deref().unwrap_or(Path::new("")).display(), key),
+            }
+            unknown.push(key);
+        })
+        .map_err(|e: toml::de::Error| e.to_string())?;
+        sources.retain(|(key, _)| !unknown.contains(key));
+        config.sources = sources;
+        config.apply_cli(cli);
+        Ok(config)
+    }
+
+    // Merges the file's keys into `settings` and returns their names. The file
+    // is also deserialized on its own first, so that type errors still name the
+    // line and column they occur on.
+    fn read_file(path: &Path, settings: &mut toml::Table) -> Result<Vec<String>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        toml::from_str::<Config>(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let table: toml::Table = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let keys = table.keys().cloned().collect();
+        settings.extend(table);
+        Ok(keys)
+    }
+
+    // Merges `GREDL_*` variables into `settings`, returning (key, variable)
+    // pairs. The variable name maps to a key by dropping the prefix and
+    // lowercasing, and its text is converted to the type the default value of
+    // that key has: numbers, `true`/`false`, or comma-separated lists (of
+    // numbers where the default list holds numbers). Keys without a default
+    // (optional settings) are taken as strings.
+    fn read_env(vars: impl Iterator<Item = (String, String)>, settings: &mut toml::Table) -> Result<Vec<(String, String)>, String> {
+        let defaults = match toml::Value::try_from(Config::default()) {
+            Ok(toml::Value::Table(defaults)) => defaults,
+            _ => toml::Table::new(),
+        };
+
+        let mut keys = Vec::new();
+        for (variable, text) in vars {
+            let Some(key) = variable.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let key = key.to_ascii_lowercase();
+            let value = match defaults.get(&key) {
+                Some(toml::Value::Integer(_)) => text
+                    .trim()
+                    .parse()
+                    .map(toml::Value::Integer)
+                    .map_err(|_| format!("{}: expected a number, found `{}`", variable, text))?,
+                Some(toml::Value::Boolean(_)) => match text.trim().to_ascii_lowercase().as_str() {
+                    "true" | "1" | "yes" | "on" => toml::Value::Boolean(true),
+                    "false" | "0" | "no" | "off" | "" => toml::Value::Boolean(false),
+                    _ => return Err(format!("{}: expected true or false, found `{}`", variable, text)),
+                },
+                Some(toml::Value::Array(default)) => toml::Value::Array(
+                    text.split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| match default.first() {
+                            Some(toml::Value::Float(_)) => item
+                                .parse()
+                                .map(toml::Value::Float)
+                                .map_err(|_| format!("{}: expected numbers, found `{}`", variable, item)),
+                            _ => Ok(toml::Value::String(item.to_string())),
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                _ => toml::Value::String(text),
+            };
+            // Checked on its own so that a bad value names its variable.
+            let single = toml::Table::from_iter([(key.clone(), value.clone())]);
+            Config::deserialize(toml::Value::Table(single)).map_err(|e| format!("{}: {}", variable, e.message()))?;
+            settings.insert(key.clone(), value);
+            keys.push((key, variable));
+        }
+        Ok(keys)
+    }
+
+    fn apply_cli(&mut self, cli: Cli) {
+        if !cli.bind.is_empty() {
+            self.bind = cli.bind;
+            self.set_by_command_line("bind");
+        }
+        if let Some(port) = cli.port {
+            self.port = port;
+            self.set_by_command_line("port");
+        }
+        if cli.unix_socket_mode.is_some() {
+            self.unix_socket_mode = cli.unix_socket_mode;
+            self.set_by_command_line("unix_socket_mode");
+        }
+        if let Some(root) = cli.root {
+            self.root = root;
+            self.set_by_command_line("root");
+        }
+        if let Some(base_url) = cli.base_url {
+            self.base_url = base_url;
+            self.set_by_command_line("base_url");
+        }
+        // Hosts given on the command line replace the file's table.
+        if !cli.vhosts.is_empty() {
+            self.vhosts = cli
+                .vhosts
+                .into_iter()
+                .map(|(host, root)| (host, VirtualHost { root, read_only: false }))
+                .collect();
+            self.set_by_command_line("vhosts");
+        }
+        if cli.strict_vhosts {
+            self.strict_vhosts = true;
+            self.set_by_command_line("strict_vhosts");
+        }
+        // Aliases given on the command line replace the file's table.
+        if !cli.aliases.is_empty() {
+            self.aliases = cli.aliases.into_iter().collect();
+            self.set_by_command_line("aliases");
+        }
+        // As do content types.
+        if !cli.mime.is_empty() {
+            self.mime = cli.mime.into_iter().collect();
+            self.set_by_command_line("mime");
+        }
+        if !cli.cache_policy.is_empty() {
+            self.cache_policy = cli.cache_policy.into_iter().collect();
+            self.set_by_command_line("cache_policy");
+        }
+        if !cli.index_files.is_empty() {
+            self.index_files = cli.index_files;
+            self.set_by_command_line("index_files");
+        }
+        if cli.no_index {
+            self.serve_index = false;
+            self.set_by_command_line("serve_index");
+        }
+        if cli.spa_fallback.is_some() {
+            self.spa_fallback = cli.spa_fallback;
+            self.set_by_command_line("spa_fallback");
+        }
+        if cli.webdav {
+            self.webdav = true;
+            self.set_by_command_line("webdav");
+        }
+        if cli.allow_write {
+            self.allow_write = true;
+            self.set_by_command_line("allow_write");
+        }
+        if cli.show_hidden {
+            self.show_hidden = true;
+            self.set_by_command_line("show_hidden");
+        }
+        if cli.show_permissions {
+            self.show_permissions = true;
+            self.set_by_command_line("show_permissions");
+        }
+        if cli.relative_times {
+            self.relative_times = true;
+            self.set_by_command_line("relative_times");
+        }
+        if cli.hide_readme {
+            self.show_readme = false;
+            self.set_by_command_line("show_readme");
+        }
+        if cli.recursive_sizes {
+            self.recursive_sizes = true;
+            self.set_by_command_line("recursive_sizes");
+        }
+        if let Some(recursive_size_depth) = cli.recursive_size_depth {
+            self.recursive_size_depth = recursive_size_depth;
+            self.set_by_command_line("recursive_size_depth");
+        }
+        if let Some(recursive_size_entries) = cli.recursive_size_entries {
+            self.recursive_size_entries = recursive_size_entries;
+            self.set_by_command_line("recursive_size_entries");
+        }
+        if let Some(search_max_depth) = cli.search_max_depth {
+            self.search_max_depth = search_max_depth;
+            self.set_by_command_line("search_max_depth");
+        }
+        if let Some(search_max_results) = cli.search_max_results {
+            self.search_max_results = search_max_results;
+            self.set_by_command_line("search_max_results");
+        }
+        if let Some(grep_max_matches) = cli.grep_max_matches {
+            self.grep_max_matches = grep_max_matches;
+            self.set_by_command_line("grep_max_matches");
+        }
+        if let Some(grep_max_file_size) = cli.grep_max_file_size {
+            self.grep_max_file_size = grep_max_file_size;
+            self.set_by_command_line("grep_max_file_size");
+        }
+        if let Some(tree_max_nodes) = cli.tree_max_nodes {
+            self.tree_max_nodes = tree_max_nodes;
+            self.set_by_command_line("tree_max_nodes");
+        }
+        if cli.lexicographic_sort {
+            self.natural_sort = false;
+            self.set_by_command_line("natural_sort");
+        }
+        if cli.case_sensitive_sort {
+            self.case_insensitive_sort = false;
+            self.set_by_command_line("case_insensitive_sort");
+        }
+        if let Some(collation) = cli.collation {
+            self.collation = collation;
+            self.set_by_command_line("collation");
+        }
+        if cli.mix_dirs {
+            self.group_dirs_first = false;
+            self.set_by_command_line("group_dirs_first");
+        }
+        if cli.verbose {
+            self.verbose = true;
+            self.set_by_command_line("verbose");
+        }
+        if cli.resolve_hostnames {
+            self.resolve_hostnames = true;
+            self.set_by_command_line("resolve_hostnames");
+        }
+        if let Some(timezone) = cli.timezone {
+            self.timezone = timezone;
+            self.set_by_command_line("timezone");
+        }
+        if let Some(date_format) = cli.date_format {
+            self.date_format = date_format;
+            self.set_by_command_line("date_format");
+        }
+        if let Some(size_units) = cli.size_units {
+            self.size_units = size_units;
+            self.set_by_command_line("size_units");
+        }
+        if !cli.histogram_buckets.is_empty() {
+            self.histogram_buckets = cli.histogram_buckets;
+            self.set_by_command_line("histogram_buckets");
+        }
+        if let Some(log_format) = cli.log_format {
+            self.log_format = log_format;
+            self.set_by_command_line("log_format");
+        }
+        if let Some(workers) = cli.workers {
+            self.workers = workers as usize;
+            self.set_by_command_line("workers");
+        }
+        if cli.user.is_some() {
+            self.user = cli.user;
+            self.set_by_command_line("user");
+        }
+        if cli.group.is_some() {
+            self.group = cli.group;
+            self.set_by_command_line("group");
+        }
+        if cli.sandbox {
+            self.sandbox = true;
+            self.set_by_command_line("sandbox");
+        }
+        if cli.pid_file.is_some() {
+            self.pid_file = cli.pid_file;
+            self.set_by_command_line("pid_file");
+        }
+        if cli.daemon {
+            self.daemon = true;
+            self.set_by_command_line("daemon");
+        }
+        if let Some(max_body_size) = cli.max_body_size {
+            self.max_body_size = max_body_size;
+            self.set_by_command_line("max_body_size");
+        }
+        if cli.max_file_size.is_some() {
+            self.max_file_size = cli.max_file_size;
+            self.set_by_command_line("max_file_size");
+        }
+        if !cli.allowed_extensions.is_empty() {
+            self.allowed_extensions = cli.allowed_extensions;
+            self.set_by_command_line("allowed_extensions");
+        }
+        if !cli.denied_extensions.is_empty() {
+            self.denied_extensions = cli.denied_extensions;
+            self.set_by_command_line("denied_extensions");
+        }
+        if let Some(header_timeout) = cli.header_timeout {
+            self.header_timeout = Duration::from_secs(header_timeout);
+            self.set_by_command_line("header_timeout");
+        }
+        if let Some(request_timeout) = cli.request_timeout {
+            self.request_timeout = Duration::from_secs(request_timeout);
+            self.set_by_command_line("request_timeout");
+        }
+        if let Some(shutdown_grace) = cli.shutdown_grace {
+            self.shutdown_grace = Duration::from_secs(shutdown_grace);
+            self.set_by_command_line("shutdown_grace");
+        }
+        if let Some(file_cache_entries) = cli.file_cache_entries {
+            self.file_cache_entries = file_cache_entries;
+            self.set_by_command_line("file_cache_entries");
+        }
+        if let Some(file_cache_max_size) = cli.file_cache_max_size {
+            self.file_cache_max_size = file_cache_max_size;
+            self.set_by_command_line("file_cache_max_size");
+        }
+        if let Some(file_cache_ttl) = cli.file_cache_ttl {
+            self.file_cache_ttl = Duration::from_secs(file_cache_ttl);
+            self.set_by_command_line("file_cache_ttl");
+        }
+        if let Some(listing_cache_ttl) = cli.listing_cache_ttl {
+            self.listing_cache_ttl = Duration::from_secs(listing_cache_ttl);
+            self.set_by_command_line("listing_cache_ttl");
+        }
+        if cli.audit_log_path.is_some() {
+            self.audit_log_path = cli.audit_log_path;
+            self.set_by_command_line("audit_log_path");
+        }
+        if cli.users_file.is_some() {
+            self.users_file = cli.users_file;
+            self.set_by_command_line("users_file");
+        }
+        if cli.otel_endpoint.is_some() {
+            self.otel_endpoint = cli.otel_endpoint;
+            self.set_by_command_line("otel_endpoint");
+        }
+        // Origins given on the command line replace the file's list rather
+        // than extending it.
+        if !cli.cors_origins.is_empty() {
+            self.cors_origins = cli.cors_origins;
+            self.set_by_command_line("cors_origins");
+        }
+        if cli.cors_credentials {
+            self.cors_credentials = true;
+            self.set_by_command_line("cors_credentials");
+        }
+        if let Some(cors_max_age) = cli.cors_max_age {
+            self.cors_max_age = cors_max_age;
+            self.set_by_command_line("cors_max_age");
+        }
+        if cli.no_security_headers {
+            self.security_headers = false;
+            self.set_by_command_line("security_headers");
+        }
+        if let Some(csp) = cli.csp {
+            self.csp = csp;
+            self.set_by_command_line("csp");
+        }
+        // Like aliases, these replace the file's table.
+        if !cli.headers.is_empty() {
+            self.headers = cli.headers.into_iter().collect();
+            self.set_by_command_line("headers");
+        }
+    }
+
+    // Whether `key` was given by any source rather than left at its default.
+    pub fn is_set(&self, key: &str) -> bool {
+        self.sources.iter().any(|(name, _)| name == key)
+    }
+
+    fn set_by_command_line(&mut self, key: &str) {
+        self.sources.push((key.to_string(), "command line".to_string()));
+    }
+
+    // One line per setting that is not a default, naming where it came from.
+    // A later source overrides an earlier one, so only the last is shown.
+    pub fn describe_sources(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for (index, (key, source)) in self.sources.iter().enumerate() {
+            if !self.sources[index + 1..].iter().any(|(later, _)| later == key) {
+                lines.push(format!("{} from {}", key, source));
+            }
+        }
+        lines
+    }
+
+    // Where a request's files come from, given its `Host` header: the
+    // matching virtual host, or the main site. None means the host is not
+    // served here at all, which only happens with `strict_vhosts`.
+    pub fn site(&self, host: Option<&str>) -> Option<Site<'_>> {
+        match host.and_then(|host| self.vhosts.get(&host_name(host))) {
+            Some(vhost) => Some(Site { config: self, root: &vhost.root, read_only: vhost.read_only, aliases: &self.aliases }),
+            None if self.strict_vhosts => None,
+            None => Some(self.main_site()),
+        }
+    }
+
+    pub fn main_site(&self) -> Site<'_> {
+        Site { config: self, root: &self.root, read_only: false, aliases: &self.aliases }
+    }
+
+    // The configured headers of a response to `url_path`: those of the
+    // longest `path_headers` prefix it is under, then the global ones that
+    // do not share a name with them.
+    pub fn headers_for(&self, url_path: &Path) -> Vec<(&str, &str)> {
+        let mut headers: Vec<(&str, &str)> = self
+            .path_headers
+            .iter()
+            .filter(|entry| url_path.starts_with(&entry.path_prefix))
+            .max_by_key(|entry| entry.path_prefix.len())
+            .into_iter()
+            .flat_map(|entry| &entry.headers)
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        for (name, value) in &self.headers {
+            if !headers.iter().any(|(other, _)| other.eq_ignore_ascii_case(name)) {
+                headers.push((name, value));
+            }
+        }
+        headers
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        if self.workers == 0 {
+            return Err("workers must be at least 1".to_string());
+        }
+        if self.bind.is_empty() {
+            return Err("at least one bind address is required".to_string());
+        }
+        self.addrs = self
+            .bind
+            .iter()
+            .map(|bind| parse_bind(bind, self.port).ok_or_else(|| format!("invalid bind address `{}`", bind)))
+            .collect::<Result<_, _>>()?;
+
+        // `files`, `/files` and `/files/` all mean `/files`; `/` means none.
+        let base_url = self.base_url.trim().trim_matches('/');
+        if base_url.contains(['?', '#']) {
+            return Err(format!("base_url must be a plain path, not `{}`", self.base_url));
+        }
+        self.base_url = if base_url.is_empty() { String::new() } else { format!("/{}", base_url) };
+
+        self.root = canonical_root(&self.root)?;
+
+        if self.sandbox && !self.vhosts.is_empty() {
+            return Err("sandbox confines the server to root, so it cannot serve vhosts".to_string());
+        }
+        if self.sandbox && !self.aliases.is_empty() {
+            return Err("sandbox confines the server to root, so it cannot serve aliases".to_string());
+        }
+        let aliases = std::mem::take(&mut self.aliases);
+        for (prefix, dir) in aliases {
+            let dir = canonical_root(&dir).map_err(|e| format!("alias {}: {}", prefix, e))?;
+            let normalized = format!("/{}", prefix.trim_matches('/'));
+            if normalized == "/" || prefix.split('/').any(|part| part == "." || part == "..") {
+                return Err(format!("alias `{}` must be a URL path below /", prefix));
+            }
+            self.aliases.insert(normalized, dir);
+        }
+        let vhosts = std::mem::take(&mut self.vhosts);
+        for (host, mut vhost) in vhosts {
+            vhost.root = canonical_root(&vhost.root).map_err(|e| format!("vhost {}: {}", host, e))?;
+            self.vhosts.insert(host_name(&host), vhost);
+        }
+
+        check_index_files(&self.index_files)?;
+        normalize_extensions(&mut self.allowed_extensions)?;
+        normalize_extensions(&mut self.denied_extensions)?;
+        let mime = std::mem::take(&mut self.mime);
+        for (extension, content_type) in mime {
+            let normalized = extension_key(&extension).ok_or_else(|| format!("mime: `{}` is not a file extension", extension))?;
+            // It becomes a header value.
+            if !content_type.contains('/') || content_type.chars().any(char::is_control) {
+                return Err(format!("mime: `{}` is not a content type", content_type));
+            }
+            self.mime.insert(normalized, content_type);
+        }
+        let cache_policy = std::mem::take(&mut self.cache_policy);
+        for (extension, value) in cache_policy {
+            let normalized = extension_key(&extension).ok_or_else(|| format!("cache_policy: `{}` is not a file extension", extension))?;
+            let value = value.trim().to_string();
+            if value.is_empty() || value.chars().any(char::is_control) {
+                return Err(format!("cache_policy: `{}` is not a header value", value.escape_debug()));
+            }
+            self.cache_policy.insert(normalized, value);
+        }
+        // Also a header value.
+        self.csp = self.csp.trim().to_string();
+        if self.csp.chars().any(char::is_control) {
+            return Err(format!("csp: `{}` contains control characters", self.csp.escape_debug()));
+        }
+        self.headers = response_headers(std::mem::take(&mut self.headers), "headers")?;
+        let path_headers = std::mem::take(&mut self.path_headers);
+        for entry in path_headers {
+            let prefix = format!("/{}", entry.path_prefix.trim_matches('/'));
+            if !entry.path_prefix.starts_with('/') || prefix.split('/').any(|part| part == "." || part == "..") {
+                return Err(format!("path_headers: `{}` must be a URL path", entry.path_prefix));
+            }
+            if self.path_headers.iter().any(|other| other.path_prefix == prefix) {
+                return Err(format!("path_headers: `{}` is given twice", prefix));
+            }
+            let context = format!("path_headers `{}`", prefix);
+            let headers = response_headers(entry.headers, &context)?;
+            self.path_headers.push(PathHeaders { path_prefix: prefix, headers });
+        }
+        if let Some(fallback) = &self.spa_fallback {
+            if !fallback.components().all(|component| matches!(component, Component::Normal(_))) {
+                return Err(format!("spa_fallback `{}` must be a path inside the root", fallback.display()));
+            }
+        }
+        // The gRPC client is built without TLS.
+        if let Some(endpoint) = self.otel_endpoint.as_deref().filter(|endpoint| !endpoint.starts_with("http://")) {
+            return Err(format!("otel_endpoint must be an http:// URL, not `{}`", endpoint));
+        }
+        if self.recursive_size_depth == 0 || self.recursive_size_entries == 0 {
+            return Err("recursive_size_depth and recursive_size_entries must be at least 1".to_string());
+        }
+        if self.search_max_results == 0 {
+            return Err("search_max_results must be at least 1".to_string());
+        }
+        if self.grep_max_matches == 0 {
+            return Err("grep_max_matches must be at least 1".to_string());
+        }
+        if self.histogram_buckets.is_empty() {
+            return Err("histogram_buckets needs at least one bucket".to_string());
+        }
+        if let Some(bound) = self.histogram_buckets.iter().find(|bound| !bound.is_finite() || **bound <= 0.0) {
+            return Err(format!("histogram_buckets: `{}` is not a positive number of seconds", bound));
+        }
+        if self.histogram_buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err("histogram_buckets must be in increasing order".to_string());
+        }
+        self.timestamps = Timestamps::new(&self.timezone, &self.date_format)?;
+
+        self.cors = Cors {
+            origins: self.cors_origins.clone(),
+            allow_credentials: self.cors_credentials,
+            max_age: self.cors_max_age,
+        };
+        self.cors.validate()
+    }
+}
+
+// Index files are looked up inside the requested directory, never anywhere
+// else.
+pub fn check_index_files(names: &[String]) -> Result<(), String> {
+    match names.iter().find(|name| name.is_empty() || name.contains(['/', '\\']) || *name == "..") {
+        Some(name) => Err(format!("index file `{}` must be a plain file name", name)),
+        None => Ok(()),
+    }
+}
+
+// Lowercases and strips the leading dot of each extension.
+pub fn normalize_extensions(extensions: &mut [String]) -> Result<(), String> {
+    for extension in extensions {
+        let normalized = extension.trim_start_matches('.').to_lowercase();
+        if normalized.is_empty() || normalized.contains(['/', '\\']) {
+            return Err(format!("`{}` is not a file extension", extension));
+        }
+        *extension = normalized;
+    }
+    Ok(())
+}
+
+// The files a request is served from: a document root, the aliases mounted
+// over it, and the settings of its host.
+pub struct Site<'a> {
+    // The configuration the site comes from, which serves the whole request
+    // even if a reload replaces it part way through.
+    pub config: &'a Config,
+    pub root: &'a Path,
+    pub read_only: bool,
+    aliases: &'a BTreeMap<String, PathBuf>,
+}
+
+impl Site<'_> {
+    // Whether `url_path` is the root or the prefix of an alias: directories
+    // the server was told to serve, which WebDAV clients may not remove or
+    // move away.
+    pub fn is_mount_point(&self, url_path: &Path) -> bool {
+        url_path == Path::new("/") || self.aliases.keys().any(|prefix| url_path == Path::new(prefix))
+    }
+
+    // Filesystem location of a normalized URL path (as `extract_path` gives
+    // it): inside the alias with the longest matching prefix, if any, and
+    // otherwise inside the root. Matching is by whole path components, so
+    // `/static` does not capture `/staticky`.
+    pub fn resolve(&self, url_path: &Path) -> PathBuf {
+        let alias = self
+            .aliases
+            .iter()
+            .filter_map(|(prefix, dir)| url_path.strip_prefix(prefix).ok().map(|rest| (prefix.len(), dir, rest)))
+            .max_by_key(|(len, _, _)| *len);
+        let resolved = match alias {
+            Some((_, dir, rest)) => dir.join(rest),
+            None => self.root.join(url_path.strip_prefix("/").unwrap_or(url_path)),
+        };
+        tracing::debug!(
+            "{} resolves to {}{}",
+            url_path.display(),
+            resolved.display(),
+            if alias.is_some() { " through an alias" } else { "" }
+        );
+        resolved
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Plain,
+    Json,
+}
+
+// How listings compare names: character by character (with `natural_sort`
+// and `case_insensitive_sort` applied), or by the Unicode Collation
+// Algorithm.
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NameCollation {
+    Codepoint,
+    Unicode,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    Binary,
+    Decimal,
+}
+
+// One entry of `[vhosts]`.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VirtualHost {
+    pub root: PathBuf,
+    // Refuse requests that would change anything on disk.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+// One entry of `[[path_headers]]`.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PathHeaders {
+    // Matched by whole path segments, so `/api` covers `/api/v1` but not
+    // `/apis`.
+    pub path_prefix: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+// `Host` header values compare without the port, a trailing dot or case.
+fn host_name(host: &str) -> String {
+    let host = host.trim();
+    let name = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.rsplit_once(':').map(|(name, _)| name).unwrap_or(host),
+    };
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+// A directory to serve, checked and made absolute.
+fn canonical_root(root: &Path) -> Result<PathBuf, String> {
+    let metadata = std::fs::metadata(root).map_err(|e| format!("cannot serve {}: {}", root.display(), e))?;
+    if !metadata.is_dir() {
+        return Err(format!("cannot serve {}: not a directory", root.display()));
+    }
+    root.canonicalize().map_err(|e| format!("cannot serve {}: {}", root.display(), e))
+}
+
+// A place to listen on, resolved from a `bind` entry.
+#[derive(Clone)]
+pub enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "{}", addr),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+// `unix:PATH`, `IP:PORT`, `[IPv6]:PORT`, or a bare `IP` / `[IPv6]` that
+// takes `port`.
+fn parse_bind(bind: &str, port: u16) -> Option<BindAddr> {
+    let bind = bind.trim();
+    if let Some(path) = bind.strip_prefix("unix:") {
+        return (!path.is_empty()).then(|| BindAddr::Unix(PathBuf::from(path)));
+    }
+    if let Ok(addr) = bind.parse::<SocketAddr>() {
+        return Some(BindAddr::Tcp(addr));
+    }
+    let ip = bind.strip_prefix('[').and_then(|ip| ip.strip_suffix(']')).unwrap_or(bind);
+    ip.parse::<IpAddr>().ok().map(|ip| BindAddr::Tcp(SocketAddr::new(ip, port)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Config};
+    use clap::Parser;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<Vec<_>>().into_iter()
+    }
+
+    // A configuration file with `text`, named after the test that writes it.
+    fn config_file(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("gredl-config-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn environment_overrides_the_file_and_flags_override_both() {
+        let file = config_file("precedence", "port = 1000\nshow_hidden = true\nmax_file_size = 10\n");
+        let cli = |flags: &[&str]| Cli::parse_from(["gredl_server", "--config", file.to_str().unwrap()].iter().chain(flags));
+        let env = [("GREDL_PORT", "2000"), ("GREDL_SHOW_HIDDEN", "off"), ("HOME", "/root")];
+
+        let config = Config::layer(cli(&[]), vars(&[])).unwrap();
+        assert_eq!((config.port, config.show_hidden, config.max_file_size), (1000, true, Some(10)));
+        let config = Config::layer(cli(&[]), vars(&env)).unwrap();
+        assert_eq!((config.port, config.show_hidden, config.max_file_size), (2000, false, Some(10)));
+        assert!(config.sources.contains(&("port".to_string(), "GREDL_PORT".to_string())), "{:?}", config.sources);
+        let config = Config::layer(cli(&["--port", "3000"]), vars(&env)).unwrap();
+        assert_eq!(config.port, 3000);
+        let _ = std::fs::remove_file(&file);
+    }
+
+    #[test]
+    fn values_take_the_type_of_the_default() {
+        let mut settings = toml::Table::new();
+        let keys = Config::read_env(vars(&[("GREDL_CORS_ORIGINS", " https://a.example, ,https://b.example "), ("GREDL_SANDBOX", "yes")]), &mut settings).unwrap();
+        assert_eq!(keys, [("cors_origins".to_string(), "GREDL_CORS_ORIGINS".to_string()), ("sandbox".to_string(), "GREDL_SANDBOX".to_string())]);
+        assert_eq!(settings["cors_origins"], toml::Value::Array(vec!["https://a.example".into(), "https://b.example".into()]));
+        assert_eq!(settings["sandbox"], toml::Value::Boolean(true));
+    }
+
+    #[test]
+    fn bad_values_name_their_variable() {
+        let error = |name: &str, value: &str| Config::read_env(vars(&[(name, value)]), &mut toml::Table::new()).unwrap_err();
+        assert_eq!(error("GREDL_PORT", "eighty"), "GREDL_PORT: expected a number, found `eighty`");
+        assert_eq!(error("GREDL_SANDBOX", "maybe"), "GREDL_SANDBOX: expected true or false, found `maybe`");
+        assert!(error("GREDL_PORT", "70000").starts_with("GREDL_PORT: "));
+    }
+}
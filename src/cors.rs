@@ -0,0 +1,79 @@
+// Cross-origin access for browser clients hosted elsewhere. With no origins
+// configured CORS is off and no headers are added at all.
+#[derive(Clone, Default)]
+pub struct Cors {
+    pub origins: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: u64,
+}
+
+const ALLOWED_METHODS: &str = "GET, HEAD, PUT, OPTIONS";
+const DEFAULT_ALLOWED_HEADERS: &str = "Accept, Content-Type, Range";
+
+impl Cors {
+    // Browsers refuse credentialed responses with a wildcard origin, and
+    // echoing any origin back instead would hand every site the user's
+    // credentials, so that combination is rejected at startup.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.allow_credentials && self.origins.iter().any(|origin| origin == "*") {
+            return Err("--cors-credentials requires explicit --cors-origin values, not '*'".to_string());
+        }
+        Ok(())
+    }
+
+    fn allowed_origin<'a>(&'a self, origin: &'a str) -> Option<&'a str> {
+        if self.origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)) {
+            Some(origin)
+        } else if self.origins.iter().any(|allowed| allowed == "*") {
+            Some("*")
+        } else {
+            None
+        }
+    }
+
+    // Headers to add to a normal response for a request carrying `Origin`.
+    pub fn response_headers(&self, origin: Option<&str>) -> String {
+        let Some(allowed) = origin.and_then(|origin| self.allowed_origin(origin)) else {
+            return String::new();
+        };
+
+        let mut headers = format!(
+            "Access-Control-Allow-Origin: {}\r\n\
+            Access-Control-Allow-Methods: {}\r\n\
+            Access-Control-Allow-Headers: {}\r\n",
+            allowed, ALLOWED_METHODS, DEFAULT_ALLOWED_HEADERS
+        );
+        if allowed != "*" {
+            headers.push_str("Vary: Origin\r\n");
+            if self.allow_credentials {
+                headers.push_str("Access-Control-Allow-Credentials: true\r\n");
+            }
+        }
+        headers
+    }
+
+    // Headers for a preflight `OPTIONS` request, or None when the origin is not
+    // allowed (the caller then answers without any CORS headers, which the
+    // browser treats as a refusal).
+    pub fn preflight_headers(&self, origin: Option<&str>, requested_headers: Option<&str>) -> Option<String> {
+        let allowed = origin.and_then(|origin| self.allowed_origin(origin))?;
+
+        let mut headers = format!(
+            "Access-Control-Allow-Origin: {}\r\n\
+            Access-Control-Allow-Methods: {}\r\n\
+            Access-Control-Allow-Headers: {}\r\n\
+            Access-Control-Max-Age: {}\r\n",
+            allowed,
+            ALLOWED_METHODS,
+            requested_headers.unwrap_or(DEFAULT_ALLOWED_HEADERS),
+            self.max_age
+        );
+        if allowed != "*" {
+            headers.push_str("Vary: Origin\r\n");
+            if self.allow_credentials {
+                headers.push_str("Access-Control-Allow-Credentials: true\r\n");
+            }
+        }
+        Some(headers)
+    }
+}
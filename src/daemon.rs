@@ -0,0 +1,123 @@
+// Traditional daemon support for init systems without socket activation or
+// readiness notification: a locked PID file and forking into the background.
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+// An exclusively locked PID file. The lock is held for as long as the process
+// runs (it survives the fork, since the child shares the open file), so a
+// second instance started with the same file fails instead of running next
+// to the first. A file left behind by a crash holds no lock and is reused.
+pub struct PidFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        let mut file = File::options().read(true).write(true).create(true).truncate(false).open(path)?;
+        if file.try_lock().is_err() {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another instance is running (pid {})", pid.trim()),
+            ));
+        }
+        Ok(PidFile { file, path: path.to_path_buf() })
+    }
+
+    // Records the current process, which after `detach` is the child.
+    pub fn write_pid(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        writeln!(self.file, "{}", std::process::id())
+    }
+}
+
+// Removal can fail once privileges are dropped or the sandbox is entered, as
+// the file usually lives in a root-owned directory outside the document root.
+// What stays behind is unlocked and harmless.
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::error!("Failed to remove pid file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+// The child's end of the channel back to the waiting parent, plus /dev/null,
+// opened while the sandbox still allows it.
+pub struct Detached {
+    #[cfg(unix)]
+    parent: Option<(File, File)>,
+}
+
+// Forks into the background. The parent does not return: it waits until the
+// child reports that it is serving, then exits 0, or exits 1 if the child
+// dies first, so its exit code tells the init script whether startup worked.
+// The child keeps the terminal for startup errors until `ready`.
+#[cfg(unix)]
+pub fn detach() -> io::Result<Detached> {
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    // SAFETY: plain system calls on descriptors this function owns; nothing
+    // else runs in the process yet, so forking is safe.
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (mut from_child, to_parent) = (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]));
+        match libc::fork() {
+            -1 => Err(io::Error::last_os_error()),
+            0 => {
+                drop(from_child);
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                let null = File::options().read(true).write(true).open("/dev/null")?;
+                Ok(Detached { parent: Some((to_parent, null)) })
+            }
+            _ => {
+                drop(to_parent);
+                let mut status = [0];
+                let started = matches!(from_child.read(&mut status), Ok(1));
+                std::process::exit(if started { 0 } else { 1 });
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn detach() -> io::Result<Detached> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--daemon is only supported on unix"))
+}
+
+impl Detached {
+    // Not detached at all, for running in the foreground.
+    pub fn foreground() -> Self {
+        Detached {
+            #[cfg(unix)]
+            parent: None,
+        }
+    }
+
+    // Lets the parent exit successfully and lets go of its terminal: output
+    // from here on goes to /dev/null.
+    pub fn ready(&mut self) {
+        #[cfg(unix)]
+        if let Some((mut parent, null)) = self.parent.take() {
+            use std::os::unix::io::AsRawFd;
+
+            // SAFETY: replaces the standard descriptors with duplicates of
+            // /dev/null, which stay open after `null` is dropped.
+            unsafe {
+                for fd in 0..3 {
+                    libc::dup2(null.as_raw_fd(), fd);
+                }
+            }
+            let _ = parent.write_all(&[0]);
+        }
+    }
+}
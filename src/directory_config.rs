@@ -0,0 +1,125 @@
+// Settings for a directory subtree from `.gredl.toml` files placed in it.
+// Such a file holds a subset of the global settings; each one found between
+// the root and the requested directory overrides what was in effect above
+// it, the innermost last.
+use crate::config::{self, Config, Site};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+
+pub const FILE_NAME: &str = ".gredl.toml";
+
+// The settings a directory can override, as they apply to one request.
+#[derive(Clone)]
+pub struct DirectoryConfig {
+    pub serve_index: bool,
+    pub index_files: Vec<String>,
+    pub show_hidden: bool,
+    pub read_only: bool,
+    pub max_file_size: Option<u64>,
+    pub allowed_extensions: Vec<String>,
+    pub denied_extensions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Overrides {
+    serve_index: Option<bool>,
+    index_files: Option<Vec<String>>,
+    show_hidden: Option<bool>,
+    read_only: Option<bool>,
+    max_file_size: Option<u64>,
+    allowed_extensions: Option<Vec<String>>,
+    denied_extensions: Option<Vec<String>>,
+}
+
+impl DirectoryConfig {
+    // The settings for `url_path` (as `extract_path` gives it), whether it
+    // names a directory or a file in one. The files are read on every
+    // request, so edits apply at once; a file that does not parse is logged
+    // and skipped.
+    pub async fn for_path(config: &Config, site: &Site<'_>, url_path: &Path) -> Self {
+        let mut settings = DirectoryConfig {
+            serve_index: config.serve_index,
+            index_files: config.index_files.clone(),
+            show_hidden: config.show_hidden,
+            read_only: site.read_only,
+            max_file_size: config.max_file_size,
+            allowed_extensions: config.allowed_extensions.clone(),
+            denied_extensions: config.denied_extensions.clone(),
+        };
+        let mut dir = PathBuf::from("/");
+        let segments = url_path.components().filter(|component| matches!(component, Component::Normal(_)));
+        for segment in std::iter::once(None).chain(segments.map(Some)) {
+            if let Some(segment) = segment {
+                dir.push(segment);
+            }
+            let file = site.resolve(&dir).join(FILE_NAME);
+            let Ok(text) = fs::read_to_string(&file).await else { continue };
+            settings.apply_file(&file, &text);
+        }
+        settings
+    }
+
+    // The settings for `dir`, a directory on disk right below the one these
+    // are for. Walks of a tree go down with it, and so read each file once.
+    pub async fn for_subdirectory(&self, dir: &Path) -> Self {
+        let mut settings = self.clone();
+        let file = dir.join(FILE_NAME);
+        if let Ok(text) = fs::read_to_string(&file).await {
+            settings.apply_file(&file, &text);
+        }
+        settings
+    }
+
+    // As `for_subdirectory`, for walks on a blocking thread.
+    pub fn for_subdirectory_blocking(&self, dir: &Path) -> Self {
+        let mut settings = self.clone();
+        let file = dir.join(FILE_NAME);
+        if let Ok(text) = std::fs::read_to_string(&file) {
+            settings.apply_file(&file, &text);
+        }
+        settings
+    }
+
+    fn apply_file(&mut self, file: &Path, text: &str) {
+        if let Err(e) = self.apply(text) {
+            tracing::error!("Ignoring {}: {}", file.display(), e);
+        }
+    }
+
+    fn apply(&mut self, text: &str) -> Result<(), String> {
+        let overrides: Overrides = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        if let Some(index_files) = &overrides.index_files {
+            config::check_index_files(index_files)?;
+        }
+        let mut allowed_extensions = overrides.allowed_extensions;
+        let mut denied_extensions = overrides.denied_extensions;
+        for extensions in [&mut allowed_extensions, &mut denied_extensions].into_iter().flatten() {
+            config::normalize_extensions(extensions)?;
+        }
+
+        // Checked in full before anything is applied, so a bad file changes
+        // nothing.
+        self.serve_index = overrides.serve_index.unwrap_or(self.serve_index);
+        self.index_files = overrides.index_files.unwrap_or(std::mem::take(&mut self.index_files));
+        self.show_hidden = overrides.show_hidden.unwrap_or(self.show_hidden);
+        self.read_only = overrides.read_only.unwrap_or(self.read_only);
+        self.max_file_size = overrides.max_file_size.or(self.max_file_size);
+        self.allowed_extensions = allowed_extensions.unwrap_or(std::mem::take(&mut self.allowed_extensions));
+        self.denied_extensions = denied_extensions.unwrap_or(std::mem::take(&mut self.denied_extensions));
+        Ok(())
+    }
+
+    // Whether the allow and deny lists let a file with this name be served.
+    // Extensions are matched as name suffixes, so `tar.gz` works and `env`
+    // covers both `prod.env` and a bare `.env`.
+    pub fn serves_extension(&self, file_name: &str) -> bool {
+        let file_name = file_name.to_lowercase();
+        let has = |extension: &String| {
+            file_name.strip_suffix(extension.as_str()).is_some_and(|stem| stem.ends_with('.'))
+        };
+        (self.allowed_extensions.is_empty() || self.allowed_extensions.iter().any(has))
+            && !self.denied_extensions.iter().any(has)
+    }
+}
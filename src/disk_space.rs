@@ -0,0 +1,32 @@
+// Free and total space of the filesystem holding a path, for the header of
+// listings. Only unix is supported; elsewhere, and whenever the filesystem
+// cannot be asked, there is simply no answer.
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+pub struct DiskSpace {
+    // Available to unprivileged users, which leaves out blocks reserved for
+    // root.
+    pub free: u64,
+    pub total: u64,
+}
+
+#[cfg(unix)]
+pub fn of(path: &Path) -> Option<DiskSpace> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    let block_size = stats.f_frsize as u64;
+    Some(DiskSpace {
+        free: (stats.f_bavail as u64).saturating_mul(block_size),
+        total: (stats.f_blocks as u64).saturating_mul(block_size),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn of(_path: &Path) -> Option<DiskSpace> {
+    None
+}
@@ -0,0 +1,62 @@
+// Reverse lookups of client addresses for the access log, with
+// `resolve_hostnames`. A lookup starts when the connection is accepted and
+// runs alongside the request for at most TIMEOUT. An access line whose
+// lookup is still running is written by a task of its own once it is done,
+// so a slow or unreachable name server never holds up a response; the line
+// just arrives a little later.
+use hickory_resolver::config::{ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub const TIMEOUT: Duration = Duration::from_millis(200);
+
+pub struct Resolver(TokioAsyncResolver);
+
+impl Resolver {
+    // Configured from /etc/resolv.conf, so this has to happen before the
+    // sandbox is entered. Without it the resolver falls back to public name
+    // servers.
+    pub fn from_system() -> Self {
+        let (config, mut options) = hickory_resolver::system_conf::read_system_conf().unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the system's DNS configuration, using defaults: {}", e);
+            (ResolverConfig::default(), ResolverOpts::default())
+        });
+        options.timeout = TIMEOUT;
+        options.attempts = 1;
+        Resolver(TokioAsyncResolver::tokio(config, options))
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Hostname {
+        let resolver = self.0.clone();
+        let (sender, receiver) = watch::channel(None);
+        tokio::spawn(async move {
+            let name = match tokio::time::timeout(TIMEOUT, resolver.reverse_lookup(ip)).await {
+                Ok(Ok(names)) => names.iter().next().map(|name| name.0.to_utf8().trim_end_matches('.').to_string()),
+                _ => None,
+            };
+            let _ = sender.send(Some(name));
+        });
+        Hostname(receiver)
+    }
+}
+
+// A lookup's result: None while it runs, then the name if one was found.
+#[derive(Clone)]
+pub struct Hostname(watch::Receiver<Option<Option<String>>>);
+
+impl Hostname {
+    pub fn is_done(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+
+    // The name, once the lookup is done and found one.
+    pub fn get(&self) -> Option<String> {
+        self.0.borrow().clone().flatten()
+    }
+
+    pub async fn done(&mut self) {
+        let _ = self.0.wait_for(Option::is_some).await;
+    }
+}
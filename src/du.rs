@@ -0,0 +1,134 @@
+// Recursive sizes of the subdirectories in a listing, for `?du=1` or
+// `recursive_sizes`. Walks stop at a depth and an entry limit, and never
+// follow symbolic links; a total cut short by a limit says so. Totals are
+// cached like listings are, by directory and modification time for at most
+// the listing cache's TTL, since changes deeper down leave the top
+// directory's modification time alone.
+use lru::LruCache;
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+// Directories read at once, over all walks in progress.
+const READ_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Limits {
+    // Levels of directories read, counting the one whose total it is as 1.
+    pub max_depth: usize,
+    // Entries looked at, over all levels.
+    pub max_entries: usize,
+}
+
+#[derive(Clone, Copy)]
+pub struct Total {
+    // Of regular files only.
+    pub bytes: u64,
+    // A limit was reached, so there may be more.
+    pub truncated: bool,
+}
+
+pub struct DirSizes {
+    totals: Mutex<LruCache<PathBuf, CachedTotal>>,
+    ttl: Duration,
+    reads: Semaphore,
+}
+
+struct CachedTotal {
+    dir_modified: SystemTime,
+    cached_at: Instant,
+    limits: Limits,
+    total: Total,
+}
+
+impl DirSizes {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        DirSizes {
+            totals: Mutex::new(LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap())),
+            ttl,
+            reads: Semaphore::new(READ_CONCURRENCY),
+        }
+    }
+
+    // Totals of `dirs`, walked side by side. Directories that cannot be
+    // read are left out.
+    pub async fn totals(self: &Arc<Self>, dirs: Vec<PathBuf>, limits: Limits) -> HashMap<PathBuf, Total> {
+        let mut walks = JoinSet::new();
+        for dir in dirs {
+            let sizes = self.clone();
+            walks.spawn(async move {
+                let total = sizes.total(&dir, limits).await;
+                (dir, total)
+            });
+        }
+        let mut totals = HashMap::new();
+        while let Some(walk) = walks.join_next().await {
+            if let Ok((dir, Some(total))) = walk {
+                totals.insert(dir, total);
+            }
+        }
+        totals
+    }
+
+    async fn total(&self, dir: &Path, limits: Limits) -> Option<Total> {
+        let dir_modified = fs::symlink_metadata(dir).await.ok()?.modified().ok();
+        if let Some(dir_modified) = dir_modified {
+            let mut totals = self.totals.lock().unwrap();
+            if let Some(cached) = totals.get(dir) {
+                if cached.dir_modified == dir_modified && cached.limits == limits && cached.cached_at.elapsed() < self.ttl {
+                    return Some(cached.total);
+                }
+                totals.pop(dir);
+            }
+        }
+
+        let total = self.walk(dir, limits).await?;
+        if let (Some(dir_modified), false) = (dir_modified, self.ttl.is_zero()) {
+            self.totals
+                .lock()
+                .unwrap()
+                .put(dir.to_path_buf(), CachedTotal { dir_modified, cached_at: Instant::now(), limits, total });
+        }
+        Some(total)
+    }
+
+    // Breadth first, so that the depth limit cuts off the deepest levels.
+    // DirEntry::metadata does not follow links: a link is neither counted
+    // nor entered.
+    async fn walk(&self, dir: &Path, limits: Limits) -> Option<Total> {
+        let mut total = Total { bytes: 0, truncated: false };
+        let mut seen = 0;
+        let mut pending = VecDeque::from([(dir.to_path_buf(), 1)]);
+        while let Some((dir, depth)) = pending.pop_front() {
+            let _permit = self.reads.acquire().await.ok()?;
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(_) if depth == 1 => return None,
+                Err(_) => continue,
+            };
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                seen += 1;
+                if seen > limits.max_entries {
+                    total.truncated = true;
+                    return Some(total);
+                }
+                let Ok(metadata) = entry.metadata().await else { continue };
+                if metadata.is_file() {
+                    total.bytes += metadata.len();
+                } else if metadata.is_dir() {
+                    if depth < limits.max_depth {
+                        pending.push_back((entry.path(), depth + 1));
+                    } else {
+                        total.truncated = true;
+                    }
+                }
+            }
+        }
+        Some(total)
+    }
+}
@@ -0,0 +1,57 @@
+use bytes::Bytes;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+// Contents of small, frequently requested files, so hot files are served from
+// memory instead of being reopened and read on every request. An entry is
+// only used while it is younger than the TTL and the file's mtime and size
+// still match what was cached.
+pub struct FileCache {
+    entries: LruCache<PathBuf, CachedFile>,
+    max_file_size: u64,
+    ttl: Duration,
+}
+
+struct CachedFile {
+    contents: Arc<Bytes>,
+    modified: SystemTime,
+    cached_at: Instant,
+}
+
+impl FileCache {
+    pub fn new(capacity: usize, max_file_size: u64, ttl: Duration) -> Self {
+        FileCache {
+            entries: LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap()),
+            max_file_size,
+            ttl,
+        }
+    }
+
+    // Whether a file with this metadata is small enough to be cached at all.
+    pub fn accepts(&self, metadata: &std::fs::Metadata) -> bool {
+        metadata.len() <= self.max_file_size
+    }
+
+    pub fn get(&mut self, path: &Path, metadata: &std::fs::Metadata) -> Option<Arc<Bytes>> {
+        let modified = metadata.modified().ok()?;
+        let entry = self.entries.get(path)?;
+        if entry.modified == modified
+            && entry.contents.len() as u64 == metadata.len()
+            && entry.cached_at.elapsed() < self.ttl
+        {
+            return Some(entry.contents.clone());
+        }
+
+        self.entries.pop(path);
+        None
+    }
+
+    pub fn insert(&mut self, path: PathBuf, metadata: &std::fs::Metadata, contents: Arc<Bytes>) {
+        if let Ok(modified) = metadata.modified() {
+            self.entries.put(path, CachedFile { contents, modified, cached_at: Instant::now() });
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Shell-style glob patterns for narrowing listings with `?filter=`: `*`
+// matches any run of characters, `?` any one character, `[abc]`, `[a-z]` and
+// `[!a-z]` (or `[^a-z]`) one character of a set, and `\` makes the next
+// character literal. Patterns match whole names; there are no path
+// separators to worry about, since only entry names are matched.
+pub struct Glob {
+    tokens: Vec<Token>,
+    case_insensitive: bool,
+}
+
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyRun,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl Glob {
+    pub fn parse(pattern: &str, case_insensitive: bool) -> Result<Self, String> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let token = match chars[i] {
+                '*' => Token::AnyRun,
+                '?' => Token::AnyChar,
+                '\\' => {
+                    i += 1;
+                    Token::Literal(*chars.get(i).ok_or("pattern ends in an unfinished escape `\\`")?)
+                }
+                '[' => {
+                    let (class, end) = parse_class(&chars, i + 1)?;
+                    i = end;
+                    class
+                }
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+            i += 1;
+        }
+        Ok(Glob { tokens, case_insensitive })
+    }
+
+    // Walks the name once, remembering the last `*` so that a mismatch after
+    // it can retry with the `*` taking one more character. That keeps
+    // matching linear per `*` instead of exponential in the number of them.
+    pub fn matches(&self, name: &str) -> bool {
+        let name: Vec<char> = name.chars().collect();
+        let (mut token, mut position) = (0, 0);
+        let mut last_run: Option<(usize, usize)> = None;
+        while position < name.len() {
+            match self.tokens.get(token) {
+                Some(Token::AnyRun) => {
+                    last_run = Some((token + 1, position));
+                    token += 1;
+                    continue;
+                }
+                Some(next) if self.matches_char(next, name[position]) => {
+                    token += 1;
+                    position += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match last_run {
+                Some((after_run, run_end)) => {
+                    token = after_run;
+                    position = run_end + 1;
+                    last_run = Some((after_run, run_end + 1));
+                }
+                None => return false,
+            }
+        }
+        self.tokens[token..].iter().all(|rest| matches!(rest, Token::AnyRun))
+    }
+
+    fn matches_char(&self, token: &Token, c: char) -> bool {
+        let variants = self.variants(c);
+        match token {
+            Token::Literal(literal) => self.variants(*literal).iter().any(|literal| variants.contains(literal)),
+            Token::AnyChar => true,
+            Token::AnyRun => false,
+            Token::Class { negated, ranges } => {
+                let in_class = variants.iter().any(|c| ranges.iter().any(|(low, high)| (low..=high).contains(&c)));
+                in_class != *negated
+            }
+        }
+    }
+
+    // `c` and, when case is ignored, its single-character lower and upper
+    // case forms.
+    fn variants(&self, c: char) -> Vec<char> {
+        let mut variants = vec![c];
+        if self.case_insensitive {
+            let mapped: [Vec<char>; 2] = [c.to_lowercase().collect(), c.to_uppercase().collect()];
+            for mapped in mapped {
+                if let [single] = mapped[..] {
+                    variants.push(single);
+                }
+            }
+        }
+        variants
+    }
+}
+
+// The class starting at `chars[start]`, just after its `[`, and the index of
+// its closing `]`. A `]` first in the class (after any negation) is a member
+// rather than the end, and so is a `-` at either end.
+fn parse_class(chars: &[char], start: usize) -> Result<(Token, usize), String> {
+    const UNCLOSED: &str = "pattern has a `[` without a matching `]`";
+    let member = |i: usize| -> Result<(char, usize), String> {
+        match chars.get(i) {
+            Some('\\') => chars.get(i + 1).map(|c| (*c, i + 1)).ok_or_else(|| UNCLOSED.to_string()),
+            Some(c) => Ok((*c, i)),
+            None => Err(UNCLOSED.to_string()),
+        }
+    };
+    let negated = matches!(chars.get(start), Some('!' | '^'));
+    let mut i = if negated { start + 1 } else { start };
+    let first = i;
+    let mut ranges = Vec::new();
+    loop {
+        if chars.get(i) == Some(&']') && i != first {
+            return Ok((Token::Class { negated, ranges }, i));
+        }
+        let (low, low_end) = member(i)?;
+        let (high, end) = match (chars.get(low_end + 1), chars.get(low_end + 2)) {
+            (Some('-'), Some(c)) if *c != ']' => member(low_end + 2)?,
+            _ => (low, low_end),
+        };
+        if high < low {
+            return Err(format!("pattern has a backwards range `{}-{}`", low, high));
+        }
+        ranges.push((low, high));
+        i = end + 1;
+    }
+}
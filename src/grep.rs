@@ -0,0 +1,162 @@
+// Content search under a directory, for `/api/v1/grep`. Files are found by
+// the same walk as name searches, so the same directories are read and the
+// same files left out, and a few at a time are read on the blocking pool and
+// matched line by line. Files larger than the configured cap, and files that
+// look binary, are skipped without a word.
+use crate::config::Site;
+use crate::search::{Step, Walk};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+// Files read at once.
+const CONCURRENCY: usize = 8;
+
+// A file with a NUL byte this close to its start is taken for binary, as git
+// and grep do.
+const BINARY_CHECK_LENGTH: usize = 8000;
+
+// Longer matching lines are cut short, at a character boundary.
+const MAX_LINE_LENGTH: usize = 1024;
+
+// What compiled regexes may grow to, so that a pattern such as `a{1000}{1000}`
+// is refused rather than built.
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+pub enum Matcher {
+    Literal(String),
+    Regex(regex::Regex),
+}
+
+impl Matcher {
+    pub fn parse(query: &str, regex: bool) -> Result<Self, String> {
+        if !regex {
+            return Ok(Matcher::Literal(query.to_string()));
+        }
+        regex::RegexBuilder::new(query)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map(Matcher::Regex)
+            .map_err(|e| e.to_string())
+    }
+
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            Matcher::Literal(literal) => line.contains(literal.as_str()),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+pub struct Match {
+    // Of the file, from the root of the site.
+    pub url_path: PathBuf,
+    // Counted from 1.
+    pub line_number: usize,
+    pub line: String,
+}
+
+pub struct Grep {
+    files: Walk,
+    matcher: Arc<Matcher>,
+    max_file_size: u64,
+    max_matches: usize,
+    reading: JoinSet<Vec<Match>>,
+    // Matches from files already read, not yet returned.
+    ready: VecDeque<Match>,
+    walked: bool,
+    found: usize,
+    // Set when a match past `max_matches` was found and left out.
+    pub limited: bool,
+}
+
+impl Grep {
+    pub fn new(start: PathBuf, matcher: Matcher, show_hidden: Option<bool>, max_depth: usize, max_file_size: u64, max_matches: usize) -> Self {
+        Grep {
+            files: Walk::new(start, show_hidden, max_depth, false),
+            matcher: Arc::new(matcher),
+            max_file_size,
+            max_matches,
+            reading: JoinSet::new(),
+            ready: VecDeque::new(),
+            walked: false,
+            found: 0,
+            limited: false,
+        }
+    }
+
+    // The next matching line, or None when every file was read or enough
+    // matches were found. Files whose reads finish first come first.
+    pub async fn next(&mut self, site: &Site<'_>) -> Option<Match> {
+        loop {
+            if let Some(found) = self.ready.pop_front() {
+                if self.found == self.max_matches {
+                    self.limited = true;
+                    return None;
+                }
+                self.found += 1;
+                return Some(found);
+            }
+            while !self.walked && self.reading.len() < CONCURRENCY {
+                let file = match self.files.next(site).await {
+                    Some(Step::Entry(file)) => file,
+                    Some(Step::Finished(_)) => continue,
+                    None => {
+                        self.walked = true;
+                        break;
+                    }
+                };
+                let max_file_size = file.settings.max_file_size.map_or(self.max_file_size, |max| max.min(self.max_file_size));
+                let size = tokio::fs::metadata(&file.path).await.ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len());
+                if !file.settings.serves_extension(&file.name) || size.is_none_or(|size| size > max_file_size) {
+                    continue;
+                }
+                let matcher = self.matcher.clone();
+                // A file never needs to give more than could still be returned.
+                let limit = self.max_matches - self.found + 1;
+                self.reading.spawn_blocking(move || grep_file(file.url_path, &file.path, &matcher, max_file_size, limit));
+            }
+            match self.reading.join_next().await? {
+                Ok(matches) => self.ready.extend(matches),
+                Err(e) => tracing::error!("Content search of a file failed: {}", e),
+            }
+        }
+    }
+}
+
+// Up to `limit` matching lines of the file at `path`; none when it cannot be
+// read, has grown past `max_file_size` or looks binary.
+fn grep_file(url_path: PathBuf, path: &std::path::Path, matcher: &Matcher, max_file_size: u64, limit: usize) -> Vec<Match> {
+    let mut contents = Vec::new();
+    let read = std::fs::File::open(path).and_then(|file| file.take(max_file_size + 1).read_to_end(&mut contents));
+    if read.is_err() || contents.len() as u64 > max_file_size || contents[..contents.len().min(BINARY_CHECK_LENGTH)].contains(&0) {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    let text = contents.strip_suffix(b"\n").unwrap_or(&contents);
+    if text.is_empty() {
+        return matches;
+    }
+    for (index, line) in text.split(|byte| *byte == b'\n').enumerate() {
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        if !matcher.matches(&line) {
+            continue;
+        }
+        let mut line = line.into_owned();
+        if line.len() > MAX_LINE_LENGTH {
+            let mut end = MAX_LINE_LENGTH;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line.truncate(end);
+        }
+        matches.push(Match { url_path: url_path.clone(), line_number: index + 1, line });
+        if matches.len() == limit {
+            break;
+        }
+    }
+    matches
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+// Icons for listing rows and file info pages. Files are grouped by their
+// last extension, compared case-insensitively; a file whose extension is in
+// no group is marked as executable if it is one, or gets the default icon.
+const GROUPS: &[(&str, &[&str])] = &[
+    ("🖼", &["png", "jpg", "jpeg", "gif", "webp", "svg", "ico", "bmp", "avif", "tif", "tiff", "heic"]),
+    ("🎵", &["mp3", "ogg", "oga", "wav", "flac", "m4a", "aac", "opus"]),
+    ("🎬", &["mp4", "webm", "mkv", "mov", "avi", "m4v"]),
+    ("🗜", &["zip", "gz", "tgz", "tar", "7z", "xz", "bz2", "zst", "rar"]),
+    ("📜", &[
+        "rs", "c", "h", "cpp", "hpp", "go", "py", "rb", "js", "mjs", "ts", "java", "kt", "swift", "sh", "bash", "zsh",
+        "html", "htm", "css", "json", "toml", "yaml", "yml", "xml", "sql",
+    ]),
+    ("📕", &["pdf"]),
+    ("🔤", &["woff", "woff2", "ttf", "otf"]),
+];
+
+// Images browsers show themselves, which the grid view previews.
+const PREVIEWABLE: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "ico", "bmp", "avif"];
+
+pub const DIRECTORY: &str = "📁";
+pub const SYMLINK: &str = "🔗";
+pub const EXECUTABLE: &str = "⚙";
+pub const DEFAULT: &str = "📄";
+
+// A symbolic link's icon wins over its target's kind, so that links stand
+// out however they are listed.
+pub fn icon(name: &str, is_dir: bool, is_symlink: bool, executable: bool) -> &'static str {
+    if is_symlink {
+        return SYMLINK;
+    }
+    if is_dir {
+        return DIRECTORY;
+    }
+    let extension = Path::new(name).extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    let group = extension.and_then(|extension| GROUPS.iter().find(|(_, extensions)| extensions.contains(&extension.as_str())));
+    match group {
+        Some((icon, _)) => icon,
+        None if executable => EXECUTABLE,
+        None => DEFAULT,
+    }
+}
+
+pub fn previewable(name: &str) -> bool {
+    let extension = Path::new(name).extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    extension.is_some_and(|extension| PREVIEWABLE.contains(&extension.as_str()))
+}
@@ -0,0 +1,173 @@
+use crate::config::BindAddr;
+use crate::dns::Hostname;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+// Any accepted connection. The request path is generic over this so TCP and
+// unix sockets share all of it; `socket` is for the little that needs the
+// socket itself, such as sending files with sendfile(2).
+pub trait Connection: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    fn socket(&self) -> Socket<'_>;
+}
+
+pub enum Socket<'a> {
+    Tcp(&'a tokio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(&'a tokio::net::UnixStream),
+}
+
+impl Connection for tokio::net::TcpStream {
+    fn socket(&self) -> Socket<'_> {
+        Socket::Tcp(self)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for tokio::net::UnixStream {
+    fn socket(&self) -> Socket<'_> {
+        Socket::Unix(self)
+    }
+}
+
+// A bound, not yet accepting, listener. Binding happens before the sandbox is
+// entered and privileges are dropped; the runtime picks these up afterwards.
+pub enum Listener {
+    Tcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    // Where clients can reach this listener, as printed at startup.
+    pub fn url(&self) -> io::Result<String> {
+        match self {
+            Listener::Tcp(listener) => Ok(format!("http://{}", listener.local_addr()?)),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Ok(format!("unix:{}", path.display())),
+        }
+    }
+}
+
+// Who is on the other end of a connection, for logs and the audit trail.
+#[derive(Clone)]
+pub enum Peer {
+    // With the reverse lookup of the address, under `resolve_hostnames`.
+    Tcp(SocketAddr, Option<Hostname>),
+    // The socket path plus the peer's credentials where the OS provides them.
+    Unix(String),
+}
+
+impl Peer {
+    // The client's identity without the ephemeral port.
+    pub fn host(&self) -> String {
+        match self {
+            Peer::Tcp(addr, _) => addr.ip().to_string(),
+            Peer::Unix(description) => description.clone(),
+        }
+    }
+
+    // A lookup still running, for the access log to wait for.
+    pub fn pending_lookup(&self) -> Option<Hostname> {
+        match self {
+            Peer::Tcp(_, Some(hostname)) if !hostname.is_done() => Some(hostname.clone()),
+            _ => None,
+        }
+    }
+}
+
+// A TCP client whose address resolved is shown by its host name.
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Peer::Tcp(addr, hostname) => match hostname.as_ref().and_then(Hostname::get) {
+                Some(name) => write!(f, "{}:{}", name, addr.port()),
+                None => write!(f, "{}", addr),
+            },
+            Peer::Unix(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+// Listeners for one configured address: one per worker for TCP, a single one
+// for a unix socket, which has no SO_REUSEPORT equivalent worth using.
+pub fn bind(addr: &BindAddr, workers: usize, unix_socket_mode: Option<u32>) -> io::Result<Vec<Listener>> {
+    match addr {
+        BindAddr::Tcp(addr) => Ok(bind_tcp(*addr, workers)?.into_iter().map(Listener::Tcp).collect()),
+        BindAddr::Unix(path) => bind_unix(path, unix_socket_mode).map(|listener| vec![listener]),
+    }
+}
+
+// One listener per worker. With port 0 the first bind picks an ephemeral port
+// and the others join it there, so all workers share one address.
+fn bind_tcp(addr: SocketAddr, workers: usize) -> io::Result<Vec<std::net::TcpListener>> {
+    let first = bind_tcp_listener(addr, workers > 1)?;
+    let addr = first.local_addr()?;
+    let mut listeners = vec![first];
+    for _ in 1..workers {
+        listeners.push(bind_tcp_listener(addr, true)?);
+    }
+    Ok(listeners)
+}
+
+// With several workers every listener is bound to the same address using
+// SO_REUSEPORT, and the kernel spreads incoming connections across them
+// instead of funnelling everything through one accept loop.
+fn bind_tcp_listener(addr: SocketAddr, reuse_port: bool) -> io::Result<std::net::TcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    socket.set_reuse_address(true)?;
+    // Keep `[::]` from also claiming the IPv4 port, so it can be bound next
+    // to `0.0.0.0` on the same port.
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    if reuse_port {
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--workers greater than 1 requires SO_REUSEPORT, which is only available on unix",
+        ));
+    }
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+// A socket file left behind by a server that did not shut down cleanly is
+// removed first, but only if nothing answers on it; a live one is an error,
+// as is a path that is not a socket at all.
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path, mode: Option<u32>) -> io::Result<Listener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another server is listening on this socket"));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, "path exists and is not a socket")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    listener.set_nonblocking(true)?;
+    Ok(Listener::Unix(listener, path.to_path_buf()))
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_path: &std::path::Path, _mode: Option<u32>) -> io::Result<Listener> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "unix sockets are not supported on this platform"))
+}
@@ -0,0 +1,508 @@
+use crate::glob::Glob;
+use caseless::Caseless;
+use lru::LruCache;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::io;
+use std::iter::Peekable;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs;
+use tokio::task::JoinSet;
+
+// One row of a directory listing, with raw values; formatting happens when
+// the page is rendered.
+#[derive(Clone)]
+pub struct EntryInfo {
+    // For display, sorting and filtering; any bytes that are not UTF-8 are
+    // replaced. Links use `file_name`, the name as it is on disk.
+    pub name: String,
+    pub file_name: OsString,
+    pub is_dir: bool,
+    // None when the entry's metadata cannot be read.
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+    // Set for a symbolic link, whose other fields describe its target.
+    pub symlink: Option<Symlink>,
+    // None when the metadata cannot be read, and always off unix.
+    pub unix: Option<UnixDetails>,
+}
+
+// Mode and ownership, for listings with permission columns.
+#[derive(Clone, Copy)]
+pub struct UnixDetails {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl UnixDetails {
+    // Executable by anyone.
+    pub fn executable(&self) -> bool {
+        self.mode & 0o111 != 0
+    }
+
+    #[cfg(unix)]
+    pub fn of(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(UnixDetails { mode: metadata.mode(), uid: metadata.uid(), gid: metadata.gid() })
+    }
+
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct Symlink {
+    // None when the link itself cannot be read.
+    pub target: Option<PathBuf>,
+    // The target does not exist (or cannot be looked at).
+    pub broken: bool,
+}
+
+impl EntryInfo {
+    // Dotfiles, which listings leave out unless asked to show them.
+    pub fn is_hidden(&self) -> bool {
+        self.name.starts_with('.')
+    }
+}
+
+// The entries a listing shows: dotfiles only when asked to, and only names
+// matching the `?filter=` pattern, if there is one.
+pub struct EntryFilter {
+    pub show_hidden: bool,
+    pub pattern: Option<Glob>,
+}
+
+impl EntryFilter {
+    pub fn admits(&self, entry: &EntryInfo) -> bool {
+        entry.name != crate::directory_config::FILE_NAME
+            && (self.show_hidden || !entry.is_hidden()) && self.pattern.as_ref().is_none_or(|pattern| pattern.matches(&entry.name))
+    }
+}
+
+// What a listing shows, counted as its rows are written.
+#[derive(Clone, Copy, Default, serde::Serialize)]
+pub struct Summary {
+    pub directories: usize,
+    pub files: usize,
+    // Total size of the files; directories add nothing.
+    pub bytes: u64,
+}
+
+impl Summary {
+    pub fn add(&mut self, entry: &EntryInfo) {
+        if entry.is_dir {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+            self.bytes += entry.size.unwrap_or(0);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directories == 0 && self.files == 0
+    }
+}
+
+// Directories with more entries than this are not buffered, sorted or
+// cached; their rows are streamed out in directory order as they are read.
+pub const MAX_BUFFERED_ENTRIES: usize = 10_000;
+
+// Unless a listing asks for an order with `?sort=`: then a larger directory
+// is read whole, up to this many entries, and sorted. That holds every entry
+// in memory and delays the first row until the last entry is read, which is
+// why the default order does not do it.
+pub const MAX_SORTED_ENTRIES: usize = 100_000;
+
+pub enum Listing {
+    // Every entry, in the order of `Sort::by_name` for the cache's collation.
+    Complete(Arc<Vec<EntryInfo>>),
+    // The first MAX_BUFFERED_ENTRIES entries in directory order, plus the
+    // reader for the rest.
+    Partial(Vec<EntryInfo>, EntryReader),
+}
+
+// One page of a listing, as requested with `?page=<N>&per_page=<N>`.
+pub struct Pagination {
+    // 1-based.
+    pub page: usize,
+    pub per_page: usize,
+}
+
+pub const DEFAULT_PER_PAGE: usize = 100;
+
+impl Pagination {
+    // None when neither parameter is present, so plain listings stay on one
+    // page. Values that do not parse fall back to the first page and the
+    // default page size; the page size is capped at MAX_BUFFERED_ENTRIES.
+    pub fn from_params(page: Option<&str>, per_page: Option<&str>) -> Option<Self> {
+        if page.is_none() && per_page.is_none() {
+            return None;
+        }
+        let page = page.and_then(|page| page.parse().ok()).filter(|&page| page > 0).unwrap_or(1);
+        let per_page = per_page
+            .and_then(|per_page| per_page.parse().ok())
+            .filter(|&per_page| per_page > 0)
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .min(MAX_BUFFERED_ENTRIES);
+        Some(Pagination { page, per_page })
+    }
+
+    pub fn offset(&self) -> usize {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    // Pages needed for `total` entries; an empty listing still has one.
+    pub fn page_count(&self, total: usize) -> usize {
+        total.div_ceil(self.per_page).max(1)
+    }
+
+    // This page, or the last one when it lies past the end of `total` entries.
+    pub fn clamped(&self, total: usize) -> Pagination {
+        Pagination { page: self.page.min(self.page_count(total)), per_page: self.per_page }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 3] = [SortKey::Name, SortKey::Size, SortKey::Modified];
+
+    // The `sort` query parameter value.
+    pub fn param(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "mtime",
+        }
+    }
+}
+
+// How names are compared and whether directories are kept apart, whatever
+// the listing is sorted by.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Collation {
+    // Compare names character by character instead of with `natural_cmp`.
+    pub lexicographic: bool,
+    // Compare names after Unicode case folding, so `apple` comes before
+    // `README`.
+    pub case_insensitive: bool,
+    // List every directory before the first file.
+    pub dirs_first: bool,
+    // Compare names with `collate::compare`, for which `case_insensitive`
+    // means nothing.
+    pub unicode: bool,
+}
+
+impl Collation {
+    // Names that compare equal, say `a` and `A` ignoring case, fall back to
+    // their bytes on disk, so that the order never depends on how the
+    // directory was read.
+    pub fn compare_names(&self, a: &EntryInfo, b: &EntryInfo) -> Ordering {
+        let ordering = match (self.lexicographic, self.case_insensitive) {
+            _ if self.unicode => crate::collate::compare(&a.name, &b.name, !self.lexicographic),
+            (true, true) => a.name.chars().default_case_fold().cmp(b.name.chars().default_case_fold()),
+            (true, false) => a.name.cmp(&b.name),
+            (false, case_insensitive) => natural_cmp(&a.name, &b.name, case_insensitive),
+        };
+        ordering.then_with(|| a.file_name.as_encoded_bytes().cmp(b.file_name.as_encoded_bytes()))
+    }
+}
+
+// Order of a listing, as requested with `?sort=name|size|mtime&order=asc|desc`.
+// The key and direction apply within the directories and within the files
+// when they are grouped, with the name breaking ties.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+    pub collation: Collation,
+}
+
+impl Sort {
+    // Values that are missing or not recognised fall back to name, ascending.
+    pub fn from_params(sort: Option<&str>, order: Option<&str>, collation: Collation) -> Self {
+        let key = SortKey::ALL.into_iter().find(|key| Some(key.param()) == sort).unwrap_or_default();
+        Sort { key, descending: order == Some("desc"), collation }
+    }
+
+    // The order listings are read and cached in.
+    pub fn by_name(collation: Collation) -> Self {
+        Sort { key: SortKey::Name, descending: false, collation }
+    }
+
+    pub fn compare(&self, a: &EntryInfo, b: &EntryInfo) -> Ordering {
+        if self.collation.dirs_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+        let ordering = match self.key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+        }
+        .then_with(|| self.collation.compare_names(a, b));
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
+
+// Orders names the way people count: runs of digits compare by their value,
+// so `chapter2` comes before `chapter10`, and, when `case_insensitive`,
+// letters compare after Unicode case folding. Runs of any length compare
+// without being parsed, so they cannot overflow. Names that only differ in
+// case or leading zeros (`file7` and `file007`) fall back to comparing
+// character by character, which keeps the order total.
+pub fn natural_cmp(a: &str, b: &str, case_insensitive: bool) -> Ordering {
+    // Folded as the comparison goes, since sorting compares every name many
+    // times.
+    if case_insensitive {
+        natural_cmp_chars(a, b, a.chars().default_case_fold(), b.chars().default_case_fold())
+    } else {
+        natural_cmp_chars(a, b, a.chars(), b.chars())
+    }
+}
+
+fn natural_cmp_chars(a: &str, b: &str, a_chars: impl Iterator<Item = char>, b_chars: impl Iterator<Item = char>) -> Ordering {
+    let mut a_chars = a_chars.peekable();
+    let mut b_chars = b_chars.peekable();
+    loop {
+        let ordering = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => compare_numbers(&mut a_chars, &mut b_chars),
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                a_chars.next();
+                b_chars.next();
+                ordering
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Consumes the runs of digits at the front of `a` and `b` and compares their
+// values: without leading zeros, the longer run is the larger number, and
+// runs of the same length compare at their first differing digit.
+fn compare_numbers(a: &mut Peekable<impl Iterator<Item = char>>, b: &mut Peekable<impl Iterator<Item = char>>) -> Ordering {
+    while a.next_if_eq(&'0').is_some() {}
+    while b.next_if_eq(&'0').is_some() {}
+    let mut first_difference = Ordering::Equal;
+    loop {
+        match (a.next_if(char::is_ascii_digit), b.next_if(char::is_ascii_digit)) {
+            (Some(x), Some(y)) => first_difference = first_difference.then(x.cmp(&y)),
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return first_difference,
+        }
+    }
+}
+
+// `entries`, which are in the order of `Sort::by_name(read_in)`, in the order
+// of `sort`.
+pub fn sorted(entries: &[EntryInfo], read_in: Collation, sort: Sort) -> Vec<&EntryInfo> {
+    let mut sorted: Vec<&EntryInfo> = entries.iter().collect();
+    if sort != Sort::by_name(read_in) {
+        sorted.sort_by(|a, b| sort.compare(a, b));
+    }
+    sorted
+}
+
+// Directory rows say how many entries the directory holds, but stop
+// counting past this many, as reading on would make the listing of a
+// directory of large directories slow.
+pub const CHILD_COUNT_LIMIT: usize = 1000;
+
+// How many entries the directory at `path` holds, counted up to one past
+// CHILD_COUNT_LIMIT. None when it cannot be read.
+pub async fn count_children(path: &Path) -> Option<usize> {
+    let mut entries = fs::read_dir(path).await.ok()?;
+    let mut count = 0;
+    while count <= CHILD_COUNT_LIMIT && entries.next_entry().await.ok()?.is_some() {
+        count += 1;
+    }
+    Some(count)
+}
+
+// Names read from the directory at a time, and metadata lookups in flight
+// at once while resolving them.
+const READ_AHEAD: usize = 256;
+const METADATA_CONCURRENCY: usize = 32;
+
+// Reads a directory's entries with their metadata. Looking the metadata up
+// one entry at a time makes big directories on NFS or spinning disks take
+// seconds, so it is looked up for a batch of names at a time, several
+// lookups at once, and handed out in directory order.
+pub struct EntryReader {
+    dir_entries: fs::ReadDir,
+    ready: VecDeque<EntryInfo>,
+    exhausted: bool,
+}
+
+impl EntryReader {
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        Ok(EntryReader { dir_entries: fs::read_dir(path).await?, ready: VecDeque::new(), exhausted: false })
+    }
+
+    // A read error ends the listing like the end of the directory does.
+    pub async fn next(&mut self) -> Option<EntryInfo> {
+        if self.ready.is_empty() && !self.exhausted {
+            self.read_batch().await;
+        }
+        self.ready.pop_front()
+    }
+
+    async fn read_batch(&mut self) {
+        let mut names = Vec::new();
+        while names.len() < READ_AHEAD {
+            match self.dir_entries.next_entry().await {
+                Ok(Some(entry)) => names.push(entry),
+                _ => {
+                    self.exhausted = true;
+                    break;
+                }
+            }
+        }
+
+        let mut batch = vec![None; names.len()];
+        let mut pending = names.into_iter().enumerate();
+        let mut lookups = JoinSet::new();
+        loop {
+            while lookups.len() < METADATA_CONCURRENCY {
+                let Some((index, entry)) = pending.next() else { break };
+                lookups.spawn(async move { (index, entry_info(entry).await) });
+            }
+            match lookups.join_next().await {
+                Some(Ok((index, info))) => batch[index] = Some(info),
+                Some(Err(_)) => {}
+                None => break,
+            }
+        }
+        self.ready.extend(batch.into_iter().flatten());
+    }
+}
+
+// An entry whose metadata cannot be read is still listed, with no size or
+// modification time; for a symbolic link that means its target is missing.
+async fn entry_info(entry: fs::DirEntry) -> EntryInfo {
+    let file_name = entry.file_name();
+    let name = file_name.to_string_lossy().to_string();
+    let file_type = entry.file_type().await.ok();
+    let mut symlink = match file_type {
+        Some(file_type) if file_type.is_symlink() => {
+            Some(Symlink { target: fs::read_link(entry.path()).await.ok(), broken: false })
+        }
+        _ => None,
+    };
+    // Unlike `entry.metadata()`, this follows links, so a link to a
+    // directory is listed as one.
+    match fs::metadata(entry.path()).await {
+        Ok(metadata) => EntryInfo {
+            name,
+            file_name,
+            is_dir: metadata.is_dir(),
+            size: Some(metadata.len()),
+            modified: metadata.modified().ok(),
+            symlink,
+            unix: UnixDetails::of(&metadata),
+        },
+        Err(_) => {
+            if let Some(symlink) = &mut symlink {
+                symlink.broken = true;
+            }
+            EntryInfo {
+                name,
+                file_name,
+                is_dir: file_type.is_some_and(|file_type| file_type.is_dir()),
+                size: None,
+                modified: None,
+                symlink,
+                unix: None,
+            }
+        }
+    }
+}
+
+pub async fn read_entries(path: &Path, collation: Collation) -> io::Result<Listing> {
+    let mut entries = Vec::new();
+    let mut reader = EntryReader::open(path).await?;
+
+    while let Some(entry) = reader.next().await {
+        entries.push(entry);
+        if entries.len() >= MAX_BUFFERED_ENTRIES {
+            return Ok(Listing::Partial(entries, reader));
+        }
+    }
+
+    entries.sort_by(|a, b| Sort::by_name(collation).compare(a, b));
+    Ok(Listing::Complete(Arc::new(entries)))
+}
+
+// Recently read listings of hot directories. A directory's mtime changes when
+// entries are added, removed or renamed, which invalidates the cached copy
+// immediately; changes to the files themselves (sizes, mtimes) only show up
+// once the TTL has run out.
+pub struct ListingCache {
+    entries: Mutex<LruCache<PathBuf, CachedListing>>,
+    ttl: Duration,
+    collation: Collation,
+}
+
+struct CachedListing {
+    dir_modified: SystemTime,
+    cached_at: Instant,
+    entries: Arc<Vec<EntryInfo>>,
+}
+
+impl ListingCache {
+    pub fn new(capacity: usize, ttl: Duration, collation: Collation) -> Self {
+        ListingCache {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(capacity.max(1)).unwrap())),
+            ttl,
+            collation,
+        }
+    }
+
+    // The order complete listings come in.
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    pub async fn get_or_read(&self, path: &Path, dir_metadata: &std::fs::Metadata) -> io::Result<Listing> {
+        let dir_modified = dir_metadata.modified().ok();
+        if let Some(dir_modified) = dir_modified {
+            let mut cache = self.entries.lock().unwrap();
+            if let Some(cached) = cache.get(path) {
+                if cached.dir_modified == dir_modified && cached.cached_at.elapsed() < self.ttl {
+                    return Ok(Listing::Complete(cached.entries.clone()));
+                }
+                cache.pop(path);
+            }
+        }
+
+        // Not holding the lock while reading: concurrent misses on the same
+        // directory may both read it, which is harmless.
+        let listing = read_entries(path, self.collation).await?;
+        if let (Listing::Complete(entries), Some(dir_modified), false) = (&listing, dir_modified, self.ttl.is_zero()) {
+            self.entries.lock().unwrap().put(
+                path.to_path_buf(),
+                CachedListing { dir_modified, cached_at: Instant::now(), entries: entries.clone() },
+            );
+        }
+        Ok(listing)
+    }
+}
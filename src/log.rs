@@ -0,0 +1,191 @@
+// Everything the server reports is a `tracing` event, written by one `Logger`
+// chosen at startup by `log_format`: plain lines as a person reads them, or
+// one JSON object per line for log collectors. Informational messages and
+// access lines go to stdout, warnings and errors to stderr, in either format.
+//
+// RUST_LOG picks the events, with the usual `tracing_subscriber` directives;
+// without it everything at info and above is written. Per-request details
+// such as connections and path resolution are at debug level, so
+// `RUST_LOG=gredl_server=debug` shows them.
+//
+// Events carrying a `kind` field ("access" or "audit") are records of
+// requests rather than messages; their other fields are the record.
+use crate::config::LogFormat;
+use chrono::Utc;
+use std::sync::OnceLock;
+use tracing::field::{Field, Visit};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+pub struct Event {
+    pub level: Level,
+    // "message", "access" or "audit".
+    pub kind: String,
+    pub message: String,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+pub trait Logger: Send + Sync + 'static {
+    fn write(&self, event: &Event);
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::ERROR => "error",
+        Level::WARN => "warning",
+        Level::INFO => "info",
+        Level::DEBUG => "debug",
+        Level::TRACE => "trace",
+    }
+}
+
+pub struct PlainLogger;
+
+impl Logger for PlainLogger {
+    fn write(&self, event: &Event) {
+        match event.kind.as_str() {
+            "access" => {
+                let field = |name: &str| match event.fields.get(name) {
+                    Some(serde_json::Value::String(text)) => text.clone(),
+                    Some(value) => value.to_string(),
+                    None => "-".to_string(),
+                };
+                println!("{} {} {} -> {}", field("peer"), field("method"), field("path"), event.message);
+            }
+            // Prefixed so it stands out from the rest of stderr.
+            "audit" => eprintln!("AUDIT {}", serde_json::Value::Object(event.fields.clone())),
+            _ => {
+                let mut line = event.message.clone();
+                for (name, value) in &event.fields {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&format!("{}={}", name, value));
+                }
+                match event.level {
+                    Level::INFO => println!("{}", line),
+                    Level::WARN => eprintln!("warning: {}", line),
+                    Level::ERROR => eprintln!("{}", line),
+                    level => println!("{}: {}", level_name(level), line),
+                }
+            }
+        }
+    }
+}
+
+// Every line carries `timestamp`, `level` and `kind`, messages a `message`,
+// and then whatever fields the event has.
+pub struct JsonLogger;
+
+impl Logger for JsonLogger {
+    fn write(&self, event: &Event) {
+        let mut line = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "level": level_name(event.level),
+            "kind": event.kind,
+        });
+        if let Some(line) = line.as_object_mut() {
+            if event.kind == "message" {
+                line.insert("message".to_string(), event.message.clone().into());
+            }
+            line.extend(event.fields.clone());
+        }
+        match event.level {
+            Level::WARN | Level::ERROR => eprintln!("{}", line),
+            _ => println!("{}", line),
+        }
+    }
+}
+
+// Collects an event's fields, with `message` and `kind` set apart.
+#[derive(Default)]
+struct Fields {
+    kind: Option<String>,
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Fields {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        match (field.name(), value) {
+            ("kind", serde_json::Value::String(kind)) => self.kind = Some(kind),
+            ("message", serde_json::Value::String(message)) => self.message = message,
+            (name, value) => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+}
+
+struct LoggerLayer(Box<dyn Logger>);
+
+impl<S: Subscriber> Layer<S> for LoggerLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.0.write(&Event {
+            level: *event.metadata().level(),
+            kind: fields.kind.unwrap_or_else(|| "message".to_string()),
+            message: fields.message,
+            fields: fields.fields,
+        });
+    }
+}
+
+// Spans go to OpenTelemetry once `export_spans` is called. The exporter
+// needs the Tokio runtime, which only starts after logging does, so the
+// layer is swapped in then.
+type SpanExport = tracing_opentelemetry::OpenTelemetryLayer<Registry, opentelemetry_sdk::trace::Tracer>;
+
+static SPAN_EXPORT: OnceLock<reload::Handle<Option<SpanExport>, Registry>> = OnceLock::new();
+
+fn subscriber_with(format: LogFormat, span_export: reload::Layer<Option<SpanExport>, Registry>) -> impl Subscriber + Send + Sync {
+    let logger: Box<dyn Logger> = match format {
+        LogFormat::Plain => Box::new(PlainLogger),
+        LogFormat::Json => Box::new(JsonLogger),
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry().with(span_export).with(filter).with(LoggerLayer(logger))
+}
+
+pub fn subscriber(format: LogFormat) -> impl Subscriber + Send + Sync {
+    subscriber_with(format, reload::Layer::new(None).0)
+}
+
+// Until this is called, while the configuration that names the format is
+// being loaded, `main` logs through a plain subscriber of its own.
+pub fn init(format: LogFormat) {
+    let (span_export, handle) = reload::Layer::new(None);
+    if tracing::subscriber::set_global_default(subscriber_with(format, span_export)).is_ok() {
+        let _ = SPAN_EXPORT.set(handle);
+    }
+}
+
+pub fn export_spans(tracer: opentelemetry_sdk::trace::Tracer) {
+    if let Some(handle) = SPAN_EXPORT.get() {
+        let _ = handle.reload(Some(tracing_opentelemetry::layer().with_tracer(tracer)));
+    }
+}
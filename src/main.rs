@@ -1,105 +1,2819 @@
-use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use percent_encoding::{percent_decode_str, percent_encode, NON_ALPHANUMERIC};
-use chrono::{DateTime, Local};
-use humansize::{format_size, BINARY};
+use percent_encoding::{percent_decode_str, percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+use humansize::{format_size, BINARY, DECIMAL};
+use std::sync::{Arc, Mutex, OnceLock};
+use clap::Parser;
+use tracing::Instrument;
+use url_path::{extract_path, normalize_path, os_bytes};
+use request::extract_header;
 
-#[tokio::main]
-async fn main() -> std::io::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:8080").await?;
-    println!("File Browser running on http://127.0.0.1:8080");
+mod api;
+mod archive;
+mod audit;
+mod checksum;
+mod chunked;
+mod collate;
+mod config;
+mod cors;
+mod daemon;
+mod directory_config;
+mod disk_space;
+mod dns;
+mod du;
+mod file_cache;
+mod glob;
+mod grep;
+mod icons;
+mod listener;
+mod listing;
+mod log;
+mod metrics;
+mod mime;
+mod owners;
+mod privileges;
+mod range;
+mod readme;
+mod relative_time;
+mod request;
+mod sandbox;
+mod search;
+mod sendfile;
+mod session;
+mod systemd;
+mod telemetry;
+mod timestamps;
+mod tree;
+mod url_path;
+mod users;
+mod watch;
+mod webdav;
 
+fn main() -> std::io::Result<()> {
+    let cli = config::Cli::parse();
+    if cli.print_default_config {
+        print!("{}", config::EXAMPLE_CONFIG);
+        return Ok(());
+    }
+    let mut config = tracing::subscriber::with_default(log::subscriber(config::LogFormat::Plain), || {
+        config::Config::load(cli.clone()).unwrap_or_else(|e| {
+            tracing::error!("error: {}", e);
+            std::process::exit(2);
+        })
+    });
+    log::init(config.log_format);
+    if config.verbose {
+        let sources = config.describe_sources();
+        if sources.is_empty() {
+            tracing::info!("Settings: all defaults");
+        } else {
+            tracing::info!("Settings: {}", sources.join(", "));
+        }
+    }
+
+    let identity = privileges::resolve(config.user.as_deref(), config.group.as_deref()).unwrap_or_else(|e| {
+        tracing::error!("Failed to resolve --user/--group: {}", e);
+        std::process::exit(1);
+    });
+
+    let audit = audit::AuditLog::open(config.audit_log_path.as_deref()).unwrap_or_else(|e| {
+        tracing::error!("Failed to open audit log: {}", e);
+        std::process::exit(1);
+    });
+
+    let users = config.users_file.as_deref().map(users::Users::load).transpose().unwrap_or_else(|e| {
+        tracing::error!("error: users file: {}", e);
+        std::process::exit(2);
+    });
+    let sessions = session::Sessions::new().unwrap_or_else(|e| {
+        tracing::error!("error: cannot draw a session secret: {}", e);
+        std::process::exit(1);
+    });
+
+    let resolver = config.resolve_hostnames.then(dns::Resolver::from_system);
+
+    // Taken before binding so that a second instance fails here rather than
+    // joining the first one's SO_REUSEPORT group.
+    let mut pid_file = config.pid_file.as_deref().map(daemon::PidFile::acquire).transpose().unwrap_or_else(|e| {
+        tracing::error!("error: pid file: {}", e);
+        std::process::exit(1);
+    });
+
+    // Under socket activation systemd's sockets replace the default bind
+    // address; addresses configured explicitly are bound in addition.
+    let activated = systemd::activated_listeners().unwrap_or_else(|e| {
+        tracing::error!("error: socket activation: {}", e);
+        std::process::exit(1);
+    });
+    let bind_configured = activated.is_none() || config.is_set("bind");
+    let mut listeners = Vec::new();
+    let mut urls = Vec::new();
+    for listener in activated.into_iter().flatten() {
+        urls.push(listener.url()?);
+        listeners.push(listener);
+    }
+    for addr in config.addrs.iter().filter(|_| bind_configured) {
+        let bound = listener::bind(addr, config.workers, config.unix_socket_mode).unwrap_or_else(|e| {
+            tracing::error!("error: cannot bind {}: {}", addr, e);
+            std::process::exit(1);
+        });
+        urls.push(bound[0].url()?);
+        listeners.extend(bound);
+    }
+
+    let notifier = systemd::Notifier::from_env().unwrap_or_else(|e| {
+        tracing::error!("Failed to open the systemd notification socket: {}", e);
+        std::process::exit(1);
+    });
+
+    let detached = if config.daemon {
+        daemon::detach().unwrap_or_else(|e| {
+            tracing::error!("error: cannot fork into the background: {}", e);
+            std::process::exit(1);
+        })
+    } else {
+        daemon::Detached::foreground()
+    };
+    if let Some(pid_file) = &mut pid_file {
+        if let Err(e) = pid_file.write_pid() {
+            tracing::error!("error: pid file: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Everything that may need root or files outside the document root has to
+    // happen above this line.
+    if config.sandbox {
+        let writes = match (config.allow_write, config.webdav) {
+            (false, _) => sandbox::Writes::Nothing,
+            (true, false) => sandbox::Writes::Directories,
+            (true, true) => sandbox::Writes::Files,
+        };
+        match sandbox::enter(&config.root, writes, identity.as_ref().is_some_and(privileges::Identity::leaves_root)) {
+            Ok(mechanism) => {
+                tracing::info!("Sandboxed using {}", mechanism);
+                if mechanism == "chroot" {
+                    config.root = PathBuf::from("/");
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to enter sandbox: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(identity) = identity {
+        if let Err(e) = identity.apply() {
+            tracing::error!("Failed to drop privileges: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // The runtime is only built now so that its worker threads inherit the
+    // sandbox and the reduced privileges.
+    let _ = BASE_URL.set(config.base_url.clone());
+    let _ = SECURITY_HEADERS.set(config.security_headers);
+    let _ = CONTENT_SECURITY_POLICY.set(config.csp.clone());
+    let state = Arc::new(ServerState {
+        urls,
+        audit,
+        users: arc_swap::ArcSwapOption::from_pointee(users),
+        sessions,
+        metrics: metrics::Metrics::new(&config.histogram_buckets),
+        file_cache: Mutex::new(file_cache::FileCache::new(
+            config.file_cache_entries,
+            config.file_cache_max_size,
+            config.file_cache_ttl,
+        )),
+        listing_cache: listing::ListingCache::new(256, config.listing_cache_ttl, collation(&config, "")),
+        dir_sizes: Arc::new(du::DirSizes::new(1024, config.listing_cache_ttl)),
+        checksums: checksum::Checksums::new(1024),
+        resolver,
+        connections: tokio_util::task::TaskTracker::new(),
+        shutdown: tokio_util::sync::CancellationToken::new(),
+        config: arc_swap::ArcSwap::from_pointee(config),
+    });
+    let runtime = tokio::runtime::Runtime::new()?;
+    // Started here, in the process that serves, since the exporter has a
+    // thread of its own that would not survive `daemon`'s fork.
+    let tracer_provider = state.config.load().otel_endpoint.as_deref().map(|endpoint| {
+        let _runtime = runtime.enter();
+        telemetry::start(endpoint).unwrap_or_else(|e| {
+            tracing::error!("error: {}", e);
+            std::process::exit(2);
+        })
+    });
+    let result = runtime.block_on(serve(listeners, notifier, detached, cli, state));
+    if let Some(tracer_provider) = tracer_provider {
+        if let Err(e) = tracer_provider.shutdown() {
+            tracing::error!("Failed to export the last traces: {}", e);
+        }
+    }
+    // Whatever is still running after the grace period is abandoned rather
+    // than waited for.
+    runtime.shutdown_background();
+    drop(pid_file);
+    result
+}
+
+// Shared by every connection task.
+struct ServerState {
+    // Replaced as a whole when the configuration is reloaded; a request works
+    // with the snapshot it started with.
+    config: arc_swap::ArcSwap<config::Config>,
+    // Where the listeners actually ended up, one per configured address;
+    // these differ from `config.addrs` when port 0 asked for ephemeral ports.
+    urls: Vec<String>,
+    audit: audit::AuditLog,
+    // Accounts from `users_file`, when requests have to be authenticated.
+    // Read again on reload.
+    users: arc_swap::ArcSwapOption<users::Users>,
+    // Signs the cookies of those who signed in at LOGIN_PATH.
+    sessions: session::Sessions,
+    metrics: metrics::Metrics,
+    file_cache: Mutex<file_cache::FileCache>,
+    listing_cache: listing::ListingCache,
+    dir_sizes: Arc<du::DirSizes>,
+    checksums: checksum::Checksums,
+    // For `resolve_hostnames`.
+    resolver: Option<dns::Resolver>,
+    // Every connection task, so shutdown can wait for them to finish.
+    connections: tokio_util::task::TaskTracker,
+    // Cancelled when shutdown begins; open-ended streams (watches) end on it.
+    shutdown: tokio_util::sync::CancellationToken,
+}
+
+async fn serve(
+    listeners: Vec<listener::Listener>,
+    notifier: systemd::Notifier,
+    mut detached: daemon::Detached,
+    cli: config::Cli,
+    state: Arc<ServerState>,
+) -> std::io::Result<()> {
+    let mut accept_loops = tokio::task::JoinSet::new();
+    for listener in listeners {
+        match listener {
+            listener::Listener::Tcp(listener) => {
+                accept_loops.spawn(accept_loop(TcpListener::from_std(listener)?, state.clone()));
+            }
+            #[cfg(unix)]
+            listener::Listener::Unix(listener, path) => {
+                accept_loops.spawn(accept_unix_loop(tokio::net::UnixListener::from_std(listener)?, path, state.clone()));
+            }
+        }
+    }
+    for url in &state.urls {
+        tracing::info!("File Browser running on {} serving {}", url, state.config.load().root.display());
+    }
+    for (host, vhost) in &state.config.load().vhosts {
+        tracing::info!("Virtual host {} serving {}", host, vhost.root.display());
+    }
+    // Listening before anyone is told the server is up: until then SIGHUP
+    // still kills the process.
+    #[cfg(unix)]
+    match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(hangup) => {
+            tokio::spawn(reload_on_hangup(hangup, cli, state.clone()));
+        }
+        Err(e) => tracing::error!("Failed to listen for SIGHUP: {}", e),
+    }
+    #[cfg(not(unix))]
+    drop(cli);
+    // Last lines of startup output, for scripts and tests that start the
+    // server on port 0 and need to know where to connect.
+    for url in &state.urls {
+        println!("LISTENING {}", url);
+    }
+    notifier.ready();
+    detached.ready();
+
+    // Accept loops only return on error; bring the whole server down with it.
+    tokio::select! {
+        result = accept_loops.join_next() => {
+            return match result {
+                Some(Ok(result)) => result,
+                Some(Err(e)) => Err(std::io::Error::other(e)),
+                None => Ok(()),
+            };
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    // Dropping the accept loops closes the listeners, so new connections are
+    // refused while the ones already accepted run to completion.
+    accept_loops.shutdown().await;
+    state.shutdown.cancel();
+    state.connections.close();
+    let grace = state.config.load().shutdown_grace;
+    tracing::info!("Shutting down, waiting up to {:?} for {} requests to finish", grace, state.connections.len());
+    tokio::select! {
+        _ = state.connections.wait() => tracing::info!("All requests finished"),
+        _ = tokio::time::sleep(grace) => {
+            tracing::error!("Grace period over, abandoning {} requests", state.connections.len());
+        }
+        _ = shutdown_signal() => {
+            tracing::error!("Second signal, abandoning {} requests", state.connections.len());
+        }
+    }
+    Ok(())
+}
+
+// Re-reads the configuration on every SIGHUP. A configuration that fails to
+// load or validate is reported and the running one stays in place.
+#[cfg(unix)]
+async fn reload_on_hangup(mut hangup: tokio::signal::unix::Signal, cli: config::Cli, state: Arc<ServerState>) {
+    while hangup.recv().await.is_some() {
+        match config::Config::reload(&cli, &state.config.load()) {
+            Ok(config) => {
+                // A users file that no longer loads leaves the accounts as
+                // they were.
+                match config.users_file.as_deref().map(users::Users::load).transpose() {
+                    Ok(users) => state.users.store(users.map(Arc::new)),
+                    Err(e) => tracing::error!("Users file not reloaded: {}", e),
+                }
+                tracing::info!("Configuration reloaded");
+                if config.verbose {
+                    tracing::info!("Settings: {}", config.describe_sources().join(", "));
+                }
+                state.config.store(Arc::new(config));
+            }
+            Err(e) => tracing::error!("Configuration not reloaded: {}", e),
+        }
+    }
+}
+
+// SIGINT (Ctrl-C) or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::error!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+async fn accept_loop(listener: TcpListener, state: Arc<ServerState>) -> std::io::Result<()> {
     loop {
         let (socket, addr) = listener.accept().await?;
-        println!("New connection: {:?}", addr);
-        tokio::spawn(handle_connection(socket));
+        tracing::debug!("New connection: {:?}", addr);
+        let hostname = state.resolver.as_ref().map(|resolver| resolver.lookup(addr.ip()));
+        state.connections.spawn(handle_connection(socket, listener::Peer::Tcp(addr, hostname), state.clone()));
+    }
+}
+
+// Unix peers have no address; they are identified by the socket they came in
+// on and, where available, the connecting process's credentials.
+#[cfg(unix)]
+async fn accept_unix_loop(listener: tokio::net::UnixListener, path: PathBuf, state: Arc<ServerState>) -> std::io::Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let peer = match socket.peer_cred() {
+            Ok(cred) => match cred.pid() {
+                Some(pid) => format!("unix:{} uid={} pid={}", path.display(), cred.uid(), pid),
+                None => format!("unix:{} uid={}", path.display(), cred.uid()),
+            },
+            Err(_) => format!("unix:{}", path.display()),
+        };
+        tracing::debug!("New connection: {}", peer);
+        state.connections.spawn(handle_connection(socket, listener::Peer::Unix(peer), state.clone()));
+    }
+}
+
+// The configuration is loaded once, as the connection arrives, and serves its
+// request to the end; a reload only affects the connections after it.
+async fn handle_connection<S: listener::Connection>(socket: S, peer: listener::Peer, state: Arc<ServerState>) {
+    let config = state.config.load_full();
+    let limit = config.request_timeout;
+    if tokio::time::timeout(limit, handle_request(socket, peer, state, config)).await.is_err() {
+        tracing::error!("Request took longer than {:?}, closing connection", limit);
+    }
+}
+
+async fn handle_request<S: listener::Connection>(mut socket: S, peer: listener::Peer, state: Arc<ServerState>, config: Arc<config::Config>) {
+    let head = match tokio::time::timeout(config.header_timeout, request::read_head(&mut socket)).await {
+        Ok(head) => head,
+        Err(_) => {
+            let response = http_response("408 Request Timeout", "Connection: close\r\n", "");
+            if let Err(e) = socket.write_all(&response.to_bytes(true, &config.headers_for(Path::new("/")))).await {
+                tracing::error!("Failed to write to socket: {}", e);
+            }
+            return;
+        }
+    };
+    let (request, leftover, started) = match head {
+        Ok(Some(head)) => head,
+        Ok(None) => {
+            tracing::debug!("Connection closed by peer.");
+            return;
+        }
+        // A head that is too large or malformed is answered, since the
+        // client is still there to be told.
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            tracing::debug!("Refused request: {}", e);
+            let response = http_response("400 Bad Request", "Connection: close\r\n", "");
+            if let Err(e) = socket.write_all(&response.to_bytes(true, &config.headers_for(Path::new("/")))).await {
+                tracing::error!("Failed to write to socket: {}", e);
+            }
+            return;
+        }
+        Err(e) => {
+            tracing::error!("Failed to read from socket: {}", e);
+            return;
+        }
+    };
+    let span = telemetry::request_span(&request);
+    respond(socket, peer, state, config, request, leftover, started).instrument(span).await;
+}
+
+// The access line of a request. While the client's host name is still being
+// looked up, it is written once the lookup is done by a task of its own, so
+// that the response goes out in the meantime.
+fn log_access(state: &ServerState, peer: &listener::Peer, method: &str, target: &str, status: &'static str) {
+    let Some(mut lookup) = peer.pending_lookup() else {
+        tracing::info!(kind = "access", peer = %peer, method, path = target, status = status_code(status), "{}", status);
+        return;
+    };
+    let (peer, method, target) = (peer.clone(), method.to_string(), target.to_string());
+    let logged = async move {
+        lookup.done().await;
+        tracing::info!(kind = "access", peer = %peer, method, path = target, status = status_code(status), "{}", status);
+    };
+    state.connections.spawn(logged.instrument(tracing::Span::current()));
+}
+
+// Everything after the request head, inside the request's span.
+async fn respond<S: listener::Connection>(
+    mut socket: S,
+    peer: listener::Peer,
+    state: Arc<ServerState>,
+    config: Arc<config::Config>,
+    request: String,
+    leftover: Vec<u8>,
+    started: tokio::time::Instant,
+) {
+    // Observed when this function returns, after the last write.
+    let mut timer = state.metrics.request_duration.start_timer(started);
+
+    let method = extract_method(&request);
+    let target = extract_target(&request);
+    // Requests outside the base URL are answered with 404 below.
+    let local_target = strip_base_url(target);
+    let query = extract_query(local_target.unwrap_or("/"));
+    let path = extract_path(local_target.unwrap_or("/"));
+    let names_directory = local_target.is_some_and(|target| target.split('?').next().unwrap_or("").ends_with('/'));
+    // Hosts not served here at all are refused with 421 below.
+    let site = config.site(extract_header(&request, "Host"));
+    let misdirected = site.is_none();
+    let site = site.unwrap_or_else(|| config.main_site());
+    let origin = extract_header(&request, "Origin");
+    let cors_headers = config.cors.response_headers(origin);
+    let server_headers = config.headers_for(&path);
+    // Below here a WebDAV request is about the path it names in the site, so
+    // that users, aliases and directory settings apply to it as they do to
+    // any other.
+    let (path, dav) = match path.strip_prefix(DAV_PATH) {
+        Ok(relative) if config.webdav => (Path::new("/").join(relative), true),
+        _ => (path, false),
+    };
+
+    // Taken once, so that a reload does not change them half way through.
+    let users = state.users.load_full();
+    let login = users.is_some() && !dav && path == Path::new(LOGIN_PATH);
+
+    // A WebDAV PUT writes its body to disk once the request has been checked
+    // below, and a login form is read there too. Any other body is read and
+    // discarded now, within the size limit, so that closing the socket after
+    // the response does not reset the connection under the client.
+    let drained = match request::Body::new(
+        extract_header(&request, "Content-Length"),
+        extract_header(&request, "Transfer-Encoding"),
+        leftover,
+        if login { config.max_body_size.min(LOGIN_FORM_LIMIT) } else { config.max_body_size },
+    ) {
+        Ok(body) if (dav && method == "PUT") || (login && method == "POST") => Ok(body),
+        Ok(mut body) => body.drain(&mut socket).await.map(|()| body),
+        Err(e) => Err(e),
+    };
+    let mut body = match drained {
+        Ok(body) => body,
+        Err(e) => {
+            let mut response = if request::is_body_too_large(&e) {
+                http_response("413 Content Too Large", "Connection: close\r\n", "")
+            } else {
+                tracing::error!("Failed to read request body: {}", e);
+                http_response("400 Bad Request", "Connection: close\r\n", "")
+            };
+            response.headers.push_str(&cors_headers);
+            if let Err(e) = socket.write_all(&response.to_bytes(true, &server_headers)).await {
+                tracing::error!("Failed to write to socket: {}", e);
+            }
+            return;
+        }
+    };
+
+    // CORS preflights are sent without credentials, so they are answered
+    // without asking for them; the login form is where they are given.
+    let (mut user, authorization) = match &users {
+        Some(users) if method != "OPTIONS" && !login => authorize(users, &state.sessions, &request, &path, query).await,
+        _ => (None, Ok(())),
+    };
+
+    let directory = directory_config::DirectoryConfig::for_path(&config, &site, &path).await;
+    let archive_format = query_param(query, "download").and_then(|value| archive::Format::from_query(&value));
+    if let (Some(format), "GET", Some(_), false, true) = (archive_format, method, local_target, misdirected, authorization.is_ok()) {
+        let full_path = site.resolve(&path);
+        if fs::metadata(&full_path).await.map(|m| m.is_dir()).unwrap_or(false) {
+            telemetry::record_status(200);
+            let bytes = send_archive(socket, &full_path, directory, format, &cors_headers, &server_headers, chunked::accepted_by(&request)).await;
+            // Like a file sent, whether or not the archive got through whole.
+            state.audit.record_access(&audit::Record {
+                remote_ip: &peer.host(),
+                user: user.as_deref(),
+                method,
+                path: target,
+                status: 200,
+                bytes,
+                rule: "",
+            });
+            if config.verbose {
+                log_access(&state, &peer, method, target, "200 OK");
+            }
+            return;
+        }
+    }
+
+    let wants_json = api::prefers_json(extract_header(&request, "Accept"));
+    let mut response = match method {
+        _ if local_target.is_none() => html_response(
+            "404 Not Found",
+            generate_error_page("404 - Path Not Found", "The requested path could not be found."),
+        )
+        .with_rule("outside_base_url"),
+        _ if misdirected => html_response(
+            "421 Misdirected Request",
+            generate_error_page("421 - Misdirected Request", "This server does not serve the requested host."),
+        )
+        .with_rule("unknown_host"),
+        // Scripts get the refusal as JSON, with any WWW-Authenticate kept.
+        _ if authorization.is_err() && is_api(&path) => {
+            let page = authorization.unwrap_err();
+            let reason = page.status.split_once(' ').map_or(page.status, |(_, reason)| reason);
+            let mut response = api_error(page.status, reason).with_rule(page.rule);
+            for challenge in page.headers.split("\r\n").filter(|line| line.starts_with("WWW-Authenticate:")) {
+                response.headers.push_str(&format!("{}\r\n", challenge));
+            }
+            response
+        }
+        _ if authorization.is_err() => authorization.unwrap_err(),
+        // Directory settings are for the server, not for its visitors.
+        _ if path.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) => html_response(
+            "404 Not Found",
+            generate_error_page("404 - Path Not Found", "The requested path could not be found."),
+        )
+        .with_rule("directory_config"),
+        "GET" | "HEAD" if login => login_page("200 OK", ""),
+        "POST" if login => {
+            let (name, response) = log_in(&mut socket, &mut body, &state).await;
+            user = name;
+            response
+        }
+        "PUT" | "MKCOL" | "DELETE" | "COPY" | "MOVE" if !config.allow_write && (dav || method == "PUT") => html_response(
+            "403 Forbidden",
+            generate_error_page("403 - Forbidden", "This server does not accept changes."),
+        )
+        .with_rule("writes_disabled"),
+        "PUT" | "MKCOL" | "DELETE" | "MOVE" if directory.read_only && (dav || method == "PUT") => html_response(
+            "403 Forbidden",
+            generate_error_page("403 - Forbidden", "This site is read-only."),
+        )
+        .with_rule("read_only"),
+        "OPTIONS" if dav => http_response("200 OK", &format!("DAV: 1\r\nAllow: {}\r\n", webdav::allowed(&config)), ""),
+        "PROPFIND" if dav => propfind(&site, &directory, &path, extract_header(&request, "Depth")).await,
+        "PUT" if dav => dav_put(&mut socket, &mut body, &site, &directory, &path, &request).await,
+        "MKCOL" if dav => dav_mkcol(&site, &path, &request).await,
+        "DELETE" if dav => dav_delete(&site, &directory, &path).await,
+        "COPY" | "MOVE" if dav => dav_copy(&state, &site, &directory, &path, &request, user.as_deref()).await,
+        // A directory is listed as a plain GET would list it, but without
+        // the redirect to its name with a slash, which would leave DAV_PATH.
+        "GET" | "HEAD" if dav => raw_file(&state, &site, &directory, &path, query, true, wants_json).await,
+        _ if dav => http_response("405 Method Not Allowed", &format!("Allow: {}\r\n", webdav::allowed(&config)), ""),
+        "GET" | "HEAD" if path == Path::new(SEARCH_PATH) => search(&site, query, wants_json).await,
+        "GET" | "HEAD" if path == Path::new(API_LS_PATH) => list_api(&state, &site, query).await,
+        "GET" | "HEAD" if path == Path::new(API_STAT_PATH) => stat_api(&site, query).await,
+        "GET" | "HEAD" if path == Path::new(API_HASH_PATH) => hash_api(&state, &site, query).await,
+        "GET" | "HEAD" if path == Path::new(API_GREP_PATH) => grep_api(&site, query).await,
+        "GET" | "HEAD" if path == Path::new(API_TREE_PATH) => tree_api(&site, query).await,
+        "GET" | "HEAD" if query_param(query, "raw").is_some() => {
+            raw_file(&state, &site, &directory, &path, query, names_directory, wants_json).await
+        }
+        "OPTIONS" => {
+            let requested_headers = extract_header(&request, "Access-Control-Request-Headers");
+            match config.cors.preflight_headers(origin, requested_headers) {
+                Some(headers) => http_response("204 No Content", &headers, ""),
+                None => http_response("204 No Content", &format!("Allow: {}\r\n", allowed_methods(&config)), ""),
+            }
+        }
+        "PUT" if target.ends_with('/') => create_directory(&site, &path, target).await,
+        "GET" if path == Path::new(EVENTS_PATH) => match watched_directory(&site, query).await {
+            Ok(dir) => {
+                let mut head = "HTTP/1.1 200 OK\r\n\
+                    Content-Type: text/event-stream\r\n\
+                    Cache-Control: no-cache\r\n\
+                    X-Accel-Buffering: no\r\n\
+                    Connection: close\r\n"
+                    .to_string();
+                head.push_str(&cors_headers);
+                add_server_headers(&mut head, &server_headers);
+                telemetry::record_status(200);
+                if config.verbose {
+                    log_access(&state, &peer, method, target, "200 OK");
+                }
+                if let Err(e) = socket.write_all(format!("{}\r\n", head).as_bytes()).await {
+                    tracing::error!("Failed to write to socket: {}", e);
+                    return;
+                }
+                timer.discard();
+                if let Err(e) = watch::stream_server_sent_events(&mut socket, &dir, &state.shutdown).await {
+                    tracing::error!("Watch of {} ended: {}", dir.display(), e);
+                }
+                return;
+            }
+            Err(response) => response,
+        },
+        "GET" if path == Path::new(WATCH_PATH) => match open_watch(&site, &request, query).await {
+            Ok((accept, dir)) => {
+                let mut handshake = format!(
+                    "HTTP/1.1 101 Switching Protocols\r\n\
+                    Upgrade: websocket\r\n\
+                    Connection: Upgrade\r\n\
+                    Sec-WebSocket-Accept: {}\r\n",
+                    accept
+                );
+                add_server_headers(&mut handshake, &server_headers);
+                handshake.push_str("\r\n");
+                telemetry::record_status(101);
+                if config.verbose {
+                    log_access(&state, &peer, method, target, "101 Switching Protocols");
+                }
+                if let Err(e) = socket.write_all(handshake.as_bytes()).await {
+                    tracing::error!("Failed to write to socket: {}", e);
+                    return;
+                }
+                timer.discard();
+                if let Err(e) = watch::stream_events(&mut socket, &dir, &state.shutdown).await {
+                    tracing::error!("Watch of {} ended: {}", dir.display(), e);
+                }
+                return;
+            }
+            Err(response) => response,
+        },
+        "GET" | "HEAD" if path == Path::new(METRICS_PATH) => http_response(
+            "200 OK",
+            "Content-Type: text/plain; version=0.0.4; charset=utf-8\r\nCache-Control: no-store\r\n",
+            state.metrics.render(),
+        ),
+        "GET" | "HEAD" => generate_response(&state, &site, &directory, &path, query, names_directory, wants_json).await,
+        _ => http_response("405 Method Not Allowed", &format!("Allow: {}\r\n", allowed_methods(&config)), ""),
+    };
+    // What a refused WebDAV PUT left unread.
+    if let Err(e) = body.drain(&mut socket).await {
+        tracing::debug!("Failed to read request body: {}", e);
+    }
+    if let Some((file, metadata)) = response.file.take() {
+        let sent = match refuse_file(&directory, &file, &metadata) {
+            Some(refusal) => Err(refusal),
+            None => send_file(&mut socket, &state, &config, &file, &metadata, &request, &cors_headers).await,
+        };
+        match sent {
+            Ok((status, bytes)) => {
+                telemetry::record_status(status_code(status));
+                state.audit.record_access(&audit::Record {
+                    remote_ip: &peer.host(),
+                    user: user.as_deref(),
+                    method,
+                    path: target,
+                    status: status_code(status),
+                    bytes: Some(bytes),
+                    rule: "",
+                });
+                return;
+            }
+            Err(error) => response = error,
+        }
+    }
+    // Only raw file downloads support ranges; they set their own header.
+    if !response.headers.contains("Accept-Ranges:") {
+        response.headers.push_str("Accept-Ranges: none\r\n");
+    }
+    if method != "OPTIONS" {
+        response.headers.push_str(&cors_headers);
+    }
+    state.audit.record(&audit::Record {
+        remote_ip: &peer.host(),
+        user: user.as_deref(),
+        method,
+        path: target,
+        status: response.status_code(),
+        bytes: None,
+        rule: response.rule,
+    });
+    telemetry::record_status(response.status_code());
+    if config.verbose {
+        log_access(&state, &peer, method, target, response.status);
+    }
+
+    response.chunked = chunked::accepted_by(&request);
+    if let Err(e) = socket.write_all(&response.to_bytes(method != "HEAD", &server_headers)).await {
+        tracing::error!("Failed to write to socket: {}", e);
+        return;
+    }
+    if let (Some(stream), true) = (response.stream, method != "HEAD") {
+        let streamed = if response.chunked {
+            let mut writer = chunked::ChunkedWriter::new(&mut socket);
+            match stream.write_to(&mut writer, &site).await {
+                Ok(()) => writer.shutdown().await,
+                Err(e) => Err(e),
+            }
+        } else {
+            stream.write_to(&mut socket, &site).await
+        };
+        if let Err(e) = streamed {
+            tracing::error!("Failed to stream response: {}", e);
+        }
+    }
+}
+
+// With a users file, a request needs Basic credentials or the session
+// cookie of an account whose roots include its path; for a watch, the path
+// of the watched directory. Basic credentials win over a cookie. Also
+// returns the user name the request gave, checked or not, for the audit log.
+async fn authorize(
+    users: &Arc<users::Users>,
+    sessions: &session::Sessions,
+    request: &str,
+    path: &Path,
+    query: &str,
+) -> (Option<String>, Result<(), Response>) {
+    let credentials = users::credentials(extract_header(request, "Authorization"));
+    let name = credentials.as_ref().map(|(name, _)| name.clone());
+    let verified = match credentials {
+        Some((name, password)) => {
+            let users = Arc::clone(users);
+            tokio::task::spawn_blocking(move || users.verify(&name, &password)).await.unwrap_or(false)
+        }
+        None => false,
+    };
+    let session = match name {
+        None => session::token(extract_header(request, "Cookie")).and_then(|token| sessions.verify(token)),
+        Some(_) => None,
+    };
+    let Some(name) = name.clone().filter(|_| verified).or(session) else {
+        let mut response = html_response(
+            "401 Unauthorized",
+            generate_error_page(
+                "401 - Unauthorized",
+                &format!("A user name and password are needed to access this server. <a href=\"{}\">Sign in</a>", link(LOGIN_PATH)),
+            ),
+        )
+        .with_rule("unauthenticated");
+        response.headers.push_str("WWW-Authenticate: Basic realm=\"gredl_server\", charset=\"UTF-8\"\r\n");
+        return (name, Err(response));
+    };
+    let path = if takes_path_param(path) {
+        path_param(query)
+    } else {
+        path.to_path_buf()
+    };
+    if !users.may_access(&name, &path) {
+        let response = html_response(
+            "403 Forbidden",
+            generate_error_page("403 - Forbidden", "Your account does not have access to this path."),
+        )
+        .with_rule("outside_user_roots");
+        return (Some(name), Err(response));
+    }
+    (Some(name), Ok(()))
+}
+
+// A POST of the login form at LOGIN_PATH. The right user name and password
+// get a session cookie and are sent on to the site; anything else gets the
+// form again. Also returns the user name given, for the audit log.
+async fn log_in<S: listener::Connection>(socket: &mut S, body: &mut request::Body, state: &ServerState) -> (Option<String>, Response) {
+    let mut form = Vec::new();
+    loop {
+        match body.next_chunk(socket).await {
+            Ok(Some(chunk)) => form.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) if request::is_body_too_large(&e) => return (None, http_response("413 Content Too Large", "Connection: close\r\n", "")),
+            Err(e) => {
+                tracing::debug!("Failed to read request body: {}", e);
+                return (None, http_response("400 Bad Request", "Connection: close\r\n", ""));
+            }
+        }
+    }
+    let form = String::from_utf8_lossy(&form);
+    let name = query_param(&form, "username").unwrap_or_default();
+    let password = query_param(&form, "password").unwrap_or_default();
+    let verified = match state.users.load_full() {
+        Some(users) => {
+            let name = name.clone();
+            tokio::task::spawn_blocking(move || users.verify(&name, &password)).await.unwrap_or(false)
+        }
+        None => false,
+    };
+    let user = Some(name.clone()).filter(|name| !name.is_empty());
+    if !verified {
+        return (user, login_page("403 Forbidden", "Wrong user name or password.").with_rule("login_failed"));
+    }
+    match state.sessions.issue(&name) {
+        Ok(token) => {
+            let headers = format!(
+                "Location: {}\r\nSet-Cookie: {}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Lax\r\n",
+                link("/"),
+                session::COOKIE_NAME,
+                token,
+                link("/"),
+                session::LIFETIME.as_secs()
+            );
+            (user, http_response("303 See Other", &headers, ""))
+        }
+        Err(e) => {
+            tracing::error!("Failed to issue a session: {}", e);
+            (user, http_response("500 Internal Server Error", "", ""))
+        }
+    }
+}
+
+// The login form, with `message` above it when there is one.
+fn login_page(status: &'static str, message: &str) -> Response {
+    let message = if message.is_empty() {
+        String::new()
+    } else {
+        format!("<p class=\"error\">{}</p>", escape_html(message))
+    };
+    html_response(
+        status,
+        format!(
+            r#"<!DOCTYPE html>
+        <html>
+        <head>
+            <title>Sign in</title>
+            <style>
+                body {{ font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif; margin: 40px; }}
+                .error {{ color: #dc3545; }}
+                label {{ display: block; margin: 8px 0; }}
+            </style>
+        </head>
+        <body>
+            <h1>Sign in</h1>
+            {}
+            <form method="post" action="{}">
+                <label>User name <input name="username" autocomplete="username" required></label>
+                <label>Password <input name="password" type="password" autocomplete="current-password" required></label>
+                <button type="submit">Sign in</button>
+            </form>
+        </body>
+        </html>"#,
+            message,
+            link(LOGIN_PATH)
+        ),
+    )
+}
+
+// The methods of requests outside DAV_PATH; PUT only creates directories,
+// and only with `allow_write`.
+fn allowed_methods(config: &config::Config) -> &'static str {
+    if config.allow_write {
+        "GET, HEAD, PUT, OPTIONS"
+    } else {
+        "GET, HEAD, OPTIONS"
+    }
+}
+
+fn extract_method(request: &str) -> &str {
+    request.lines()
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or("GET")
+}
+
+fn extract_target(request: &str) -> &str {
+    request.lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/")
+}
+
+fn extract_query(target: &str) -> &str {
+    target.split_once('?').map(|(_, query)| query).unwrap_or("")
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().to_string())
+}
+
+// URL path the server is mounted under (`base_url`), without a trailing
+// slash, or empty. It cannot change while the server runs, so it is kept here
+// rather than threaded through to every page that links somewhere.
+static BASE_URL: OnceLock<String> = OnceLock::new();
+
+// Whether responses carry the headers of `add_security_headers`; turned off
+// with --no-security-headers when a proxy in front sets its own. Fixed at
+// startup like `BASE_URL`.
+static SECURITY_HEADERS: OnceLock<bool> = OnceLock::new();
+
+// `Content-Security-Policy` of generated pages (`csp`), or empty for none.
+static CONTENT_SECURITY_POLICY: OnceLock<String> = OnceLock::new();
+
+// Headers every response carries: the security headers, then the configured
+// ones, from `Config::headers_for`. A configured header replaces the security
+// header of the same name, and gives way to one the response already has,
+// such as the `no-store` `Cache-Control` of the metrics.
+fn add_server_headers(headers: &mut String, configured: &[(&str, &str)]) {
+    let has_header = |headers: &str, name: &str| {
+        headers.split("\r\n").any(|line| line.split_once(':').is_some_and(|(line_name, _)| line_name.eq_ignore_ascii_case(name)))
+    };
+    let mut security = String::new();
+    add_security_headers(&mut security);
+    for line in security.split_terminator("\r\n") {
+        if !configured.iter().any(|(name, _)| has_header(line, name)) {
+            headers.push_str(&format!("{}\r\n", line));
+        }
+    }
+    for (name, value) in configured {
+        if !has_header(headers, name) {
+            headers.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+}
+
+// Hardening headers every response carries: no MIME sniffing of served files,
+// no framing by other sites, and no full URLs leaking to other origins.
+fn add_security_headers(headers: &mut String) {
+    if SECURITY_HEADERS.get().copied().unwrap_or(true) {
+        headers.push_str(
+            "X-Content-Type-Options: nosniff\r\n\
+            X-Frame-Options: SAMEORIGIN\r\n\
+            Referrer-Policy: strict-origin-when-cross-origin\r\n",
+        );
+    }
+}
+
+// The request target with the base URL taken off the front, or None if the
+// target is outside it.
+fn strip_base_url(target: &str) -> Option<&str> {
+    let base_url = BASE_URL.get().map(String::as_str).unwrap_or("");
+    match target.strip_prefix(base_url)? {
+        rest if rest.is_empty() || rest.starts_with(['/', '?']) => Some(rest),
+        _ => None,
+    }
+}
+
+// Bytes that cannot appear literally in a path segment: the URL standard's
+// path percent-encode set, plus `%` so names round-trip, `&` so that a link
+// means the same inside an HTML attribute, `/` and `\` (which browsers read
+// as `/`), and the rest of the characters that are not allowed in URLs at
+// all. Non-ASCII bytes are always encoded.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+// Href for a server path such as `/docs/a b.txt`: under the base URL, with
+// each segment percent-encoded and joined by literal slashes. Every generated
+// link and redirect goes through here. Names are encoded from their bytes on
+// disk, so one that is not UTF-8 still links to itself.
+fn link(url_path: impl AsRef<OsStr>) -> String {
+    let base_url = BASE_URL.get().map(String::as_str).unwrap_or("");
+    let segments: Vec<String> = os_bytes(url_path.as_ref())
+        .split(|byte| *byte == b'/')
+        .map(|segment| percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect();
+    format!("{}{}", base_url, segments.join("/"))
+}
+
+// `link` for a directory: its path with a trailing slash.
+fn directory_link(url_path: &Path) -> String {
+    let mut url_path = url_path.as_os_str().to_owned();
+    if url_path != "/" {
+        url_path.push("/");
+    }
+    link(url_path)
+}
+
+// WebSocket and Server-Sent Events endpoints streaming change events for the
+// directory named by the `path` query parameter. Like any request they are cut
+// off after the request timeout; clients are expected to reconnect.
+const WATCH_PATH: &str = "/_ws/watch";
+const EVENTS_PATH: &str = "/_events/watch";
+
+// Directory listings as JSON for scripts; see `list_api`. Versioned, so
+// that a changed schema can come at a new address beside the old one.
+const API_LS_PATH: &str = "/api/v1/ls";
+
+// Recursive name search; see `search`. Unlike the other endpoints it is a
+// page for people, at an address meant to be typed.
+const SEARCH_PATH: &str = "/search";
+
+// The metadata of one path as JSON; see `stat_api`.
+const API_STAT_PATH: &str = "/api/v1/stat";
+
+// The digest of one file as JSON; see `hash_api`.
+const API_HASH_PATH: &str = "/api/v1/hash";
+
+// Lines of files under a directory, as JSON lines; see `grep_api`.
+const API_GREP_PATH: &str = "/api/v1/grep";
+
+// What is under a directory, as nested JSON; see `tree_api`.
+const API_TREE_PATH: &str = "/api/v1/tree";
+
+// Whether `path` is one of the JSON endpoints, whose errors are JSON too.
+fn is_api(path: &Path) -> bool {
+    [API_LS_PATH, API_STAT_PATH, API_HASH_PATH, API_GREP_PATH, API_TREE_PATH].iter().any(|endpoint| path == Path::new(endpoint))
+}
+
+// Whether the endpoint at `path` acts on the path in its `path` query
+// parameter, which is then the one access is checked against.
+fn takes_path_param(path: &Path) -> bool {
+    [WATCH_PATH, EVENTS_PATH, SEARCH_PATH].iter().any(|endpoint| path == Path::new(endpoint)) || is_api(path)
+}
+
+// The `path` query parameter as a URL path, normalized like the path of a
+// request, so that it resolves to the same file a request for it would.
+fn path_param(query: &str) -> PathBuf {
+    normalize_path(Path::new(&query_param(query, "path").unwrap_or_default()))
+}
+
+// Prometheus metrics. Like the watch endpoints it sits under a `/_` prefix,
+// where it is less likely to shadow a file.
+const METRICS_PATH: &str = "/_metrics";
+
+// The form that signs a browser in with a session cookie, when there is a
+// users file; see `log_in`.
+const LOGIN_PATH: &str = "/_login";
+
+// A login form is a user name and a password; anything much larger is not
+// one.
+const LOGIN_FORM_LIMIT: u64 = 8 * 1024;
+
+// Where the site is served again over WebDAV, with `webdav`; see `webdav`.
+const DAV_PATH: &str = "/_dav";
+
+// Validates a watch request. On success returns the `Sec-WebSocket-Accept`
+// value and the directory to watch; otherwise the response to send instead.
+async fn open_watch(site: &config::Site<'_>, request: &str, query: &str) -> Result<(String, PathBuf), Response> {
+    let is_upgrade = extract_header(request, "Upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if !is_upgrade || extract_header(request, "Sec-WebSocket-Version") != Some("13") {
+        return Err(http_response(
+            "426 Upgrade Required",
+            "Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\nConnection: Upgrade\r\n",
+            "",
+        ));
+    }
+    let Some(key) = extract_header(request, "Sec-WebSocket-Key") else {
+        return Err(http_response("400 Bad Request", "", ""));
+    };
+    Ok((watch::accept_key(key), watched_directory(site, query).await?))
+}
+
+// The directory a watch request asks for, or a 404 response.
+async fn watched_directory(site: &config::Site<'_>, query: &str) -> Result<PathBuf, Response> {
+    let url_path = path_param(query);
+    let dir = site.resolve(&url_path);
+    if !fs::metadata(&dir).await.map(|metadata| metadata.is_dir()).unwrap_or(false) {
+        return Err(html_response(
+            "404 Not Found",
+            generate_error_page("404 - Path Not Found", "The requested directory could not be found."),
+        )
+        .with_rule("not_found"));
+    }
+    Ok(dir)
+}
+
+// `names_directory` says whether the request path ended in `/`. Directories
+// are only listed under such a path, so that relative links on the page
+// resolve inside the directory rather than next to it. A directory holding one
+// of the index files is answered with the first of them instead, unless
+// `serve_index` is off.
+async fn generate_response(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    directory: &directory_config::DirectoryConfig,
+    requested_path: &Path,
+    query: &str,
+    names_directory: bool,
+    json: bool,
+) -> Response {
+    let full_path = site.resolve(requested_path);
+
+    let (status, html_content, rule) = match fs::metadata(&full_path).await {
+        Ok(metadata) => {
+            if metadata.is_dir() && !names_directory {
+                let mut location = directory_link(requested_path);
+                if !query.is_empty() {
+                    location = format!("{}?{}", location, query);
+                }
+                return http_response("301 Moved Permanently", &format!("Location: {}\r\n", location), "");
+            }
+            if metadata.is_dir() {
+                if let Some((index, index_metadata)) = find_index(directory, &full_path).await {
+                    let mut response = http_response("200 OK", "", "");
+                    response.file = Some((index, index_metadata));
+                    return response;
+                }
+            }
+            // Listings come as HTML or JSON depending on Accept, which caches
+            // need to be told.
+            if metadata.is_dir() && json {
+                return match generate_json_listing(state, site, directory, requested_path, &full_path, &metadata, query).await {
+                    Ok(listing) => http_response("200 OK", "Content-Type: application/json\r\nVary: Accept\r\n", listing),
+                    Err(_) => {
+                        let mut response = api_error("403 Forbidden", "The requested directory cannot be read.").with_rule("unreadable_directory");
+                        response.headers.push_str("Vary: Accept\r\n");
+                        response
+                    }
+                };
+            }
+            if metadata.is_dir() {
+                match generate_directory_listing(state, site, directory, requested_path, &full_path, &metadata, query).await {
+                    Ok((listing, rows)) => {
+                        let mut response = html_response("200 OK", listing);
+                        response.headers.push_str("Vary: Accept\r\n");
+                        response.stream = rows.map(Stream::Rows);
+                        return response;
+                    }
+                    Err(_) => ("403 Forbidden", generate_error_page("403 - Forbidden", "The requested directory cannot be read."), "unreadable_directory"),
+                }
+            } else if let Some(refusal) = refuse_by_extension(directory, &full_path) {
+                return refusal;
+            } else {
+                let config = site.config;
+                ("200 OK", generate_file_info(requested_path, &full_path, Some(&metadata), show_details(config, query), &config.timestamps, config.size_units).await, "")
+            }
+        }
+        Err(_) if fs::symlink_metadata(&full_path).await.is_ok_and(|metadata| metadata.file_type().is_symlink()) => {
+            if let Some(refusal) = refuse_by_extension(directory, &full_path) {
+                return refusal;
+            }
+            let config = site.config;
+            ("200 OK", generate_file_info(requested_path, &full_path, None, false, &config.timestamps, config.size_units).await, "")
+        }
+        Err(_) => {
+            if let Some(fallback) = find_spa_fallback(site).await {
+                let mut response = http_response("200 OK", "", "");
+                response.file = Some(fallback);
+                return response;
+            }
+            ("404 Not Found", generate_error_page("404 - Path Not Found", "The requested path could not be found."), "not_found")
+        }
+    };
+
+    html_response(status, html_content).with_rule(rule)
+}
+
+// The file at `path` itself, for `?raw=1` and WebDAV; anything else is
+// answered as a plain GET would be.
+async fn raw_file(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    directory: &directory_config::DirectoryConfig,
+    path: &Path,
+    query: &str,
+    names_directory: bool,
+    json: bool,
+) -> Response {
+    let full_path = site.resolve(path);
+    match fs::metadata(&full_path).await {
+        Ok(metadata) if metadata.is_file() => {
+            let mut response = http_response("200 OK", "", "");
+            response.file = Some((full_path, metadata));
+            response
+        }
+        _ => generate_response(state, site, directory, path, query, names_directory, json).await,
+    }
+}
+
+// A WebDAV PROPFIND of `path`, and of its entries with `Depth: 1`.
+async fn propfind(site: &config::Site<'_>, directory: &directory_config::DirectoryConfig, path: &Path, depth: Option<&str>) -> Response {
+    let children = match depth.map(str::trim) {
+        Some("0") => false,
+        Some("1") => true,
+        _ => {
+            return http_response("403 Forbidden", "Content-Type: application/xml; charset=utf-8\r\n", webdav::FINITE_DEPTH_ERROR)
+                .with_rule("webdav_depth")
+        }
+    };
+    let full_path = site.resolve(path);
+    if fs::metadata(&full_path).await.is_ok_and(|metadata| !metadata.is_dir()) {
+        if let Some(refusal) = refuse_by_extension(directory, &full_path) {
+            return refusal;
+        }
+    }
+    match webdav::resources(site, directory, path, children).await {
+        Ok(resources) => http_response("207 Multi-Status", "Content-Type: application/xml; charset=utf-8\r\n", webdav::multistatus(&resources)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => http_response("404 Not Found", "", "").with_rule("not_found"),
+        Err(_) => http_response("403 Forbidden", "", "").with_rule("unreadable_directory"),
+    }
+}
+
+// A WebDAV PUT of `path`. The body is only read now that the request has
+// passed every other check.
+async fn dav_put<S: listener::Connection>(
+    socket: &mut S,
+    body: &mut request::Body,
+    site: &config::Site<'_>,
+    directory: &directory_config::DirectoryConfig,
+    path: &Path,
+    request: &str,
+) -> Response {
+    let full_path = site.resolve(path);
+    if fs::metadata(&full_path).await.is_ok_and(|metadata| metadata.is_dir()) {
+        return http_response("405 Method Not Allowed", &format!("Allow: {}\r\n", webdav::ALLOW), "");
+    }
+    if let Some(refusal) = refuse_by_extension(directory, &full_path) {
+        return refusal;
+    }
+    if !is_directory(full_path.parent()).await {
+        return http_response("409 Conflict", "", "");
+    }
+    // Clients that ask wait to hear that the body is wanted before sending
+    // it.
+    if extract_header(request, "Expect").is_some_and(|expect| expect.trim().eq_ignore_ascii_case("100-continue")) {
+        if let Err(e) = socket.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await {
+            tracing::error!("Failed to write to socket: {}", e);
+        }
+    }
+    match webdav::put(socket, body, &full_path).await {
+        Ok(true) => http_response("201 Created", "", ""),
+        Ok(false) => http_response("204 No Content", "", ""),
+        Err(e) if request::is_body_too_large(&e) => http_response("413 Content Too Large", "Connection: close\r\n", ""),
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof) => {
+            tracing::debug!("Failed to read request body: {}", e);
+            http_response("400 Bad Request", "Connection: close\r\n", "")
+        }
+        Err(e) => dav_error(&full_path, &e),
+    }
+}
+
+// A WebDAV MKCOL of `path`, which needs its parent to exist.
+async fn dav_mkcol(site: &config::Site<'_>, path: &Path, request: &str) -> Response {
+    // A body would be some extension's description of what to create, and
+    // the server knows none.
+    let has_body = extract_header(request, "Transfer-Encoding").is_some()
+        || extract_header(request, "Content-Length").is_some_and(|length| length.trim() != "0");
+    if has_body {
+        return http_response("415 Unsupported Media Type", "", "");
+    }
+    let full_path = site.resolve(path);
+    match fs::create_dir(&full_path).await {
+        Ok(()) => http_response("201 Created", "", ""),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            http_response("405 Method Not Allowed", &format!("Allow: {}\r\n", webdav::ALLOW), "")
+        }
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory) => http_response("409 Conflict", "", ""),
+        Err(e) => dav_error(&full_path, &e),
+    }
+}
+
+// A WebDAV DELETE of `path`, a directory with all that is in it, unless any
+// of that may not be removed.
+async fn dav_delete(site: &config::Site<'_>, directory: &directory_config::DirectoryConfig, path: &Path) -> Response {
+    if site.is_mount_point(path) {
+        return http_response("403 Forbidden", "", "").with_rule("webdav_mount_point");
+    }
+    let full_path = site.resolve(path);
+    let Ok(metadata) = fs::symlink_metadata(&full_path).await else {
+        return http_response("404 Not Found", "", "").with_rule("not_found");
+    };
+    if metadata.is_dir() {
+        let refused = webdav::refused(path.to_path_buf(), full_path.clone(), directory.clone(), webdav::Change::Delete).await;
+        if !refused.is_empty() {
+            return dav_failures(&refused, &[path]);
+        }
+    } else if let Some(refusal) = refuse_by_extension(directory, &full_path) {
+        return refusal;
+    }
+    match webdav::delete(path.to_path_buf(), full_path).await {
+        failures if failures.is_empty() => http_response("204 No Content", "", ""),
+        failures => dav_failures(&failures, &[path]),
+    }
+}
+
+// A WebDAV COPY or MOVE of `path` to the one its Destination header names.
+// That one is checked as the request's own path was: against the users
+// file, its directory's settings and what is served at all. What is taken
+// must be something `source`, the settings of `path`, lets be sent.
+async fn dav_copy(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    source: &directory_config::DirectoryConfig,
+    path: &Path,
+    request: &str,
+    user: Option<&str>,
+) -> Response {
+    let moving = extract_method(request) == "MOVE";
+    let destination = match dav_destination(request) {
+        Ok(destination) => destination,
+        Err(status) => return http_response(status, "", "").with_rule("webdav_destination"),
+    };
+    if let (Some(users), Some(user)) = (state.users.load_full(), user) {
+        if !users.may_access(user, &destination) {
+            return html_response(
+                "403 Forbidden",
+                generate_error_page("403 - Forbidden", "Your account does not have access to this path."),
+            )
+            .with_rule("outside_user_roots");
+        }
+    }
+    let directory = directory_config::DirectoryConfig::for_path(site.config, site, &destination).await;
+    if directory.read_only {
+        return html_response(
+            "403 Forbidden",
+            generate_error_page("403 - Forbidden", "This site is read-only."),
+        )
+        .with_rule("read_only");
+    }
+    if destination.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return http_response("403 Forbidden", "", "").with_rule("directory_config");
+    }
+    let from = site.resolve(path);
+    let Ok(metadata) = fs::symlink_metadata(&from).await else {
+        return http_response("404 Not Found", "", "").with_rule("not_found");
+    };
+    if metadata.is_dir() {
+        let change = if moving { webdav::Change::Move } else { webdav::Change::Copy };
+        let refused = webdav::refused(path.to_path_buf(), from.clone(), source.clone(), change).await;
+        if !refused.is_empty() {
+            return dav_failures(&refused, &[path]);
+        }
+    } else if let Some(refusal) = refuse_file(source, &from, &metadata) {
+        return refusal;
+    }
+    let to = site.resolve(&destination);
+    if !metadata.is_dir() {
+        if let Some(refusal) = refuse_by_extension(&directory, &to) {
+            return refusal;
+        }
+    }
+    // Into itself, or over a directory the server was told to serve.
+    if destination.starts_with(path) || site.is_mount_point(&destination) || moving && site.is_mount_point(path) {
+        return http_response("403 Forbidden", "", "").with_rule("webdav_destination");
+    }
+    // A MOVE takes all there is; a COPY may take a directory alone.
+    let members = match extract_header(request, "Depth").map(str::trim) {
+        None | Some("infinity") => true,
+        Some("0") if !moving => false,
+        _ => return http_response("400 Bad Request", "", ""),
+    };
+    if !is_directory(to.parent()).await {
+        return http_response("409 Conflict", "", "");
+    }
+    let existing = fs::symlink_metadata(&to).await;
+    let existed = existing.is_ok();
+    if let Ok(existing) = existing {
+        if extract_header(request, "Overwrite").is_some_and(|overwrite| overwrite.trim().eq_ignore_ascii_case("F")) {
+            return http_response("412 Precondition Failed", "", "");
+        }
+        if existing.is_dir() {
+            let refused = webdav::refused(destination.clone(), to.clone(), directory.clone(), webdav::Change::Delete).await;
+            if !refused.is_empty() {
+                return dav_failures(&refused, &[&destination]);
+            }
+        }
+        let failures = webdav::delete(destination.clone(), to.clone()).await;
+        if !failures.is_empty() {
+            return dav_failures(&failures, &[&destination]);
+        }
+    }
+    let failures = if moving {
+        webdav::rename(from, to, path.to_path_buf(), destination.clone()).await
+    } else {
+        webdav::copy(from, to, destination.clone(), members).await
+    };
+    match failures {
+        failures if !failures.is_empty() => dav_failures(&failures, &[path, &destination]),
+        _ if existed => http_response("204 No Content", "", ""),
+        _ => http_response("201 Created", "", ""),
+    }
+}
+
+// The site path a COPY or MOVE names in its Destination header, an absolute
+// URL or path under DAV_PATH. It is normalized as the request target is, so
+// it cannot lead out of the site either. One on another host, or outside
+// DAV_PATH, is not somewhere this server can put anything. Errors are the
+// status to answer with.
+fn dav_destination(request: &str) -> Result<PathBuf, &'static str> {
+    let Some(destination) = extract_header(request, "Destination") else {
+        return Err("400 Bad Request");
+    };
+    let target = match destination.trim().split_once("://") {
+        Some((_, rest)) => {
+            let (authority, target) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            if !extract_header(request, "Host").is_some_and(|host| host.trim().eq_ignore_ascii_case(authority)) {
+                return Err("502 Bad Gateway");
+            }
+            target
+        }
+        None => destination.trim(),
+    };
+    let path = strip_base_url(target).map(extract_path);
+    match path.as_deref().map(|path| path.strip_prefix(DAV_PATH)) {
+        Some(Ok(relative)) => Ok(Path::new("/").join(relative)),
+        _ => Err("502 Bad Gateway"),
+    }
+}
+
+async fn is_directory(path: Option<&Path>) -> bool {
+    match path {
+        Some(path) => fs::metadata(path).await.is_ok_and(|metadata| metadata.is_dir()),
+        None => false,
+    }
+}
+
+// The answer to a WebDAV change that failed, in part or in whole. When only
+// one of `paths`, which the request named, failed, that failure's status is
+// the answer; otherwise a 207 lists what failed.
+fn dav_failures(failures: &[webdav::Failure], paths: &[&Path]) -> Response {
+    match failures {
+        [failure] if paths.contains(&failure.url_path.as_path()) => http_response(failure.status, "", ""),
+        _ => http_response("207 Multi-Status", "Content-Type: application/xml; charset=utf-8\r\n", webdav::failures_multistatus(failures)),
+    }
+}
+
+// A WebDAV change to a single path that the filesystem refused.
+fn dav_error(path: &Path, error: &std::io::Error) -> Response {
+    tracing::error!("WebDAV change to {} failed: {}", path.display(), error);
+    http_response(webdav::status(error), "", "")
+}
+
+// 403 for a file whose contents are not to be sent: because of its
+// extension, or its size.
+fn refuse_file(directory: &directory_config::DirectoryConfig, path: &Path, metadata: &std::fs::Metadata) -> Option<Response> {
+    if let Some(refusal) = refuse_by_extension(directory, path) {
+        return Some(refusal);
+    }
+    let len = metadata.len();
+    let max_file_size = directory.max_file_size.filter(|max_file_size| len > *max_file_size)?;
+    let message = format!(
+        "The requested file is {}, larger than the {} this server is configured to send.",
+        format_size(len, BINARY),
+        format_size(max_file_size, BINARY)
+    );
+    Some(
+        html_response(
+            "403 Forbidden",
+            generate_error_page("403 - File Too Large", &message),
+        )
+        .with_rule("file_too_large"),
+    )
+}
+
+// 403 for a file the extension allow and deny lists keep from being served.
+fn refuse_by_extension(directory: &directory_config::DirectoryConfig, path: &Path) -> Option<Response> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    if directory.serves_extension(&file_name) {
+        return None;
+    }
+    Some(
+        html_response(
+            "403 Forbidden",
+            generate_error_page("403 - Forbidden", "Files of this type are not served."),
+        )
+        .with_rule("extension_not_allowed"),
+    )
+}
+
+// The file that answers for missing paths, so a single-page application's
+// own routes load the application. None when it is not configured or missing
+// itself, which leaves the 404.
+async fn find_spa_fallback(site: &config::Site<'_>) -> Option<(PathBuf, std::fs::Metadata)> {
+    let fallback = site.root.join(site.config.spa_fallback.as_ref()?);
+    match fs::metadata(&fallback).await {
+        Ok(metadata) if metadata.is_file() => {
+            tracing::debug!("falling back to {}", fallback.display());
+            Some((fallback, metadata))
+        }
+        _ => None,
+    }
+}
+
+// The first configured index file present in `dir`.
+async fn find_index(directory: &directory_config::DirectoryConfig, dir: &Path) -> Option<(PathBuf, std::fs::Metadata)> {
+    if !directory.serve_index {
+        return None;
+    }
+    for name in &directory.index_files {
+        let index = dir.join(name);
+        if let Ok(metadata) = fs::metadata(&index).await {
+            if metadata.is_file() {
+                tracing::debug!("serving index file {}", index.display());
+                return Some((index, metadata));
+            }
+        }
+    }
+    None
+}
+
+struct Response {
+    status: &'static str,
+    headers: String,
+    body: String,
+    // Why a request was refused, for the audit log ("" for normal responses).
+    rule: &'static str,
+    // Rows of a listing too large to buffer, or the results of a search by
+    // name or content.
+    // `body` then only holds the start of the page; the rest is written as
+    // it is produced.
+    stream: Option<Stream>,
+    // Whether a streamed body may be sent chunked (the client speaks HTTP/1.1).
+    chunked: bool,
+    // A file to send in place of this response, as for a raw download.
+    file: Option<(PathBuf, std::fs::Metadata)>,
+}
+
+// Rows are written in batches of about this many bytes, each one chunk.
+const STREAMED_BATCH_SIZE: usize = 16 * 1024;
+
+// The part of a response written after `body`, as it is produced.
+enum Stream {
+    Rows(StreamedRows),
+    Search(SearchResults),
+    Grep(GrepResults),
+}
+
+impl Stream {
+    async fn write_to<W: tokio::io::AsyncWrite + Unpin>(self, writer: &mut W, site: &config::Site<'_>) -> std::io::Result<()> {
+        match self {
+            Stream::Rows(rows) => rows.write_to(writer).await,
+            Stream::Search(results) => results.write_to(writer, site).await,
+            Stream::Grep(results) => results.write_to(writer, site).await,
+        }
+    }
+}
+
+struct StreamedRows {
+    // Entries already read: the start of the directory in filesystem order,
+    // or all of it, sorted.
+    entries: std::vec::IntoIter<listing::EntryInfo>,
+    // The rest of the directory, read as the rows are written.
+    dir_entries: Option<listing::EntryReader>,
+    current_path: OsString,
+    filter: listing::EntryFilter,
+    style: RowStyle,
+    readme: String,
+}
+
+impl StreamedRows {
+    async fn write_to<W: tokio::io::AsyncWrite + Unpin>(mut self, writer: &mut W) -> std::io::Result<()> {
+        let mut batch = String::new();
+        let mut summary = listing::Summary::default();
+        loop {
+            let entry = match (self.entries.next(), &mut self.dir_entries) {
+                (Some(entry), _) => entry,
+                (None, Some(dir_entries)) => match dir_entries.next().await {
+                    Some(entry) => entry,
+                    None => break,
+                },
+                (None, None) => break,
+            };
+            if !self.filter.admits(&entry) {
+                continue;
+            }
+            summary.add(&entry);
+            batch.push_str(&render_listing_row(&self.current_path, &entry, &self.style));
+            if batch.len() >= STREAMED_BATCH_SIZE {
+                writer.write_all(batch.as_bytes()).await?;
+                batch.clear();
+            }
+        }
+        batch.push_str(&listing_page_foot(&summary, &self.style, &self.readme));
+        writer.write_all(batch.as_bytes()).await
+    }
+}
+
+// The matching lines of a content search, one JSON object per line, ending
+// with the count.
+struct GrepResults {
+    grep: grep::Grep,
+}
+
+impl GrepResults {
+    async fn write_to<W: tokio::io::AsyncWrite + Unpin>(mut self, writer: &mut W, site: &config::Site<'_>) -> std::io::Result<()> {
+        let mut batch = String::new();
+        let mut count = 0;
+        while let Some(found) = self.grep.next(site).await {
+            let found = api::GrepMatch {
+                path: found.url_path.to_string_lossy().to_string(),
+                line_number: found.line_number,
+                line: found.line,
+            };
+            batch.push_str(&serde_json::to_string(&found).unwrap_or_default());
+            batch.push('\n');
+            count += 1;
+            if batch.len() >= STREAMED_BATCH_SIZE {
+                writer.write_all(batch.as_bytes()).await?;
+                batch.clear();
+            }
+        }
+        batch.push_str(&format!("{}\n", serde_json::json!({ "matches": count, "truncated": self.grep.limited })));
+        writer.write_all(batch.as_bytes()).await
+    }
+}
+
+// The results of a search, written as the tree is walked: table rows, or
+// the `results` of a JSON document, which `search` starts and this ends.
+struct SearchResults {
+    search: search::Search,
+    // The URL path the search started in, which results are shown relative to.
+    start: PathBuf,
+    json: bool,
+}
+
+impl SearchResults {
+    async fn write_to<W: tokio::io::AsyncWrite + Unpin>(mut self, writer: &mut W, site: &config::Site<'_>) -> std::io::Result<()> {
+        let config = site.config;
+        let mut batch = String::new();
+        let mut count = 0;
+        while let Some(found) = self.search.next(site).await {
+            let relative = found.url_path.strip_prefix(&self.start).unwrap_or(&found.url_path).to_string_lossy().to_string();
+            let href = if found.is_dir { directory_link(&found.url_path) } else { link(&found.url_path) };
+            if self.json {
+                let entry = api::Entry {
+                    name: relative,
+                    kind: if found.is_dir { api::EntryType::Directory } else { api::EntryType::File },
+                    size: found.size,
+                    mtime: found.modified.map(api::timestamp),
+                    href,
+                };
+                if count > 0 {
+                    batch.push(',');
+                }
+                batch.push_str(&serde_json::to_string(&entry).unwrap_or_default());
+            } else {
+                let name = found.url_path.file_name().unwrap_or_default().to_string_lossy();
+                batch.push_str(&format!(
+                    r#"<tr>
+                    <td><a href="{}">{} {}</a></td>
+                    <td>{}</td>
+                    <td>{}</td>
+                </tr>
+"#,
+                    href,
+                    icons::icon(&name, found.is_dir, found.is_symlink, false),
+                    escape_html(&relative),
+                    found.size.map(|size| human_size(size, config.size_units)).unwrap_or_else(|| "-".to_string()),
+                    found.modified.map(|modified| escape_html(&config.timestamps.format(modified))).unwrap_or_else(|| "-".to_string())
+                ));
+            }
+            count += 1;
+            if batch.len() >= STREAMED_BATCH_SIZE {
+                writer.write_all(batch.as_bytes()).await?;
+                batch.clear();
+            }
+        }
+        if self.json {
+            batch.push_str(&format!(r#"],"truncated":{}}}"#, self.search.limited));
+        } else {
+            let empty_row = if count == 0 { r#"<tr class="empty"><td colspan="3">Nothing found</td></tr>"# } else { "" };
+            let limited = if self.search.limited { format!(", stopping at the first {}", group_digits(count as u64)) } else { String::new() };
+            batch.push_str(&format!(
+                r#"
+                        {}
+                    </tbody>
+                </table>
+                <p class="summary">{} {}{}</p>
+            </div>
+        </body>
+        </html>"#,
+                empty_row,
+                group_digits(count as u64),
+                if count == 1 { "match" } else { "matches" },
+                limited
+            ));
+        }
+        writer.write_all(batch.as_bytes()).await
+    }
+}
+
+impl Response {
+    fn with_rule(mut self, rule: &'static str) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    fn status_code(&self) -> u16 {
+        status_code(self.status)
+    }
+
+    // HEAD responses carry the same headers as GET but no body. A streamed
+    // response has no known length: it is sent in chunks, the first of them
+    // `body`, or to HTTP/1.0 clients unframed up to the closing of the
+    // connection.
+    fn to_bytes(&self, include_body: bool, server_headers: &[(&str, &str)]) -> Vec<u8> {
+        let framing = match (&self.stream, self.chunked) {
+            (Some(_), true) => "Transfer-Encoding: chunked\r\n".to_string(),
+            (Some(_), false) => "Connection: close\r\n".to_string(),
+            (None, _) => format!("Content-Length: {}\r\n", self.body.len()),
+        };
+        let mut headers = self.headers.clone();
+        add_server_headers(&mut headers, server_headers);
+        let mut bytes = format!("HTTP/1.1 {}\r\n{}{}\r\n", self.status, headers, framing).into_bytes();
+        match (include_body, self.stream.is_some() && self.chunked) {
+            (false, _) => {}
+            (true, true) => bytes.extend_from_slice(&chunked::chunk(self.body.as_bytes())),
+            (true, false) => bytes.extend_from_slice(self.body.as_bytes()),
+        }
+        bytes
+    }
+}
+
+// 404 for "404 Not Found".
+fn status_code(status: &str) -> u16 {
+    status.split(' ').next().and_then(|code| code.parse().ok()).unwrap_or(500)
+}
+
+fn http_response(status: &'static str, headers: &str, body: impl Into<String>) -> Response {
+    Response { status, headers: headers.to_string(), body: body.into(), rule: "", stream: None, chunked: false, file: None }
+}
+
+// A page generated by the server: a listing, file info or an error. Files
+// served from disk never come through here, so the policy only governs the
+// server's own pages.
+fn html_response(status: &'static str, body: impl Into<String>) -> Response {
+    let mut headers = "Content-Type: text/html; charset=utf-8\r\n".to_string();
+    if let Some(policy) = CONTENT_SECURITY_POLICY.get().filter(|policy| !policy.is_empty()) {
+        headers.push_str(&format!("Content-Security-Policy: {}\r\n", policy));
+    }
+    http_response(status, &headers, body)
+}
+
+// `PUT /some/dir/` (note the trailing slash) creates the directory and any
+// missing parents. The Location header echoes the request target so it points
+// straight at the new listing.
+#[tracing::instrument(skip_all, fields(path = %requested_path.display()))]
+async fn create_directory(site: &config::Site<'_>, requested_path: &Path, target: &str) -> Response {
+    let full_path = site.resolve(requested_path);
+
+    match fs::metadata(&full_path).await {
+        Ok(metadata) if metadata.is_dir() => return http_response("200 OK", "", ""),
+        Ok(_) => return http_response("409 Conflict", "", ""),
+        Err(_) => {}
+    }
+
+    match fs::create_dir_all(&full_path).await {
+        Ok(()) => http_response("201 Created", &format!("Location: {}\r\n", target), ""),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            tracing::debug!("Refused to create directory {}: {}", full_path.display(), e);
+            html_response(
+                "403 Forbidden",
+                generate_error_page("403 - Forbidden", "The server may not create a directory here."),
+            )
+            .with_rule("unwritable_directory")
+        }
+        Err(e) => {
+            tracing::error!("Failed to create directory {}: {}", full_path.display(), e);
+            http_response("500 Internal Server Error", "", "")
+        }
+    }
+}
+
+// The archive is streamed straight to the socket as it is built, so there is
+// no Content-Length; the body is chunked, or for HTTP/1.0 clients ends when
+// the connection is closed. Returns the size of the archive, unless it could
+// not be sent whole.
+#[tracing::instrument(skip_all, fields(path = %dir.display()))]
+async fn send_archive<S: listener::Connection>(
+    mut socket: S,
+    dir: &Path,
+    directory: directory_config::DirectoryConfig,
+    format: archive::Format,
+    extra_headers: &str,
+    server_headers: &[(&str, &str)],
+    chunked: bool,
+) -> Option<u64> {
+    let mut headers = format!(
+        "HTTP/1.1 200 OK\r\n\
+        Content-Type: {}\r\n\
+        Content-Disposition: {}\r\n\
+        Accept-Ranges: none\r\n\
+        {}\
+        {}",
+        format.content_type(),
+        archive::content_disposition(dir, format.extension()),
+        extra_headers,
+        if chunked { "Transfer-Encoding: chunked\r\n" } else { "Connection: close\r\n" }
+    );
+    add_server_headers(&mut headers, server_headers);
+    headers.push_str("\r\n");
+    if let Err(e) = socket.write_all(headers.as_bytes()).await {
+        tracing::error!("Failed to write to socket: {}", e);
+        return None;
     }
+    let written = if chunked {
+        archive::write_archive(chunked::ChunkedWriter::new(socket), dir, directory, format).await
+    } else {
+        archive::write_archive(socket, dir, directory, format).await
+    };
+    written
+        .inspect_err(|e| tracing::error!("Failed to stream {} archive of {}: {}", format.extension(), dir.display(), e))
+        .ok()
 }
 
-async fn handle_connection(mut socket: TcpStream) {
-    let mut buffer = vec![0; 4096];
+// Small files go through the shared cache; anything larger is streamed from
+// disk so it never has to fit in memory. If the file cannot be read nothing
+// has been written yet and the error response is handed back to the caller.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+async fn send_file<S: listener::Connection>(
+    socket: &mut S,
+    state: &ServerState,
+    config: &config::Config,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    request: &str,
+    extra_headers: &str,
+) -> Result<(&'static str, u64), Response> {
+    let include_body = extract_method(request) != "HEAD";
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = range::etag(len, modified);
+    let mut validators = format!("Accept-Ranges: bytes\r\nETag: {}\r\n", etag);
+    if let Some(modified) = modified {
+        validators.push_str(&format!("Last-Modified: {}\r\n", range::http_date(modified)));
+    }
+
+    // `If-Range` is checked first: when the file changed since the client's
+    // partial copy, its `Range` no longer applies and the whole file is sent.
+    let range_request = if range::if_range_allows(extract_header(request, "If-Range"), &etag, modified) {
+        range::parse(extract_header(request, "Range"), len)
+    } else {
+        range::RangeRequest::Full
+    };
+    // The body is a list of byte ranges of the file, each optionally preceded
+    // by a multipart part header, followed by a closing delimiter.
+    let content_type = mime::content_type(path, &config.mime).to_string();
+    let mut content_type_header = content_type.to_string();
+    let (status, parts, trailer) = match range_request {
+        range::RangeRequest::Full if len == 0 => ("200 OK", Vec::new(), String::new()),
+        range::RangeRequest::Full => ("200 OK", vec![(String::new(), range::ByteRange { start: 0, end: len - 1 })], String::new()),
+        range::RangeRequest::Partial(range) => {
+            validators.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", range.start, range.end, len));
+            ("206 Partial Content", vec![(String::new(), range)], String::new())
+        }
+        range::RangeRequest::Multiple(ranges) => {
+            let boundary = range::boundary();
+            content_type_header = format!("multipart/byteranges; boundary={}", boundary);
+            let parts = ranges
+                .into_iter()
+                .map(|range| {
+                    let part_header = format!(
+                        "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        boundary, content_type, range.start, range.end, len
+                    );
+                    (part_header, range)
+                })
+                .collect();
+            ("206 Partial Content", parts, format!("\r\n--{}--\r\n", boundary))
+        }
+        range::RangeRequest::Unsatisfiable => {
+            return Err(http_response(
+                "416 Range Not Satisfiable",
+                &format!("Accept-Ranges: bytes\r\nContent-Range: bytes */{}\r\n", len),
+                "",
+            ));
+        }
+    };
+    let body_len = parts.iter().map(|(part_header, range)| part_header.len() as u64 + range.len()).sum::<u64>()
+        + trailer.len() as u64;
+
+    let mut headers = format!(
+        "HTTP/1.1 {}\r\n\
+        Content-Type: {}\r\n\
+        Content-Length: {}\r\n\
+        {}\
+        {}",
+        status,
+        content_type_header,
+        body_len,
+        validators,
+        extra_headers
+    );
+    // As `respond` picks them, for the path the request names.
+    let mut server_headers = config.headers_for(&extract_path(strip_base_url(extract_target(request)).unwrap_or("/")));
+    // The cache policy of the file's type is the response's own, over any
+    // configured Cache-Control. Types it does not list get the default only
+    // where none is configured.
+    match mime::cache_control(path, &config.cache_policy) {
+        Some(cache_control) => headers.push_str(&format!("Cache-Control: {}\r\n", cache_control)),
+        None => server_headers.push(("Cache-Control", mime::DEFAULT_CACHE_CONTROL)),
+    }
+    add_server_headers(&mut headers, &server_headers);
+    headers.push_str("\r\n");
+
+    let (cacheable, cached) = {
+        let mut cache = state.file_cache.lock().unwrap();
+        let cacheable = cache.accepts(metadata);
+        (cacheable, if cacheable { cache.get(path, metadata) } else { None })
+    };
+    let contents = match cached {
+        Some(contents) => Some(contents),
+        None if cacheable => match fs::read(path).await {
+            Ok(contents) if contents.len() as u64 == metadata.len() => {
+                let contents = Arc::new(bytes::Bytes::from(contents));
+                state.file_cache.lock().unwrap().insert(path.to_path_buf(), metadata, contents.clone());
+                Some(contents)
+            }
+            // Changed since we looked at it; stream whatever is there now.
+            Ok(_) => None,
+            Err(e) => {
+                tracing::error!("Failed to read {}: {}", path.display(), e);
+                return Err(html_response(
+                    "403 Forbidden",
+                    generate_error_page("403 - Forbidden", "The requested file cannot be read."),
+                )
+                .with_rule("unreadable_file"));
+            }
+        },
+        None => None,
+    };
 
-    match socket.read(&mut buffer).await {
-        Ok(bytes_read) if bytes_read > 0 => {
-            let request = String::from_utf8_lossy(&buffer[..bytes_read]);
-            let path = extract_path(&request);
-            let response = generate_response(&path).await;
-            
-            if let Err(e) = socket.write_all(response.as_bytes()).await {
-                eprintln!("Failed to write to socket: {}", e);
+    // Body bytes written, for the audit log.
+    let mut sent = 0;
+    let result = async {
+        socket.write_all(headers.as_bytes()).await?;
+        if !include_body {
+            return Ok(());
+        }
+        // Each range is written on its own, straight from the cached copy or
+        // the file, so several ranges are never gathered in memory.
+        let mut file = None;
+        for (part_header, range) in &parts {
+            socket.write_all(part_header.as_bytes()).await?;
+            sent += part_header.len() as u64;
+            match &contents {
+                Some(contents) => socket.write_all(&contents[range.start as usize..=range.end as usize]).await?,
+                None => {
+                    if file.is_none() {
+                        file = Some(fs::File::open(path).await?);
+                    }
+                    let file = file.as_mut().unwrap();
+                    if !sendfile::send(socket, file, range.start, range.len()).await? {
+                        file.seek(std::io::SeekFrom::Start(range.start)).await?;
+                        tokio::io::copy(&mut file.take(range.len()), socket).await?;
+                    }
+                }
             }
+            sent += range.len();
         }
-        Ok(_) => println!("Connection closed by peer."),
-        Err(e) => eprintln!("Failed to read from socket: {}", e),
+        socket.write_all(trailer.as_bytes()).await?;
+        sent += trailer.len() as u64;
+        Ok::<_, std::io::Error>(())
+    };
+    if let Err(e) = result.await {
+        tracing::error!("Failed to send {}: {}", path.display(), e);
     }
+    Ok((status, sent))
 }
 
-fn extract_path(request: &str) -> PathBuf {
-    let path = request.lines()
-        .next()
-        .and_then(|line| line.split_whitespace().nth(1))
-        .unwrap_or("/");
+// Returns the whole page, or for directories too large to buffer, the start
+// of the page plus the rows still to be streamed. Smaller directories are
+// always sorted (by name unless asked otherwise) and come from the listing
+// cache, so their page is built in one go.
+// `url_path` is the path as requested (used for links), `path` where it lives
+// on disk.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+async fn generate_directory_listing(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    directory: &directory_config::DirectoryConfig,
+    url_path: &Path,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    query: &str,
+) -> std::io::Result<(String, Option<StreamedRows>)> {
+    let listing = state.listing_cache.get_or_read(path, metadata).await?;
+    let pagination = listing::Pagination::from_params(
+        query_param(query, "page").as_deref(),
+        query_param(query, "per_page").as_deref(),
+    );
 
-    let decoded_path = percent_decode_str(&path[1..])
-        .decode_utf8_lossy()
-        .to_string();
+    let config = site.config;
+    let sort = listing::Sort::from_params(
+        query_param(query, "sort").as_deref(),
+        query_param(query, "order").as_deref(),
+        collation(config, query),
+    );
+    let read_in = state.listing_cache.collation();
+    let mut style = RowStyle {
+        grid: query_param(query, "view").as_deref() == Some("grid"),
+        details: show_details(config, query),
+        relative_times: relative_times(config, query),
+        timestamps: config.timestamps.clone(),
+        size_units: config.size_units,
+        recursive_sizes: HashMap::new(),
+        child_counts: HashMap::new(),
+    };
+    let du = recursive_sizes(config, query);
+    let columns = column_headers(query, sort, style.details);
+    let show_hidden = show_hidden(directory, query);
+    let (pattern, filter_notice) = listing_filter(query);
+    let filter = listing::EntryFilter { show_hidden, pattern };
 
-    PathBuf::from("/").join(decoded_path)
-}
+    let current_path = if url_path == Path::new("/") { OsString::new() } else { url_path.as_os_str().to_owned() };
+    let parent_row = match url_path.parent() {
+        Some(parent) if style.grid => format!(
+            r#"<a class="tile" href="{}"><span class="preview">{}</span><span class="name">..</span></a>"#,
+            directory_link(parent),
+            icons::DIRECTORY
+        ),
+        Some(parent) => format!(
+            r#"<tr><td><a href="{}">{} ..</a></td><td>-</td><td>-</td>{}</tr>"#,
+            directory_link(parent),
+            icons::DIRECTORY,
+            if style.details { details_cells(None) } else { String::new() }
+        ),
+        None => String::new(),
+    };
 
-async fn generate_response(requested_path: &Path) -> String {
-    let root_path = Path::new("/");
-    let full_path = root_path.join(requested_path.strip_prefix("/").unwrap_or(requested_path));
-    
-    let html_content = match fs::metadata(&full_path).await {
-        Ok(metadata) => {
-            if metadata.is_dir() {
-                generate_directory_listing(&full_path).await
+    // Asked anew for every page, which costs one system call.
+    let disk_space = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || disk_space::of(&path)).await.ok().flatten()
+    };
+    let disk_space = match disk_space {
+        Some(space) => format!(
+            r#"<p class="disk-space">{} free of {}</p>"#,
+            human_size(space.free, config.size_units),
+            human_size(space.total, config.size_units)
+        ),
+        None => String::new(),
+    };
+    let grid = style.grid;
+    let page_head = |notice: &str| {
+        let notice = format!(
+            "{}{}{}{}{}{}",
+            search_box(url_path, ""),
+            disk_space,
+            filter_notice,
+            notice,
+            hidden_toggle(query, show_hidden),
+            view_toggle(query, grid)
+        );
+        listing_page_head(url_path, &notice, &listing_contents_open(grid, &columns, &parent_row))
+    };
+
+    let readme = readme_panel(config, directory, path).await;
+
+    match (listing, pagination) {
+        (listing::Listing::Complete(entries), None) => {
+            let entries: Vec<_> = listing::sorted(&entries, read_in, sort).into_iter().filter(|entry| filter.admits(entry)).collect();
+            let mut page = page_head(&format!("<p>{} entries</p>", entries.len()));
+            if du {
+                style.recursive_sizes = directory_sizes(state, site, path, &entries).await;
+            } else {
+                style.child_counts = child_counts(path, &entries).await;
+            }
+            let mut summary = listing::Summary::default();
+            for entry in entries {
+                summary.add(entry);
+                page.push_str(&render_listing_row(&current_path, entry, &style));
+            }
+            page.push_str(&listing_page_foot(&summary, &style, &readme));
+            Ok((page, None))
+        }
+        (listing::Listing::Complete(entries), Some(pagination)) => {
+            let entries: Vec<_> = listing::sorted(&entries, read_in, sort).into_iter().filter(|entry| filter.admits(entry)).collect();
+            let pagination = pagination.clamped(entries.len());
+            let mut page = page_head(&pagination_notice(query, &pagination, entries.len(), ""));
+            let entries: Vec<_> = entries.into_iter().skip(pagination.offset()).take(pagination.per_page).collect();
+            if du {
+                style.recursive_sizes = directory_sizes(state, site, path, &entries).await;
+            } else {
+                style.child_counts = child_counts(path, &entries).await;
+            }
+            let mut summary = listing::Summary::default();
+            for entry in entries {
+                summary.add(entry);
+                page.push_str(&render_listing_row(&current_path, entry, &style));
+            }
+            page.push_str(&listing_page_foot(&summary, &style, &readme));
+            Ok((page, None))
+        }
+        // Without an explicit order, a huge directory is listed as it is read:
+        // the head goes out at once and the rows follow in filesystem order.
+        (listing::Listing::Partial(entries, dir_entries), None) if query_param(query, "sort").is_none() => {
+            let notice = format!(
+                r#"<p>This directory has more than {} entries, so they are shown unsorted, in the order the filesystem returns them. Choose a column to sort them, which takes longer.</p>"#,
+                listing::MAX_BUFFERED_ENTRIES
+            );
+            let rows = StreamedRows { entries: entries.into_iter(), dir_entries: Some(dir_entries), current_path, filter, style, readme };
+            Ok((page_head(&notice), Some(rows)))
+        }
+        // An explicit order needs the whole directory before the first row, up
+        // to MAX_SORTED_ENTRIES; past that the rows are streamed unsorted.
+        (listing::Listing::Partial(mut entries, mut dir_entries), None) => {
+            let mut exhausted = false;
+            while !exhausted && entries.len() < listing::MAX_SORTED_ENTRIES {
+                match dir_entries.next().await {
+                    Some(entry) if filter.admits(&entry) => entries.push(entry),
+                    Some(_) => {}
+                    None => exhausted = true,
+                }
+            }
+            let notice = if exhausted {
+                entries.retain(|entry| filter.admits(entry));
+                entries.sort_by(|a, b| sort.compare(a, b));
+                format!("<p>{} entries</p>", entries.len())
             } else {
-                generate_file_info(&full_path, &metadata).await
+                format!(
+                    r#"<p>This directory has more than {} entries, too many to sort, so they are shown in the order the filesystem returns them.</p>"#,
+                    listing::MAX_SORTED_ENTRIES
+                )
+            };
+            let dir_entries = (!exhausted).then_some(dir_entries);
+            Ok((page_head(&notice), Some(StreamedRows { entries: entries.into_iter(), dir_entries, current_path, filter, style, readme })))
+        }
+        // Pages of a huge directory are cut from the unsorted directory order.
+        // The whole directory is still read to count it, but only the rows of
+        // the requested page are kept, plus the entries of the last page
+        // before it in case the directory turns out to end before it.
+        (listing::Listing::Partial(entries, mut dir_entries), Some(pagination)) => {
+            let range = pagination.offset()..pagination.offset().saturating_add(pagination.per_page);
+            let mut rows = String::new();
+            let mut summary = listing::Summary::default();
+            let mut earlier_page = Vec::new();
+            let mut total = 0;
+            let mut add = |entry: &listing::EntryInfo| {
+                if range.contains(&total) {
+                    summary.add(entry);
+                    rows.push_str(&render_listing_row(&current_path, entry, &style));
+                } else if total < range.start {
+                    if total % pagination.per_page == 0 {
+                        earlier_page.clear();
+                    }
+                    earlier_page.push(entry.clone());
+                }
+                total += 1;
+            };
+            for entry in entries.iter().filter(|entry| filter.admits(entry)) {
+                add(entry);
+            }
+            while let Some(entry) = dir_entries.next().await {
+                if filter.admits(&entry) {
+                    add(&entry);
+                }
+            }
+            let requested_page = pagination.page;
+            let pagination = pagination.clamped(total);
+            if pagination.page != requested_page {
+                summary = listing::Summary::default();
+                rows = earlier_page
+                    .iter()
+                    .map(|entry| {
+                        summary.add(entry);
+                        render_listing_row(&current_path, entry, &style)
+                    })
+                    .collect();
+            }
+
+            let notice = pagination_notice(
+                query,
+                &pagination,
+                total,
+                "<p>This directory is too large to sort, so its entries are shown in the order the filesystem returns them.</p>",
+            );
+            let mut page = page_head(&notice);
+            page.push_str(&rows);
+            page.push_str(&listing_page_foot(&summary, &style, &readme));
+            Ok((page, None))
+        }
+    }
+}
+
+// `api::Listing` as JSON, with the entries the HTML listing would show, in
+// the same order and with the same pagination.
+async fn generate_json_listing(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    directory: &directory_config::DirectoryConfig,
+    url_path: &Path,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    query: &str,
+) -> std::io::Result<String> {
+    let sort = listing::Sort::from_params(
+        query_param(query, "sort").as_deref(),
+        query_param(query, "order").as_deref(),
+        collation(site.config, query),
+    );
+    let filter = listing::EntryFilter { show_hidden: show_hidden(directory, query), pattern: listing_filter(query).0 };
+    let pagination = listing::Pagination::from_params(
+        query_param(query, "page").as_deref(),
+        query_param(query, "per_page").as_deref(),
+    );
+
+    let (entries, truncated) = list_directory(state, path, metadata, &filter, sort).await?;
+    let (offset, count) = match pagination {
+        Some(pagination) => {
+            let pagination = pagination.clamped(entries.len());
+            (pagination.offset(), pagination.per_page)
+        }
+        None => (0, entries.len()),
+    };
+    let listing = api_listing(url_path, entries.iter().skip(offset).take(count), truncated);
+    Ok(serde_json::to_string(&listing).unwrap_or_default())
+}
+
+// The entries of a directory that `filter` admits, in `sort` order, and
+// whether any were left out: a directory too large to buffer is read up to
+// MAX_SORTED_ENTRIES entries, as for sorting its HTML listing.
+async fn list_directory(
+    state: &ServerState,
+    path: &Path,
+    metadata: &std::fs::Metadata,
+    filter: &listing::EntryFilter,
+    sort: listing::Sort,
+) -> std::io::Result<(Vec<listing::EntryInfo>, bool)> {
+    match state.listing_cache.get_or_read(path, metadata).await? {
+        listing::Listing::Complete(entries) => Ok((
+            listing::sorted(&entries, state.listing_cache.collation(), sort).into_iter().filter(|entry| filter.admits(entry)).cloned().collect(),
+            false,
+        )),
+        listing::Listing::Partial(mut entries, mut dir_entries) => {
+            let mut truncated = false;
+            entries.retain(|entry| filter.admits(entry));
+            while let Some(entry) = dir_entries.next().await {
+                if !filter.admits(&entry) {
+                    continue;
+                }
+                if entries.len() >= listing::MAX_SORTED_ENTRIES {
+                    truncated = true;
+                    break;
+                }
+                entries.push(entry);
+            }
+            entries.sort_by(|a, b| sort.compare(a, b));
+            Ok((entries, truncated))
+        }
+    }
+}
+
+// `entries` of the directory at `url_path`, as the JSON documents give them.
+fn api_listing<'a>(url_path: &Path, entries: impl Iterator<Item = &'a listing::EntryInfo>, truncated: bool) -> api::Listing {
+    let current_path = if url_path == Path::new("/") { OsString::new() } else { url_path.as_os_str().to_owned() };
+    let mut summary = listing::Summary::default();
+    let entries = entries
+        .map(|entry| {
+            summary.add(entry);
+            let mut entry_path = current_path.clone();
+            entry_path.push("/");
+            entry_path.push(&entry.file_name);
+            if entry.is_dir {
+                entry_path.push("/");
             }
+            api::Entry {
+                name: entry.name.clone(),
+                kind: if entry.is_dir { api::EntryType::Directory } else { api::EntryType::File },
+                size: if entry.is_dir { None } else { entry.size },
+                mtime: entry.modified.map(api::timestamp),
+                href: link(entry_path),
+            }
+        })
+        .collect();
+    let mut display_path = url_path.to_string_lossy().to_string();
+    if !display_path.ends_with('/') {
+        display_path.push('/');
+    }
+    api::Listing {
+        path: display_path,
+        parent: url_path.parent().map(directory_link),
+        entries,
+        summary,
+        truncated,
+    }
+}
+
+// `GET /api/v1/ls?path=/some/dir`: the listing of a directory as JSON, like
+// the one a request for the directory itself gets when it prefers JSON, but
+// at an address of its own. It takes the same `sort`, `order`, `hidden`,
+// `filter` and `ci` parameters, and is paged with `offset` and `limit`
+// rather than `page` and `per_page`; `truncated` is then also set when
+// entries follow the page. Errors are JSON as well.
+async fn list_api(state: &ServerState, site: &config::Site<'_>, query: &str) -> Response {
+    let url_path = path_param(query);
+    let path = site.resolve(&url_path);
+    if url_path.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return api_error("404 Not Found", "The requested directory could not be found.").with_rule("directory_config");
+    }
+    let metadata = match fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_dir() => metadata,
+        Ok(_) => return api_error("400 Bad Request", "The requested path is not a directory."),
+        Err(_) => return api_error("404 Not Found", "The requested directory could not be found.").with_rule("not_found"),
+    };
+    let number = |name: &str| query_param(query, name).map(|value| value.parse::<usize>().map_err(|_| format!("`{}` must be a whole number.", name))).transpose();
+    let (offset, limit) = match (number("offset"), number("limit")) {
+        (Ok(offset), Ok(limit)) => (offset.unwrap_or(0), limit.unwrap_or(usize::MAX)),
+        (Err(e), _) | (_, Err(e)) => return api_error("400 Bad Request", &e),
+    };
+    let pattern = match query_param(query, "filter").filter(|pattern| !pattern.is_empty()) {
+        Some(pattern) => match glob::Glob::parse(&pattern, query_param(query, "ci").as_deref() == Some("1")) {
+            Ok(glob) => Some(glob),
+            Err(e) => return api_error("400 Bad Request", &format!("Invalid filter: {}", e)),
+        },
+        None => None,
+    };
+
+    let directory = directory_config::DirectoryConfig::for_path(site.config, site, &url_path).await;
+    let filter = listing::EntryFilter { show_hidden: show_hidden(&directory, query), pattern };
+    let sort = listing::Sort::from_params(
+        query_param(query, "sort").as_deref(),
+        query_param(query, "order").as_deref(),
+        collation(site.config, query),
+    );
+    match list_directory(state, &path, &metadata, &filter, sort).await {
+        Ok((entries, truncated)) => {
+            let page = entries.iter().skip(offset).take(limit);
+            let truncated = truncated || offset.saturating_add(limit) < entries.len();
+            let listing = api::ListingPage { listing: api_listing(&url_path, page, truncated), offset, total: entries.len() };
+            http_response("200 OK", "Content-Type: application/json\r\n", serde_json::to_string(&listing).unwrap_or_default())
+        }
+        Err(_) => api_error("403 Forbidden", "The requested directory cannot be read.").with_rule("unreadable_directory"),
+    }
+}
+
+// `GET /api/v1/stat?path=/some/file`: what the file info page says about a
+// path, as `api::Stat`. The path is resolved as a request for it would be,
+// and refused the same way: settings files are not found, and files of a
+// type the directory does not serve are forbidden.
+async fn stat_api(site: &config::Site<'_>, query: &str) -> Response {
+    let url_path = path_param(query);
+    if url_path.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return api_error("404 Not Found", "The requested path could not be found.").with_rule("directory_config");
+    }
+    let path = site.resolve(&url_path);
+    let metadata = fs::metadata(&path).await.ok();
+    let link = match fs::symlink_metadata(&path).await {
+        Ok(link_metadata) if link_metadata.file_type().is_symlink() => Some(fs::read_link(&path).await.unwrap_or_default()),
+        Ok(_) => None,
+        Err(_) => return api_error("404 Not Found", "The requested path could not be found.").with_rule("not_found"),
+    };
+    if !metadata.as_ref().is_some_and(|metadata| metadata.is_dir()) {
+        let directory = directory_config::DirectoryConfig::for_path(site.config, site, &url_path).await;
+        if refuse_by_extension(&directory, &path).is_some() {
+            return api_error("403 Forbidden", "Files of this type are not served.").with_rule("extension_not_allowed");
+        }
+    }
+    let stat = api::Stat::new(url_path.to_string_lossy().to_string(), metadata.as_ref(), link.as_deref());
+    http_response("200 OK", "Content-Type: application/json\r\n", serde_json::to_string(&stat).unwrap_or_default())
+}
+
+// `GET /api/v1/hash?path=/some/file&algo=sha256`: the file's digest, as
+// `api::Checksum`. `algo` is one of sha256 (the default), sha1, md5 and
+// blake3. Files the server would refuse to send are refused here too.
+async fn hash_api(state: &ServerState, site: &config::Site<'_>, query: &str) -> Response {
+    let url_path = path_param(query);
+    if url_path.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return api_error("404 Not Found", "The requested file could not be found.").with_rule("directory_config");
+    }
+    let Some(algorithm) = checksum::Algorithm::from_query(&query_param(query, "algo").unwrap_or_else(|| "sha256".to_string())) else {
+        return api_error("400 Bad Request", "`algo` must be one of sha256, sha1, md5 and blake3.");
+    };
+    let path = site.resolve(&url_path);
+    let metadata = match fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_dir() => return api_error("400 Bad Request", "The requested path is not a file."),
+        Ok(metadata) => metadata,
+        Err(_) => return api_error("404 Not Found", "The requested file could not be found.").with_rule("not_found"),
+    };
+    let directory = directory_config::DirectoryConfig::for_path(site.config, site, &url_path).await;
+    if let Some(refusal) = refuse_file(&directory, &path, &metadata) {
+        return api_error("403 Forbidden", "This file is not served.").with_rule(refusal.rule);
+    }
+    match state.checksums.digest(&path, &metadata, algorithm).await {
+        Ok(digest) => {
+            let checksum = api::Checksum {
+                path: url_path.to_string_lossy().to_string(),
+                algo: algorithm.name(),
+                digest,
+                size: metadata.len(),
+                mtime: metadata.modified().ok().map(api::timestamp),
+            };
+            http_response("200 OK", "Content-Type: application/json\r\n", serde_json::to_string(&checksum).unwrap_or_default())
+        }
+        Err(_) => api_error("403 Forbidden", "The requested file cannot be read.").with_rule("unreadable_file"),
+    }
+}
+
+// `GET /search?q=report&path=/some/dir`: entries under the directory whose
+// names contain `q`, or match it as a glob (see `search::Pattern`), at most
+// `search_max_depth` levels down and `search_max_results` of them. Results
+// are streamed as they are found, as a table or, to a request preferring
+// JSON, as `{"path", "query", "results": [api::Entry], "truncated"}` with
+// each entry's `name` its path below the directory. `?hidden=` works as for
+// listings. Without `q` a page only has the search box.
+async fn search(site: &config::Site<'_>, query: &str, json: bool) -> Response {
+    let start = path_param(query);
+    let q = query_param(query, "q").unwrap_or_default();
+    let not_found = || {
+        if json {
+            return api_error("404 Not Found", "The requested directory could not be found.").with_rule("not_found");
+        }
+        html_response("404 Not Found", generate_error_page("404 - Path Not Found", "The requested directory could not be found.")).with_rule("not_found")
+    };
+    if start.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return not_found();
+    }
+    if !fs::metadata(site.resolve(&start)).await.is_ok_and(|metadata| metadata.is_dir()) {
+        return not_found();
+    }
+    if q.is_empty() {
+        if json {
+            return api_error("400 Bad Request", "`q` must name something to search for.");
+        }
+        let mut page = listing_page_head(&start, &search_box(&start, ""), "");
+        page.push_str("            </div>\n        </body>\n        </html>");
+        return html_response("200 OK", page);
+    }
+    let pattern = match search::Pattern::parse(&q) {
+        Ok(pattern) => pattern,
+        Err(e) if json => return api_error("400 Bad Request", &format!("Invalid pattern: {}", e)),
+        Err(e) => {
+            return html_response(
+                "400 Bad Request",
+                generate_error_page("400 - Bad Request", &format!("Invalid pattern <code>{}</code>: {}", escape_html(&q), escape_html(&e))),
+            )
         }
-        Err(_) => generate_error_page(),
     };
+    let show_hidden = hidden_param(query);
+    let config = site.config;
+    let globbing = matches!(pattern, search::Pattern::Glob(_));
+    let search = search::Search::new(start.clone(), pattern, show_hidden, config.search_max_depth, config.search_max_results);
+
+    let mut response = if json {
+        let head = format!(
+            r#"{{"path":{},"query":{},"results":["#,
+            serde_json::Value::from(directory_link(&start)),
+            serde_json::Value::from(q.as_str())
+        );
+        http_response("200 OK", "Content-Type: application/json\r\nVary: Accept\r\n", head)
+    } else {
+        let notice = format!(
+            "{}<p>Names under this directory {} <code>{}</code>, ignoring case</p>",
+            search_box(&start, &q),
+            if globbing { "matching" } else { "containing" },
+            escape_html(&q)
+        );
+        let columns = "<th>Path</th><th>Size</th><th>Modified</th>";
+        let mut response = html_response("200 OK", listing_page_head(&start, &notice, &listing_contents_open(false, columns, "")));
+        response.headers.push_str("Vary: Accept\r\n");
+        response
+    };
+    response.stream = Some(Stream::Search(SearchResults { search, start, json }));
+    response
+}
+
+// `GET /api/v1/grep?q=hostname&path=/etc`: the lines of text files under the
+// directory that contain `q`, or with `regex=1` match it as a regex, as JSON
+// lines of `api::GrepMatch` streamed as files are read. A last line of
+// `{"matches": n, "truncated": bool}` ends the output; `truncated` is set when
+// `grep_max_matches` cut it short. Files are those a name search would find,
+// `?hidden=` included, to `search_max_depth` levels down; see `grep` for
+// which are skipped.
+async fn grep_api(site: &config::Site<'_>, query: &str) -> Response {
+    let start = path_param(query);
+    let is_dir = fs::metadata(site.resolve(&start)).await.is_ok_and(|metadata| metadata.is_dir());
+    if !is_dir || start.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return api_error("404 Not Found", "The requested directory could not be found.").with_rule("not_found");
+    }
+    let q = query_param(query, "q").unwrap_or_default();
+    if q.is_empty() {
+        return api_error("400 Bad Request", "`q` must name something to search for.");
+    }
+    let matcher = match grep::Matcher::parse(&q, query_param(query, "regex").as_deref() == Some("1")) {
+        Ok(matcher) => matcher,
+        Err(e) => return api_error("400 Bad Request", &format!("Invalid pattern: {}", e)),
+    };
+    let config = site.config;
+    let grep = grep::Grep::new(
+        start,
+        matcher,
+        hidden_param(query),
+        config.search_max_depth,
+        config.grep_max_file_size,
+        config.grep_max_matches,
+    );
+    let mut response = http_response("200 OK", "Content-Type: application/x-ndjson\r\n", "");
+    response.stream = Some(Stream::Grep(GrepResults { grep }));
+    response
+}
+
+// `GET /api/v1/tree?path=/srv&depth=3`: the directory and what is under it,
+// `depth` levels of directories deep (1, the default, for its entries alone),
+// as `api::Tree`. The depth is capped one level below `search_max_depth`,
+// since it counts the directory itself, and the tree at `tree_max_nodes`
+// entries; `?hidden=` works as for listings. See `tree` for the walk.
+async fn tree_api(site: &config::Site<'_>, query: &str) -> Response {
+    let start = path_param(query);
+    let is_dir = fs::metadata(site.resolve(&start)).await.is_ok_and(|metadata| metadata.is_dir());
+    if !is_dir || start.file_name() == Some(OsStr::new(directory_config::FILE_NAME)) {
+        return api_error("404 Not Found", "The requested directory could not be found.").with_rule("not_found");
+    }
+    let depth = match query_param(query, "depth").map(|depth| depth.parse::<usize>()) {
+        None => 1,
+        Some(Ok(depth)) if depth >= 1 => depth,
+        Some(_) => return api_error("400 Bad Request", "`depth` must be a whole number of at least 1."),
+    };
+    let config = site.config;
+    let depth = depth.min(config.search_max_depth + 1);
+    let tree = tree::build(site, start, hidden_param(query), depth, config.tree_max_nodes).await;
+    http_response("200 OK", "Content-Type: application/json\r\n", serde_json::to_string(&tree).unwrap_or_default())
+}
 
+// The search form at the top of listings and of the search page, for names
+// under `url_path`.
+fn search_box(url_path: &Path, q: &str) -> String {
     format!(
-        "HTTP/1.1 200 OK\r\n\
-        Content-Type: text/html; charset=utf-8\r\n\
-        Content-Length: {}\r\n\
-        \r\n\
-        {}",
-        html_content.len(),
-        html_content
+        r#"<form class="search" action="{}" method="get"><input type="hidden" name="path" value="{}"><input type="search" name="q" value="{}" placeholder="Search this directory"> <button type="submit">Search</button></form>"#,
+        link(SEARCH_PATH),
+        escape_html(&url_path.to_string_lossy()),
+        escape_html(q)
+    )
+}
+
+// An error from the API: `{"error": message, "status": code}`.
+fn api_error(status: &'static str, message: &str) -> Response {
+    http_response(
+        status,
+        "Content-Type: application/json\r\n",
+        serde_json::json!({ "error": message, "status": status_code(status) }).to_string(),
+    )
+}
+
+// Entry count and Previous / Next links for one page of a listing. The links
+// keep every other query parameter, so whatever else shaped the listing
+// carries over from page to page.
+fn pagination_notice(query: &str, pagination: &listing::Pagination, total: usize, extra: &str) -> String {
+    let page_count = pagination.page_count(total);
+    let mut nav = Vec::new();
+    if pagination.page > 1 {
+        nav.push(format!(r#"<a href="?{}">Previous</a>"#, with_query_param(query, "page", &(pagination.page - 1).to_string())));
+    }
+    if pagination.page < page_count {
+        nav.push(format!(r#"<a href="?{}">Next</a>"#, with_query_param(query, "page", &(pagination.page + 1).to_string())));
+    }
+    format!(
+        "{}<p>{} entries, page {} of {}</p><p>{}</p>",
+        extra,
+        total,
+        pagination.page,
+        page_count,
+        nav.join(" | ")
     )
 }
 
-async fn generate_directory_listing(path: &Path) -> String {
-    let mut entries = Vec::new();
-    let mut dir_entries = fs::read_dir(path).await.unwrap();
-    
-    while let Ok(Some(entry)) = dir_entries.next_entry().await {
-        if let Ok(metadata) = entry.metadata().await {
-            let name = entry.file_name().to_string_lossy().to_string();
-            let size = format_size(metadata.len(), BINARY);
-            let modified: DateTime<Local> = metadata.modified().unwrap().into();
-            
-            entries.push((name, metadata.is_dir(), size, modified.format("%Y-%m-%d %H:%M:%S").to_string()));
+// `query` with `name` set to `value`, replacing any existing occurrence.
+fn with_query_param(query: &str, name: &str, value: &str) -> String {
+    let mut pairs: Vec<String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(name))
+        .map(str::to_string)
+        .collect();
+    pairs.push(format!("{}={}", name, percent_encode(value.as_bytes(), NON_ALPHANUMERIC)));
+    pairs.join("&")
+}
+
+// `query` without any of the parameters in `names`.
+fn without_query_params(query: &str, names: &[&str]) -> String {
+    let pairs: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !names.contains(&pair.split('=').next().unwrap_or_default()))
+        .collect();
+    pairs.join("&")
+}
+
+// The glob pattern of `?filter=` (matched ignoring case with `?ci=1`) and a
+// line for the page saying what it does. A pattern that does not parse is
+// reported there and otherwise ignored.
+fn listing_filter(query: &str) -> (Option<glob::Glob>, String) {
+    let Some(pattern) = query_param(query, "filter").filter(|pattern| !pattern.is_empty()) else {
+        return (None, String::new());
+    };
+    let case_insensitive = query_param(query, "ci").as_deref() == Some("1");
+    match glob::Glob::parse(&pattern, case_insensitive) {
+        Ok(filter) => {
+            let notice = format!(
+                r#"<p>Showing entries matching <code>{}</code>{} (<a href="?{}">show all</a>)</p>"#,
+                escape_html(&pattern),
+                if case_insensitive { ", ignoring case" } else { "" },
+                without_query_params(query, &["filter", "ci", "page"])
+            );
+            (Some(filter), notice)
         }
+        Err(e) => (None, format!("<p>Invalid filter <code>{}</code>: {}</p>", escape_html(&pattern), escape_html(&e))),
     }
-    
-    entries.sort_by(|a, b| {
-        if a.1 == b.1 {
-            a.0.cmp(&b.0)
-        } else {
-            b.1.cmp(&a.1)
+}
+
+// Link to the same listing with dotfiles shown or hidden the other way round,
+// keeping the sort order. The number of pages changes, so it goes back to the
+// first.
+fn hidden_toggle(query: &str, show_hidden: bool) -> String {
+    let query = without_query_params(query, &["page"]);
+    let (value, text) = if show_hidden { ("0", "Hide hidden files") } else { ("1", "Show hidden files") };
+    format!(r#"<p><a href="?{}">{}</a></p>"#, with_query_param(&query, "hidden", value), text)
+}
+
+// Link to the same listing in the other view, keeping its order, filter and
+// page.
+fn view_toggle(query: &str, grid: bool) -> String {
+    let (value, text) = if grid { ("table", "Table view") } else { ("grid", "Grid view") };
+    format!(r#"<p><a href="?{}">{}</a></p>"#, with_query_param(query, "view", value), text)
+}
+
+// Column headings linking to the listing sorted by that column: ascending
+// first, then toggling. The active column shows an arrow. A new order starts
+// again at the first page.
+fn column_headers(query: &str, sort: listing::Sort, details: bool) -> String {
+    let query = without_query_params(query, &["page"]);
+    let mut headers = String::new();
+    for (key, title) in listing::SortKey::ALL.into_iter().zip(["Name", "Size", "Modified"]) {
+        let active = sort.key == key;
+        let order = if active && !sort.descending { "desc" } else { "asc" };
+        let arrow = match (active, sort.descending) {
+            (false, _) => "",
+            (true, false) => " ▲",
+            (true, true) => " ▼",
+        };
+        let href = with_query_param(&with_query_param(&query, "sort", key.param()), "order", order);
+        headers.push_str(&format!(r#"<th><a href="?{}">{}{}</a></th>"#, href, title, arrow));
+    }
+    if details {
+        headers.push_str("<th>Permissions</th><th>Owner</th><th>Group</th>");
+    }
+    headers
+}
+
+// Whether a listing includes dotfiles: `?hidden=1` or `?hidden=0`, or else
+// the directory's `show_hidden`. Dotfiles are only left out of listings;
+// requests for them are served as usual.
+fn show_hidden(directory: &directory_config::DirectoryConfig, query: &str) -> bool {
+    hidden_param(query).unwrap_or(directory.show_hidden)
+}
+
+// `?hidden=1` or `?hidden=0`, when a request has either.
+fn hidden_param(query: &str) -> Option<bool> {
+    match query_param(query, "hidden").as_deref() {
+        Some("1") => Some(true),
+        Some("0") => Some(false),
+        _ => None,
+    }
+}
+
+// Whether a page shows permissions, owner and group: `?details=1` or
+// `?details=0`, or else `show_permissions`. Never off unix, where there are
+// none to show.
+fn show_details(config: &config::Config, query: &str) -> bool {
+    cfg!(unix)
+        && match query_param(query, "details").as_deref() {
+            Some("1") => true,
+            Some("0") => false,
+            _ => config.show_permissions,
         }
-    });
+}
+
+// How a listing orders names: `?sort_ci=1` or `?sort_ci=0` for whether case
+// is ignored, else `case_insensitive_sort`, and `?dirs_first=1` or
+// `?dirs_first=0` for whether directories come first, else
+// `group_dirs_first`.
+fn collation(config: &config::Config, query: &str) -> listing::Collation {
+    let flag = |name: &str, default: bool| match query_param(query, name).as_deref() {
+        Some("1") => true,
+        Some("0") => false,
+        _ => default,
+    };
+    listing::Collation {
+        lexicographic: !config.natural_sort,
+        case_insensitive: flag("sort_ci", config.case_insensitive_sort),
+        dirs_first: flag("dirs_first", config.group_dirs_first),
+        unicode: config.collation == config::NameCollation::Unicode,
+    }
+}
+
+// Whether a listing gives the total size of each subdirectory: `?du=1` or
+// `?du=0`, or else `recursive_sizes`. Only listings read whole do; the
+// streamed rows of huge directories never wait for walks.
+fn recursive_sizes(config: &config::Config, query: &str) -> bool {
+    match query_param(query, "du").as_deref() {
+        Some("1") => true,
+        Some("0") => false,
+        _ => config.recursive_sizes,
+    }
+}
+
+// Walks the subdirectories among `entries` of the directory at `path`. One
+// that is a symbolic link is walked only when it leads to somewhere inside
+// the root.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+async fn directory_sizes(
+    state: &ServerState,
+    site: &config::Site<'_>,
+    path: &Path,
+    entries: &[&listing::EntryInfo],
+) -> HashMap<OsString, du::Total> {
+    let root = fs::canonicalize(site.root).await.unwrap_or_else(|_| site.root.to_path_buf());
+    let mut dirs = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.is_dir) {
+        let dir = path.join(&entry.file_name);
+        let dir = match &entry.symlink {
+            Some(_) => match fs::canonicalize(&dir).await {
+                Ok(target) if target.starts_with(&root) => target,
+                _ => continue,
+            },
+            None => dir,
+        };
+        dirs.push((entry.file_name.clone(), dir));
+    }
+    let config = site.config;
+    let limits = du::Limits { max_depth: config.recursive_size_depth, max_entries: config.recursive_size_entries };
+    let totals = state.dir_sizes.totals(dirs.iter().map(|(_, dir)| dir.clone()).collect(), limits).await;
+    dirs.into_iter().filter_map(|(name, dir)| Some((name, *totals.get(&dir)?))).collect()
+}
+
+// Listings that do not walk their subdirectories say how many entries each
+// holds, as long as there are at most this many of them: counting reads
+// every one.
+const CHILD_COUNT_DIRECTORIES: usize = 50;
+
+// How many entries each subdirectory among `entries` of the directory at
+// `path` holds, or nothing when there are too many to count.
+async fn child_counts(path: &Path, entries: &[&listing::EntryInfo]) -> HashMap<OsString, usize> {
+    let dirs: Vec<_> = entries.iter().filter(|entry| entry.is_dir).collect();
+    let mut counts = HashMap::new();
+    if dirs.len() > CHILD_COUNT_DIRECTORIES {
+        return counts;
+    }
+    for entry in dirs {
+        if let Some(count) = listing::count_children(&path.join(&entry.file_name)).await {
+            counts.insert(entry.file_name.clone(), count);
+        }
+    }
+    counts
+}
+
+// Whether a listing's Modified column says how long ago rather than when:
+// `?times=relative` or `?times=absolute`, or else `relative_times`.
+fn relative_times(config: &config::Config, query: &str) -> bool {
+    match query_param(query, "times").as_deref() {
+        Some("relative") => true,
+        Some("absolute") => false,
+        _ => config.relative_times,
+    }
+}
 
-    let current_path = path.to_string_lossy();
-    let parent_path = path.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+// The permissions, owner and group cells of a listing row.
+fn details_cells(unix: Option<listing::UnixDetails>) -> String {
+    match unix {
+        Some(unix) => format!(
+            "<td><code>{}</code></td><td>{}</td><td>{}</td>",
+            owners::mode_string(unix.mode),
+            escape_html(&owners::user_name(unix.uid)),
+            escape_html(&owners::group_name(unix.gid))
+        ),
+        None => "<td>-</td><td>-</td><td>-</td>".to_string(),
+    }
+}
 
+fn listing_page_head(url_path: &Path, notice: &str, contents_open: &str) -> String {
     format!(
         r#"<!DOCTYPE html>
         <html>
@@ -115,6 +2829,19 @@ async fn generate_directory_listing(path: &Path) -> String {
                 th {{ background: #f8f9fa; }}
                 tr:hover {{ background: #f5f5f5; }}
                 .icon {{ margin-right: 8px; }}
+                .link-target {{ color: #666; }}
+                .broken, .broken a {{ color: #999; }}
+                .empty td {{ color: #999; text-align: center; }}
+                .summary {{ color: #666; }}
+                .search {{ margin-bottom: 12px; }}
+                .grid {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(160px, 1fr)); gap: 12px; }}
+                .tile {{ display: flex; flex-direction: column; align-items: center; padding: 8px; border: 1px solid #ddd; border-radius: 8px; overflow: hidden; }}
+                .tile .preview {{ height: 120px; display: flex; align-items: center; justify-content: center; font-size: 48px; }}
+                .tile img {{ max-width: 100%; max-height: 120px; object-fit: contain; }}
+                .tile .name {{ width: 100%; text-align: center; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }}
+                .grid .empty {{ grid-column: 1 / -1; color: #999; text-align: center; }}
+                .readme {{ margin-top: 20px; padding: 0 20px 20px; border: 1px solid #ddd; border-radius: 8px; }}
+                .readme pre {{ white-space: pre-wrap; }}
                 a {{ color: #0366d6; text-decoration: none; }}
                 a:hover {{ text-decoration: underline; }}
             </style>
@@ -124,53 +2851,323 @@ async fn generate_directory_listing(path: &Path) -> String {
                 <div class="header">
                     <h1>File Browser</h1>
                     <div class="breadcrumb">
-                        <a href="/">Root</a> / {}</div>
+                        {}</div>
+                    {}
                 </div>
-                <table>
+                {}
+"#,
+        escape_html(&url_path.to_string_lossy()),
+        breadcrumbs(url_path),
+        notice,
+        contents_open
+    )
+}
+
+// The start of the listing's entries, up to and including the parent
+// directory's: a table with `columns`, or the grid of tiles.
+fn listing_contents_open(grid: bool, columns: &str, parent_row: &str) -> String {
+    if grid {
+        return format!(
+            r#"<div class="grid">
+                    {}
+"#,
+            parent_row
+        );
+    }
+    format!(
+        r#"<table>
                     <thead>
                         <tr>
-                            <th>Name</th>
-                            <th>Size</th>
-                            <th>Modified</th>
+                            {}
                         </tr>
                     </thead>
                     <tbody>
                         {}
+"#,
+        columns, parent_row
+    )
+}
+
+// "Root / a / b / c" for `/a/b/c`, each part but the last linking to its
+// directory.
+fn breadcrumbs(url_path: &Path) -> String {
+    let mut trail = vec![format!(r#"<a href="{}">Root</a>"#, link("/"))];
+    let segments: Vec<&OsStr> = url_path.iter().filter(|segment| *segment != "/").collect();
+    let mut prefix = PathBuf::from("/");
+    for (i, segment) in segments.iter().enumerate() {
+        prefix.push(segment);
+        let name = escape_html(&segment.to_string_lossy());
+        if i + 1 < segments.len() {
+            trail.push(format!(r#"<a href="{}">{}</a>"#, directory_link(&prefix), name));
+        } else {
+            trail.push(name);
+        }
+    }
+    trail.join(" / ")
+}
+
+// The directory's README, in a panel for beneath the listing, or nothing when
+// there is none or `show_readme` is off. A README the server would refuse to
+// send is not shown either.
+async fn readme_panel(config: &config::Config, directory: &directory_config::DirectoryConfig, dir: &Path) -> String {
+    if !config.show_readme {
+        return String::new();
+    }
+    let Some((path, metadata)) = readme::find(dir).await else {
+        return String::new();
+    };
+    if refuse_file(directory, &path, &metadata).is_some() {
+        return String::new();
+    }
+    match readme::render(&path, &metadata).await {
+        Some(html) => format!(
+            r#"<div class="readme"><h2>{}</h2>{}</div>"#,
+            escape_html(&path.file_name().unwrap_or_default().to_string_lossy()),
+            html
+        ),
+        None => String::new(),
+    }
+}
+
+// Closes the table, with a row saying so when it has no entries, under a
+// count of the rows above it ("2 directories, 3 files, 1.2 KiB total") and
+// the README panel.
+fn listing_page_foot(summary: &listing::Summary, style: &RowStyle, readme: &str) -> String {
+    let (empty_row, contents_close) = match (summary.is_empty(), style.grid) {
+        (true, true) => (r#"<p class="empty">This directory is empty</p>"#.to_string(), "</div>"),
+        (true, false) => {
+            let columns = if style.details { 6 } else { 3 };
+            (format!(r#"<tr class="empty"><td colspan="{}">This directory is empty</td></tr>"#, columns), "</tbody>\n                </table>")
+        }
+        (false, true) => (String::new(), "</div>"),
+        (false, false) => (String::new(), "</tbody>\n                </table>"),
+    };
+    let count = |n: usize, one: &str, many: &str| format!("{} {}", group_digits(n as u64), if n == 1 { one } else { many });
+    format!(
+        r#"
                         {}
-                    </tbody>
-                </table>
+                    {}
+                <p class="summary">{}, {}, {} total</p>
+                {}
             </div>
         </body>
         </html>"#,
-        current_path,
-        current_path,
-        if !path.as_os_str().is_empty() {
-            format!(r#"<tr><td><a href="{}">📁 ..</a></td><td>-</td><td>-</td></tr>"#, parent_path)
-        } else {
-            String::new()
-        },
-        entries.iter().map(|(name, is_dir, size, modified)| {
-            let encoded_path = percent_encode(format!("{}/{}", current_path, name).as_bytes(), NON_ALPHANUMERIC).to_string();
+        empty_row,
+        contents_close,
+        count(summary.directories, "directory", "directories"),
+        count(summary.files, "file", "files"),
+        human_size(summary.bytes, style.size_units),
+        readme
+    )
+}
+
+// Which optional columns and formats the rows of a listing use.
+#[derive(Clone)]
+struct RowStyle {
+    // Tiles with image previews in place of table rows, for `?view=grid`.
+    grid: bool,
+    details: bool,
+    relative_times: bool,
+    timestamps: timestamps::Timestamps,
+    size_units: config::SizeUnits,
+    // Totals of the subdirectories, by name, when they were asked for.
+    recursive_sizes: HashMap<OsString, du::Total>,
+    // How many entries the subdirectories hold, by name, when they were
+    // counted; see `child_counts`.
+    child_counts: HashMap<OsString, usize>,
+}
+
+fn render_listing_row(current_path: &OsStr, entry: &listing::EntryInfo, style: &RowStyle) -> String {
+    let mut path = current_path.to_owned();
+    path.push("/");
+    path.push(&entry.file_name);
+    if entry.is_dir {
+        path.push("/");
+    }
+    let encoded_path = link(path);
+    if style.grid {
+        return render_listing_tile(&encoded_path, entry);
+    }
+    // A relative time keeps the exact one as its tooltip.
+    let modified = match entry.modified {
+        Some(modified) => {
+            let exact = escape_html(&style.timestamps.format(modified));
+            if style.relative_times {
+                format!(r#"<td title="{}">{}</td>"#, exact, relative_time::describe(modified, std::time::SystemTime::now()))
+            } else {
+                format!("<td>{}</td>", exact)
+            }
+        }
+        None => "<td>-</td>".to_string(),
+    };
+    let icon = icons::icon(&entry.name, entry.is_dir, entry.symlink.is_some(), entry.unix.is_some_and(|unix| unix.executable()));
+    // Links show where they point, and ones pointing nowhere are greyed out
+    // rather than left out.
+    let (row_class, link_target) = match &entry.symlink {
+        Some(symlink) => (
+            if symlink.broken { r#" class="broken""# } else { "" },
             format!(
-                r#"<tr>
-                    <td><a href="{}">{} {}</a></td>
-                    <td>{}</td>
-                    <td>{}</td>
-                </tr>"#,
-                encoded_path,
-                if *is_dir { "📁" } else { "📄" },
-                name,
-                if *is_dir { "-" } else { size },
-                modified
-            )
-        }).collect::<Vec<_>>().join("\n")
+                r#" <span class="link-target">→ {}</span>"#,
+                symlink.target.as_ref().map(|target| escape_html(&target.to_string_lossy())).unwrap_or_else(|| "?".to_string())
+            ),
+        ),
+        None => ("", String::new()),
+    };
+    format!(
+        r#"<tr{}>
+                    <td><a href="{}">{} {}</a>{}</td>
+                    {}
+                    {}{}
+                </tr>
+"#,
+        row_class,
+        encoded_path,
+        icon,
+        escape_html(&entry.name),
+        link_target,
+        size_cell(entry, style),
+        modified,
+        if style.details { details_cells(entry.unix) } else { String::new() }
     )
 }
 
-async fn generate_file_info(path: &Path, metadata: &std::fs::Metadata) -> String {
-    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-    let size = format_size(metadata.len(), BINARY);
-    let modified: DateTime<Local> = metadata.modified().unwrap().into();
+// A grid view tile: a preview for images browsers can show, the icon for
+// anything else, over the name, which is cut short with its full form as the
+// tooltip.
+fn render_listing_tile(encoded_path: &str, entry: &listing::EntryInfo) -> String {
+    let name = escape_html(&entry.name);
+    let broken = entry.symlink.as_ref().is_some_and(|symlink| symlink.broken);
+    let preview = if !entry.is_dir && !broken && icons::previewable(&entry.name) {
+        format!(r#"<img src="{}?raw=1" alt="" loading="lazy">"#, encoded_path)
+    } else {
+        icons::icon(&entry.name, entry.is_dir, entry.symlink.is_some(), entry.unix.is_some_and(|unix| unix.executable())).to_string()
+    };
+    format!(
+        r#"<a class="tile{}" href="{}" title="{}"><span class="preview">{}</span><span class="name">{}</span></a>
+"#,
+        if broken { " broken" } else { "" },
+        encoded_path,
+        name,
+        preview,
+        name
+    )
+}
+
+// Files give their size in the configured units with the exact count as a
+// tooltip, directories their total size when walked, or else how many
+// entries they hold when that was counted.
+fn size_cell(entry: &listing::EntryInfo, style: &RowStyle) -> String {
+    match (entry.is_dir, entry.size, style.recursive_sizes.get(&entry.file_name)) {
+        (false, Some(size), _) => format!(r#"<td title="{}">{}</td>"#, exact_size(size), human_size(size, style.size_units)),
+        // A total cut short by the limits is a lower bound.
+        (true, _, Some(total)) => format!(
+            r#"<td title="{}{}">{}{}</td>"#,
+            if total.truncated { "at least " } else { "" },
+            exact_size(total.bytes),
+            if total.truncated { "≥ " } else { "" },
+            human_size(total.bytes, style.size_units)
+        ),
+        (true, _, None) => match style.child_counts.get(&entry.file_name) {
+            Some(&count) if count > listing::CHILD_COUNT_LIMIT => {
+                format!("<td>{}+ items</td>", group_digits(listing::CHILD_COUNT_LIMIT as u64))
+            }
+            Some(1) => "<td>1 item</td>".to_string(),
+            Some(&count) => format!("<td>{} items</td>", group_digits(count as u64)),
+            None => "<td>-</td>".to_string(),
+        },
+        _ => "<td>-</td>".to_string(),
+    }
+}
+
+fn human_size(size: u64, units: config::SizeUnits) -> String {
+    match units {
+        config::SizeUnits::Binary => format_size(size, BINARY),
+        config::SizeUnits::Decimal => format_size(size, DECIMAL),
+    }
+}
+
+// "1,234,567 bytes".
+fn exact_size(size: u64) -> String {
+    match size {
+        1 => "1 byte".to_string(),
+        size => format!("{} bytes", group_digits(size)),
+    }
+}
+
+fn group_digits(number: u64) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// For a symbolic link the page gives the link's own details and then its
+// target's, or says the target is missing (`metadata` is then None).
+// `permissions` adds the mode, owner and group, as listings show them.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+async fn generate_file_info(
+    url_path: &Path,
+    path: &Path,
+    metadata: Option<&std::fs::Metadata>,
+    permissions: bool,
+    timestamps: &timestamps::Timestamps,
+    size_units: config::SizeUnits,
+) -> String {
+    let file_name = escape_html(&path.file_name().unwrap_or_default().to_string_lossy());
+    let format_modified = |metadata: &std::fs::Metadata| match metadata.modified() {
+        Ok(modified) => escape_html(&timestamps.format(modified)),
+        Err(_) => "-".to_string(),
+    };
+    let mut details = String::new();
+    let link_metadata = fs::symlink_metadata(path).await.ok().filter(|metadata| metadata.file_type().is_symlink());
+    if let Some(link_metadata) = &link_metadata {
+        let target = match fs::read_link(path).await {
+            Ok(target) => escape_html(&target.to_string_lossy()),
+            Err(_) => "?".to_string(),
+        };
+        details.push_str(&format!(
+            "<p>Symbolic link to: {}</p>\n                <p>Link modified: {}</p>\n                ",
+            target,
+            format_modified(link_metadata)
+        ));
+    }
+    match metadata {
+        Some(metadata) => {
+            details.push_str(&format!(
+                "<p>Size: {} ({})</p>\n                <p>Modified: {}</p>\n                ",
+                human_size(metadata.len(), size_units),
+                exact_size(metadata.len()),
+                format_modified(metadata)
+            ));
+            if let Some(unix) = listing::UnixDetails::of(metadata).filter(|_| permissions) {
+                details.push_str(&format!(
+                    "<p>Permissions: <code>{}</code> ({:04o})</p>\n                <p>Owner: {}</p>\n                <p>Group: {}</p>\n                ",
+                    owners::mode_string(unix.mode),
+                    unix.mode & 0o7777,
+                    escape_html(&owners::user_name(unix.uid)),
+                    escape_html(&owners::group_name(unix.gid))
+                ));
+            }
+            details.push_str(r#"<p><a href="?raw=1">Open file</a></p>"#);
+            details.push_str(&format!(
+                r#"<p><a href="{}?{}&amp;algo=sha256">Compute SHA-256 checksum</a></p>"#,
+                link(API_HASH_PATH),
+                with_query_param("", "path", &url_path.to_string_lossy())
+            ));
+        }
+        None => details.push_str("<p>The link's target is missing.</p>"),
+    }
+    let icon = icons::icon(
+        &path.file_name().unwrap_or_default().to_string_lossy(),
+        false,
+        link_metadata.is_some(),
+        metadata.and_then(listing::UnixDetails::of).is_some_and(|unix| unix.executable()),
+    );
 
     format!(
         r#"<!DOCTYPE html>
@@ -187,38 +3184,85 @@ async fn generate_file_info(path: &Path, metadata: &std::fs::Metadata) -> String
         </head>
         <body>
             <div class="back-link">
-                <a href="javascript:history.back()">← Back</a>
+                <a href="./">← Back</a>
             </div>
             <div class="file-info">
-                <h2>📄 {}</h2>
-                <p>Size: {}</p>
-                <p>Modified: {}</p>
+                <h2>{} {}</h2>
+                {}
             </div>
         </body>
         </html>"#,
         file_name,
+        icon,
         file_name,
-        size,
-        modified.format("%Y-%m-%d %H:%M:%S")
+        details
     )
 }
 
-fn generate_error_page() -> String {
+// `text` made safe to place in HTML, including inside attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn generate_error_page(title: &str, message: &str) -> String {
     format!(
         r#"<!DOCTYPE html>
         <html>
         <head>
-            <title>Error - Path Not Found</title>
+            <title>Error - {}</title>
             <style>
                 body {{ font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif; margin: 40px; }}
                 .error {{ color: #dc3545; }}
             </style>
         </head>
         <body>
-            <h1 class="error">404 - Path Not Found</h1>
-            <p>The requested path could not be found.</p>
-            <a href="/">Return to Home</a>
+            <h1 class="error">{}</h1>
+            <p>{}</p>
+            <a href="{}">Return to Home</a>
         </body>
-        </html>"#
+        </html>"#,
+        title,
+        title,
+        message,
+        link("/")
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_path, extract_target};
+    use std::path::PathBuf;
+
+    // The path a request line asks for, as `respond` finds it. How the path
+    // itself is taken apart is tested in `url_path`.
+    fn requested_path(request: &str) -> PathBuf {
+        extract_path(extract_target(request))
+    }
+
+    #[test]
+    fn well_formed_request() {
+        assert_eq!(requested_path("GET /docs/notes.txt HTTP/1.1\r\nHost: x\r\n\r\n"), PathBuf::from("/docs/notes.txt"));
+    }
+
+    #[test]
+    fn empty_request_line_is_the_root() {
+        assert_eq!(requested_path(""), PathBuf::from("/"));
+        assert_eq!(requested_path("\r\nHost: x\r\n"), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn missing_target_is_the_root() {
+        assert_eq!(requested_path("GET"), PathBuf::from("/"));
+    }
+}
@@ -0,0 +1,93 @@
+// Counters for the `/_metrics` endpoint, in the Prometheus text format.
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::Instant;
+
+// Prometheus' own default buckets, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+pub struct Metrics {
+    // From the first byte of a request to the last byte of its response.
+    pub request_duration: Histogram,
+}
+
+impl Metrics {
+    pub fn new(buckets: &[f64]) -> Self {
+        Metrics { request_duration: Histogram::new(buckets) }
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        self.request_duration.render(
+            &mut text,
+            "gredl_request_duration_seconds",
+            "Time from the first byte of a request to the last byte of its response.",
+        );
+        text
+    }
+}
+
+pub struct Histogram {
+    // Upper bounds, increasing; the `+Inf` bucket is implied.
+    bounds: Vec<f64>,
+    // Observations per bucket, not cumulative: the last one counts those
+    // above every bound.
+    counts: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, seconds: f64) {
+        let bucket = self.bounds.iter().position(|bound| seconds <= *bound).unwrap_or(self.bounds.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add((seconds * 1e9) as u64, Ordering::Relaxed);
+    }
+
+    // Observes the time from `started` when the returned timer is dropped.
+    pub fn start_timer(&self, started: Instant) -> Timer<'_> {
+        Timer { histogram: Some(self), started }
+    }
+
+    fn render(&self, text: &mut String, name: &str, help: &str) {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        cumulative += self.counts[self.bounds.len()].load(Ordering::Relaxed);
+        let _ = writeln!(text, "{}_bucket{{le=\"+Inf\"}} {}", name, cumulative);
+        let _ = writeln!(text, "{}_sum {}", name, self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9);
+        let _ = writeln!(text, "{}_count {}", name, cumulative);
+    }
+}
+
+pub struct Timer<'a> {
+    histogram: Option<&'a Histogram>,
+    started: Instant,
+}
+
+impl Timer<'_> {
+    // For responses that are not requests in the latency sense, such as
+    // watches that stay open until the client leaves.
+    pub fn discard(&mut self) {
+        self.histogram = None;
+    }
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        if let Some(histogram) = self.histogram {
+            histogram.observe(self.started.elapsed().as_secs_f64());
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Built-in extension to MIME type table. Extensions are lowercase and matched
+// case-insensitively against the last extension of the file name.
+const TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("mjs", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("log", "text/plain; charset=utf-8"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("avif", "image/avif"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("flac", "audio/flac"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("mkv", "video/x-matroska"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("7z", "application/x-7z-compressed"),
+    ("wasm", "application/wasm"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+];
+
+pub const DEFAULT_TYPE: &str = "application/octet-stream";
+
+// `overrides` is the `[mime]` table of the configuration, keyed like the
+// built-in one, and wins over it.
+pub fn content_type<'a>(path: &Path, overrides: &'a BTreeMap<String, String>) -> &'a str {
+    let Some(extension) = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()) else {
+        return DEFAULT_TYPE;
+    };
+
+    if let Some(mime) = overrides.get(&extension) {
+        return mime;
+    }
+    TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or(DEFAULT_TYPE)
+}
+
+// Cache-Control of files whose type `cache_policy` does not list. Their
+// validators still spare a client that revalidates the whole download.
+pub const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+// What `policy`, the `[cache_policy]` table keyed like `[mime]`, says about
+// files like `path`; None when it does not list their extension.
+pub fn cache_control<'a>(path: &Path, policy: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    policy.get(&extension).map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_control, content_type, DEFAULT_TYPE, TYPES};
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    fn built_in(name: &str) -> &'static str {
+        static NO_OVERRIDES: BTreeMap<String, String> = BTreeMap::new();
+        content_type(Path::new(name), &NO_OVERRIDES)
+    }
+
+    #[test]
+    fn every_built_in_extension_matches_in_any_case() {
+        for (extension, mime) in TYPES {
+            assert_eq!(built_in(&format!("file.{}", extension)), *mime, "{}", extension);
+            assert_eq!(built_in(&format!("FILE.{}", extension.to_uppercase())), *mime, "{}", extension);
+            let mixed: String = extension
+                .chars()
+                .enumerate()
+                .map(|(i, c)| if i % 2 == 0 { c.to_ascii_uppercase() } else { c })
+                .collect();
+            assert_eq!(built_in(&format!("dir/file.{}", mixed)), *mime, "{}", mixed);
+        }
+    }
+
+    #[test]
+    fn built_in_extensions_are_lowercase_and_unique() {
+        for (i, (extension, _)) in TYPES.iter().enumerate() {
+            assert_eq!(*extension, extension.to_lowercase());
+            assert!(TYPES[i + 1..].iter().all(|(other, _)| other != extension), "{} is listed twice", extension);
+        }
+    }
+
+    #[test]
+    fn only_the_last_extension_counts() {
+        assert_eq!(built_in("backup.tar.gz"), "application/gzip");
+        assert_eq!(built_in("site.tar"), "application/x-tar");
+        assert_eq!(built_in("notes.txt.bak"), DEFAULT_TYPE);
+        assert_eq!(built_in("archive.gz.txt"), "text/plain; charset=utf-8");
+    }
+
+    #[test]
+    fn names_without_an_extension_get_the_default() {
+        assert_eq!(built_in("Makefile"), DEFAULT_TYPE);
+        assert_eq!(built_in("trailing."), DEFAULT_TYPE);
+        // A leading dot starts a hidden name, not an extension.
+        assert_eq!(built_in(".gitignore"), DEFAULT_TYPE);
+        assert_eq!(built_in(".html"), DEFAULT_TYPE);
+        assert_eq!(built_in(".config.json"), "application/json");
+    }
+
+    #[test]
+    fn unknown_extensions_get_the_default() {
+        assert_eq!(built_in("part.gcode"), "application/octet-stream");
+        assert_eq!(built_in("photo.heic"), DEFAULT_TYPE);
+    }
+
+    #[test]
+    fn overrides_win_over_the_built_in_table() {
+        let overrides = BTreeMap::from([("txt".to_string(), "text/plain; charset=iso-8859-1".to_string())]);
+        assert_eq!(content_type(Path::new("a.TXT"), &overrides), "text/plain; charset=iso-8859-1");
+        assert_eq!(content_type(Path::new("a.md"), &overrides), "text/markdown; charset=utf-8");
+    }
+
+    #[test]
+    fn cache_policy_is_looked_up_by_the_last_extension() {
+        let policy = BTreeMap::from([("js".to_string(), "public, max-age=31536000, immutable".to_string())]);
+        assert_eq!(cache_control(Path::new("app.min.JS"), &policy), Some("public, max-age=31536000, immutable"));
+        assert_eq!(cache_control(Path::new("app.js.map"), &policy), None);
+        assert_eq!(cache_control(Path::new(".js"), &policy), None);
+    }
+}
@@ -0,0 +1,105 @@
+// `ls -l` style details of a file: its mode as `drwxr-xr-x`, and the names of
+// its owner and group. Names are looked up once per id and then cached; an
+// id with no name (or one looked up inside a chroot, where /etc/passwd is out
+// of reach) is shown as the number.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+static USER_NAMES: Mutex<BTreeMap<u32, String>> = Mutex::new(BTreeMap::new());
+static GROUP_NAMES: Mutex<BTreeMap<u32, String>> = Mutex::new(BTreeMap::new());
+
+pub fn user_name(uid: u32) -> String {
+    cached(&USER_NAMES, uid, lookup_user)
+}
+
+pub fn group_name(gid: u32) -> String {
+    cached(&GROUP_NAMES, gid, lookup_group)
+}
+
+fn cached(names: &Mutex<BTreeMap<u32, String>>, id: u32, lookup: fn(u32) -> Option<String>) -> String {
+    if let Some(name) = names.lock().unwrap().get(&id) {
+        return name.clone();
+    }
+    let name = lookup(id).unwrap_or_else(|| id.to_string());
+    names.lock().unwrap().insert(id, name.clone());
+    name
+}
+
+// The file type letter followed by read, write and execute for owner, group
+// and others, with the setuid, setgid and sticky bits in the execute places.
+pub fn mode_string(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut text = String::from(file_type);
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => letter,
+            (false, true) => letter.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+// The reentrant lookups report ERANGE while the buffer is too small for the
+// entry, which for a group with many members can take a few doublings.
+#[cfg(unix)]
+fn lookup_with_buffer(mut lookup: impl FnMut(&mut [libc::c_char]) -> Result<Option<String>, i32>) -> Option<String> {
+    let mut buffer = vec![0; 1024];
+    loop {
+        match lookup(&mut buffer) {
+            Ok(name) => return name,
+            Err(libc::ERANGE) if buffer.len() < 1 << 20 => buffer.resize(buffer.len() * 2, 0),
+            Err(_) => return None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lookup_user(uid: u32) -> Option<String> {
+    lookup_with_buffer(|buffer| {
+        let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let status = unsafe { libc::getpwuid_r(uid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+        match status {
+            0 if result.is_null() => Ok(None),
+            0 => Ok(Some(unsafe { std::ffi::CStr::from_ptr(entry.pw_name) }.to_string_lossy().into_owned())),
+            error => Err(error),
+        }
+    })
+}
+
+#[cfg(unix)]
+fn lookup_group(gid: u32) -> Option<String> {
+    lookup_with_buffer(|buffer| {
+        let mut entry: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        let status = unsafe { libc::getgrgid_r(gid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+        match status {
+            0 if result.is_null() => Ok(None),
+            0 => Ok(Some(unsafe { std::ffi::CStr::from_ptr(entry.gr_name) }.to_string_lossy().into_owned())),
+            error => Err(error),
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn lookup_user(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn lookup_group(_gid: u32) -> Option<String> {
+    None
+}
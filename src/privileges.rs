@@ -0,0 +1,115 @@
+use std::io;
+
+// The identity to switch to once everything that needs root (binding low
+// ports, opening the log/pid/key files, entering the sandbox) has been done.
+// Names are resolved up front because /etc/passwd is no longer reachable
+// after a chroot.
+#[cfg(unix)]
+pub struct Identity {
+    uid: Option<libc::uid_t>,
+    gid: Option<libc::gid_t>,
+}
+
+#[cfg(not(unix))]
+pub struct Identity;
+
+// `user` and `group` accept either names or numeric ids; when only `user` is
+// given the group defaults to that user's primary group.
+#[cfg(unix)]
+pub fn resolve(user: Option<&str>, group: Option<&str>) -> io::Result<Option<Identity>> {
+    if user.is_none() && group.is_none() {
+        return Ok(None);
+    }
+
+    let passwd = user.map(lookup_user).transpose()?;
+    let gid = match group {
+        Some(group) => Some(lookup_group(group)?),
+        None => passwd.map(|(_, gid)| gid),
+    };
+
+    Ok(Some(Identity { uid: passwd.map(|(uid, _)| uid), gid }))
+}
+
+#[cfg(not(unix))]
+pub fn resolve(user: Option<&str>, group: Option<&str>) -> io::Result<Option<Identity>> {
+    if user.is_none() && group.is_none() {
+        return Ok(None);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--user/--group are only supported on unix platforms",
+    ))
+}
+
+impl Identity {
+    // Whether `apply` gives up root, which a chroot needs to hold.
+    #[cfg(unix)]
+    pub fn leaves_root(&self) -> bool {
+        self.uid.is_some_and(|uid| uid != 0)
+    }
+
+    #[cfg(not(unix))]
+    pub fn leaves_root(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn apply(&self) -> io::Result<()> {
+        // Order matters: supplementary groups and the gid can only be changed
+        // while we are still root.
+        if let Some(gid) = self.gid {
+            if unsafe { libc::setgroups(1, &gid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if unsafe { libc::setgid(gid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if let Some(uid) = self.uid {
+            if unsafe { libc::setuid(uid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Paranoia: if we can get root back the switch did not stick.
+            if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+                return Err(io::Error::other("privileges could be regained after setuid"));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn lookup_user(name: &str) -> io::Result<(libc::uid_t, libc::gid_t)> {
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "user name contains a NUL byte"))?;
+    let entry = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    if !entry.is_null() {
+        return Ok(unsafe { ((*entry).pw_uid, (*entry).pw_gid) });
+    }
+
+    let uid: libc::uid_t = name
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("unknown user '{}'", name)))?;
+    let entry = unsafe { libc::getpwuid(uid) };
+    let gid = if entry.is_null() { uid } else { unsafe { (*entry).pw_gid } };
+    Ok((uid, gid))
+}
+
+#[cfg(unix)]
+fn lookup_group(name: &str) -> io::Result<libc::gid_t> {
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "group name contains a NUL byte"))?;
+    let entry = unsafe { libc::getgrnam(c_name.as_ptr()) };
+    if !entry.is_null() {
+        return Ok(unsafe { (*entry).gr_gid });
+    }
+
+    name.parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("unknown group '{}'", name)))
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// An inclusive byte range within a file of known length.
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+pub enum RangeRequest {
+    // No usable `Range` header: send the whole file with 200.
+    Full,
+    Partial(ByteRange),
+    // Several ranges, sent as a `multipart/byteranges` body.
+    Multiple(Vec<ByteRange>),
+    // Answered with 416 and `Content-Range: bytes */<len>`.
+    Unsatisfiable,
+}
+
+// More ranges than this in one request are not worth the per-part overhead
+// (and can be used to make a small file expensive to serve); such requests
+// get the full file instead.
+const MAX_RANGES: usize = 16;
+
+// Interprets a `Range` header against a file of `len` bytes. Headers that do
+// not parse, use a unit other than bytes, or ask for too many ranges are
+// ignored, which the spec allows, and the full file is sent instead. Ranges
+// that start past the end of the file are dropped; if none remain, the request
+// cannot be satisfied.
+pub fn parse(header: Option<&str>, len: u64) -> RangeRequest {
+    let Some(specs) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    let specs: Vec<&str> = specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()).collect();
+    if specs.is_empty() || specs.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs {
+        match parse_one(spec, len) {
+            Some(Some(range)) => ranges.push(range),
+            Some(None) => {}
+            None => return RangeRequest::Full,
+        }
+    }
+    match ranges.len() {
+        0 => RangeRequest::Unsatisfiable,
+        1 => RangeRequest::Partial(ranges.remove(0)),
+        _ => RangeRequest::Multiple(ranges),
+    }
+}
+
+// One `first-last` spec: None if it does not parse, Some(None) if it parses
+// but lies outside the file.
+fn parse_one(spec: &str, len: u64) -> Option<Option<ByteRange>> {
+    let (start, end) = spec.split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        // `-N`: the last N bytes.
+        ("", suffix) => match suffix.parse::<u64>().ok()? {
+            0 => return Some(None),
+            suffix => ByteRange { start: len.saturating_sub(suffix), end: len.wrapping_sub(1) },
+        },
+        // `N-` or `N-M`, with M clamped to the end of the file.
+        (start, end) => {
+            let start = start.parse::<u64>().ok()?;
+            let end = match end {
+                "" => len.wrapping_sub(1),
+                end => match end.parse::<u64>().ok()? {
+                    end if end >= start => end.min(len.wrapping_sub(1)),
+                    _ => return None,
+                },
+            };
+            ByteRange { start, end }
+        }
+    };
+
+    if len == 0 || range.start >= len {
+        return Some(None);
+    }
+    Some(Some(range))
+}
+
+// Strong validator for a file, derived from its size and modification time.
+pub fn etag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!("\"{:x}-{:x}-{:x}\"", len, modified.as_secs(), modified.subsec_nanos())
+}
+
+// `Last-Modified` value in IMF-fixdate form.
+pub fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+// Whether a `Range` header may be honoured given the request's `If-Range`.
+// An entity tag must match ours exactly (weak tags never do); a date must
+// equal the file's modification time to the second. Anything else means the
+// client's partial copy is stale and it gets the full file.
+pub fn if_range_allows(if_range: Option<&str>, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(if_range) = if_range.map(str::trim) else {
+        return true;
+    };
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return if_range == etag;
+    }
+    match (DateTime::parse_from_rfc2822(if_range), modified) {
+        (Ok(date), Some(modified)) => {
+            let modified = DateTime::<Utc>::from(modified).timestamp();
+            date.timestamp() == modified
+        }
+        _ => false,
+    }
+}
+
+// Separator for `multipart/byteranges` bodies. It only has to be absent from
+// the ranges it separates; a value that changes per response makes an
+// accidental match in file contents vanishingly unlikely.
+pub fn boundary() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("gredl-{:x}{:08x}", now.as_secs(), now.subsec_nanos())
+}
@@ -0,0 +1,45 @@
+// A README shown beneath a directory's listing, the way code hosts show one.
+// Markdown is rendered to HTML and then sanitized, so a README cannot put
+// scripts, styles or event handlers on the page; a plain text README is
+// escaped into a `<pre>`.
+use pulldown_cmark::{html, Options, Parser};
+use std::path::{Path, PathBuf};
+
+// Looked for in this order; the first that is a file is shown.
+const NAMES: &[&str] = &["README.md", "README.txt"];
+
+// Larger READMEs are left out rather than cut short, since markdown cut at an
+// arbitrary point can render as something else entirely.
+pub const MAX_SIZE: u64 = 256 * 1024;
+
+pub async fn find(dir: &Path) -> Option<(PathBuf, std::fs::Metadata)> {
+    for name in NAMES {
+        let path = dir.join(name);
+        if let Ok(metadata) = tokio::fs::metadata(&path).await {
+            if metadata.is_file() {
+                return Some((path, metadata));
+            }
+        }
+    }
+    None
+}
+
+// The README as HTML, or None when it is too large or cannot be read.
+pub async fn render(path: &Path, metadata: &std::fs::Metadata) -> Option<String> {
+    if metadata.len() > MAX_SIZE {
+        return None;
+    }
+    let contents = tokio::fs::read(path).await.ok()?;
+    // It may have grown since it was looked at.
+    if contents.len() as u64 > MAX_SIZE {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&contents);
+    if path.extension().is_some_and(|extension| extension == "md") {
+        let mut rendered = String::new();
+        html::push_html(&mut rendered, Parser::new_ext(&text, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH));
+        Some(ammonia::clean(&rendered))
+    } else {
+        Some(format!("<pre>{}</pre>", crate::escape_html(&text)))
+    }
+}
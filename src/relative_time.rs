@@ -0,0 +1,88 @@
+use std::time::{Duration, SystemTime};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+// How long before `now` a file was modified, the way a person would say it:
+// "just now", "5 minutes ago", "yesterday", "last year". Each unit is
+// counted in whole steps, rounding down. Times slightly in the future come
+// from clocks that disagree and read as "just now"; further ones are only
+// said to be in the future.
+pub fn describe(modified: SystemTime, now: SystemTime) -> String {
+    let elapsed = match now.duration_since(modified) {
+        Ok(elapsed) => elapsed,
+        Err(e) if e.duration() < Duration::from_secs(MINUTE) => Duration::ZERO,
+        Err(_) => return "in the future".to_string(),
+    };
+    let seconds = elapsed.as_secs();
+    let (count, unit, previous) = match seconds {
+        0..10 => return "just now".to_string(),
+        10..MINUTE => (seconds, "second", None),
+        MINUTE..HOUR => (seconds / MINUTE, "minute", None),
+        HOUR..DAY => (seconds / HOUR, "hour", None),
+        DAY..MONTH => (seconds / DAY, "day", Some("yesterday")),
+        MONTH..YEAR => (seconds / MONTH, "month", Some("last month")),
+        _ => (seconds / YEAR, "year", Some("last year")),
+    };
+    match (count, previous) {
+        (1, Some(previous)) => previous.to_string(),
+        (1, None) => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", count, unit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe, DAY, HOUR, MINUTE, MONTH, YEAR};
+    use std::time::{Duration, SystemTime};
+
+    // A fixed moment, so that nothing depends on how long the test takes.
+    fn now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(50 * YEAR)
+    }
+
+    fn ago(seconds: u64) -> String {
+        describe(now() - Duration::from_secs(seconds), now())
+    }
+
+    #[test]
+    fn each_unit_starts_at_its_boundary() {
+        let cases = [
+            (0, "just now"),
+            (9, "just now"),
+            (10, "10 seconds ago"),
+            (MINUTE - 1, "59 seconds ago"),
+            (MINUTE, "1 minute ago"),
+            (2 * MINUTE - 1, "1 minute ago"),
+            (2 * MINUTE, "2 minutes ago"),
+            (HOUR - 1, "59 minutes ago"),
+            (HOUR, "1 hour ago"),
+            (DAY - 1, "23 hours ago"),
+            (DAY, "yesterday"),
+            (2 * DAY - 1, "yesterday"),
+            (2 * DAY, "2 days ago"),
+            (MONTH - 1, "29 days ago"),
+            (MONTH, "last month"),
+            (2 * MONTH, "2 months ago"),
+            (YEAR - 1, "12 months ago"),
+            (YEAR, "last year"),
+            (2 * YEAR - 1, "last year"),
+            (2 * YEAR, "2 years ago"),
+        ];
+        for (seconds, expected) in cases {
+            assert_eq!(ago(seconds), expected, "{} seconds", seconds);
+        }
+    }
+
+    #[test]
+    fn slightly_skewed_clocks_read_as_just_now() {
+        let later = |seconds| describe(now() + Duration::from_secs(seconds), now());
+        assert_eq!(later(1), "just now");
+        assert_eq!(later(MINUTE - 1), "just now");
+        assert_eq!(later(MINUTE), "in the future");
+        assert_eq!(later(3 * DAY), "in the future");
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::Instant;
+
+// Request heads larger than this are rejected outright.
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+// Reads until the blank line that ends the request head. Returns the head as
+// text plus whatever body bytes arrived in the same reads and when the first
+// of them did, or None if the peer closed the connection before sending
+// anything.
+pub async fn read_head<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<(String, Vec<u8>, Instant)>> {
+    let mut buffer = Vec::with_capacity(4096);
+    let mut chunk = [0; 4096];
+    let mut started = None;
+
+    loop {
+        let bytes_read = reader.read(&mut chunk).await?;
+        if bytes_read == 0 {
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid-request"));
+        }
+        let started = *started.get_or_insert_with(Instant::now);
+        // Resume the search a few bytes back in case the terminator straddles reads.
+        let search_from = buffer.len().saturating_sub(3);
+        buffer.extend_from_slice(&chunk[..bytes_read]);
+
+        if let Some(end) = buffer[search_from..].windows(4).position(|window| window == b"\r\n\r\n") {
+            let end = search_from + end + 4;
+            let leftover = buffer.split_off(end);
+            check_head(&buffer).map_err(|problem| io::Error::new(io::ErrorKind::InvalidData, problem))?;
+            return Ok(Some((String::from_utf8_lossy(&buffer).to_string(), leftover, started)));
+        }
+        if buffer.len() > MAX_HEAD_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too large"));
+        }
+    }
+}
+
+// Refuses heads whose lines do not all end in CRLF, and header lines that are
+// not a name, a colon and a value. A bare CR or LF would otherwise split a
+// line differently here than in a proxy in front, and a name with spaces or
+// control characters in it is not one any handler looks for.
+fn check_head(head: &[u8]) -> Result<(), &'static str> {
+    let head = head.strip_suffix(b"\r\n\r\n").unwrap_or(head);
+    let pieces: Vec<&[u8]> = head.split(|&byte| byte == b'\n').collect();
+    for (i, piece) in pieces.iter().enumerate() {
+        let line = if i + 1 < pieces.len() { piece.strip_suffix(b"\r").ok_or("request head has a bare LF")? } else { piece };
+        if line.contains(&b'\r') {
+            return Err("request head has a bare CR");
+        }
+        // The first line is the request line.
+        if i > 0 {
+            match line.iter().position(|&byte| byte == b':') {
+                Some(colon) if colon > 0 && line[..colon].iter().all(|&byte| is_token_char(byte)) => {}
+                _ => return Err("malformed header line"),
+            }
+        }
+    }
+    Ok(())
+}
+
+// The characters of a header name (RFC 9110's `tchar`).
+pub fn is_token_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+// Value of the first header called `name` (case-insensitive), if present.
+pub fn extract_header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+// Marker error for bodies exceeding the configured maximum.
+#[derive(Debug)]
+pub struct BodyTooLarge;
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request body exceeds the configured maximum size")
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+pub fn is_body_too_large(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|inner| inner.is::<BodyTooLarge>())
+}
+
+enum Framing {
+    Length(u64),
+    // Bytes left in the current chunk; None means a size line comes next.
+    Chunked(Option<u64>),
+    Done,
+}
+
+// The request body, read incrementally with a hard cap on the total size.
+// The reader is passed to each call so the socket stays usable for writing
+// the response in between. Callers that write the body to disk must remove
+// the partial file when a read fails.
+pub struct Body {
+    buffered: Vec<u8>,
+    framing: Framing,
+    received: u64,
+    limit: u64,
+}
+
+impl Body {
+    // A declared Content-Length over the limit is refused before reading a
+    // single body byte.
+    pub fn new(content_length: Option<&str>, transfer_encoding: Option<&str>, leftover: Vec<u8>, limit: u64) -> Result<Self, io::Error> {
+        let framing = if transfer_encoding.is_some_and(|value| value.to_ascii_lowercase().contains("chunked")) {
+            Framing::Chunked(None)
+        } else if let Some(length) = content_length {
+            let length: u64 = length
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid Content-Length"))?;
+            if length > limit {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, BodyTooLarge));
+            }
+            if length == 0 { Framing::Done } else { Framing::Length(length) }
+        } else {
+            Framing::Done
+        };
+
+        Ok(Body { buffered: leftover, framing, received: 0, limit })
+    }
+
+    // Next piece of the body, or None once it has been read completely.
+    pub async fn next_chunk<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            match self.framing {
+                Framing::Done => return Ok(None),
+                Framing::Length(remaining) => {
+                    let data = self.take(reader, remaining).await?;
+                    let remaining = remaining - data.len() as u64;
+                    self.framing = if remaining == 0 { Framing::Done } else { Framing::Length(remaining) };
+                    return self.count(data).map(Some);
+                }
+                Framing::Chunked(Some(remaining)) => {
+                    let data = self.take(reader, remaining).await?;
+                    let remaining = remaining - data.len() as u64;
+                    if remaining == 0 {
+                        self.expect_crlf(reader).await?;
+                        self.framing = Framing::Chunked(None);
+                    } else {
+                        self.framing = Framing::Chunked(Some(remaining));
+                    }
+                    return self.count(data).map(Some);
+                }
+                Framing::Chunked(None) => {
+                    let line = self.read_line(reader).await?;
+                    let size = line.split(';').next().unwrap_or("").trim();
+                    let size = u64::from_str_radix(size, 16)
+                        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+                    if size == 0 {
+                        // Skip any trailers up to the final blank line.
+                        while !self.read_line(reader).await?.is_empty() {}
+                        self.framing = Framing::Done;
+                    } else {
+                        self.framing = Framing::Chunked(Some(size));
+                    }
+                }
+            }
+        }
+    }
+
+    // Reads and discards the rest of the body, still bounded by the limit.
+    pub async fn drain<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<()> {
+        while self.next_chunk(reader).await?.is_some() {}
+        Ok(())
+    }
+
+    fn count(&mut self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        self.received += data.len() as u64;
+        if self.received > self.limit {
+            self.framing = Framing::Done;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, BodyTooLarge));
+        }
+        Ok(data)
+    }
+
+    // Up to `max` bytes, served from the leftover buffer first.
+    async fn take<R: AsyncRead + Unpin>(&mut self, reader: &mut R, max: u64) -> io::Result<Vec<u8>> {
+        if self.buffered.is_empty() {
+            self.fill(reader).await?;
+        }
+        let len = self.buffered.len().min(max.min(usize::MAX as u64) as usize);
+        let rest = self.buffered.split_off(len);
+        Ok(std::mem::replace(&mut self.buffered, rest))
+    }
+
+    async fn fill<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<()> {
+        let mut chunk = [0; 16 * 1024];
+        let bytes_read = reader.read(&mut chunk).await?;
+        if bytes_read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid-body"));
+        }
+        self.buffered.extend_from_slice(&chunk[..bytes_read]);
+        Ok(())
+    }
+
+    async fn read_line<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<String> {
+        loop {
+            if let Some(end) = self.buffered.windows(2).position(|window| window == b"\r\n") {
+                let rest = self.buffered.split_off(end + 2);
+                let line = std::mem::replace(&mut self.buffered, rest);
+                return Ok(String::from_utf8_lossy(&line[..end]).to_string());
+            }
+            if self.buffered.len() > MAX_HEAD_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk header too long"));
+            }
+            self.fill(reader).await?;
+        }
+    }
+
+    async fn expect_crlf<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> io::Result<()> {
+        if !self.read_line(reader).await?.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "missing CRLF after chunk"));
+        }
+        Ok(())
+    }
+}
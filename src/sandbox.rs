@@ -0,0 +1,84 @@
+use std::io;
+use std::path::Path;
+
+// What the server changes below the root, which the sandbox has to leave it
+// able to do.
+#[derive(Clone, Copy)]
+pub enum Writes {
+    Nothing,
+    // `PUT /dir/`, with `allow_write`.
+    Directories,
+    // WebDAV writes as well, with `webdav` too.
+    Files,
+}
+
+// Confines the process to `root` before any request is served. Landlock is
+// preferred because it works unprivileged; chroot is the fallback when we are
+// root on a kernel without Landlock. Root can leave a chroot at will, so the
+// fallback is only taken when `leaves_root` says privileges are dropped right
+// after. Landlock rules only bind the calling thread and the threads it
+// spawns afterwards, so this must run before the tokio runtime is built.
+#[cfg(target_os = "linux")]
+pub fn enter(root: &Path, writes: Writes, leaves_root: bool) -> io::Result<&'static str> {
+    if landlock(root, writes)? {
+        return Ok("landlock");
+    }
+
+    if unsafe { libc::geteuid() } == 0 {
+        if !leaves_root {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Landlock is not available, and a chroot does not confine a process that stays root; pass --user as well",
+            ));
+        }
+        std::os::unix::fs::chroot(root)?;
+        std::env::set_current_dir("/")?;
+        return Ok("chroot");
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Landlock is not available and chroot requires running as root",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enter(_root: &Path, _writes: Writes, _leaves_root: bool) -> io::Result<&'static str> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--sandbox is only supported on Linux"))
+}
+
+// Returns false when the kernel does not enforce Landlock at all.
+#[cfg(target_os = "linux")]
+fn landlock(root: &Path, writes: Writes) -> io::Result<bool> {
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus, ABI,
+    };
+
+    let abi = ABI::V5;
+    // Read access everywhere below the root, plus what `writes` needs. WebDAV
+    // uploads into a hidden file renamed over the target, and copies trees
+    // with their symbolic links.
+    let allowed = match writes {
+        Writes::Nothing => AccessFs::from_read(abi),
+        Writes::Directories => AccessFs::from_read(abi) | AccessFs::MakeDir,
+        Writes::Files => {
+            AccessFs::from_read(abi)
+                | AccessFs::MakeDir
+                | AccessFs::WriteFile
+                | AccessFs::Truncate
+                | AccessFs::MakeReg
+                | AccessFs::MakeSym
+                | AccessFs::RemoveFile
+                | AccessFs::RemoveDir
+                | AccessFs::Refer
+        }
+    };
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules([root], allowed)))
+        .and_then(|ruleset| ruleset.restrict_self())
+        .map_err(io::Error::other)?;
+
+    Ok(!matches!(status.ruleset, RulesetStatus::NotEnforced))
+}
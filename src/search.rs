@@ -0,0 +1,198 @@
+// Recursive name search under a directory, for `/search`, on top of the walk
+// that content searches and trees share. Directories are read breadth first
+// by their URL paths, resolved the way a request for them would be, so
+// aliases apply and nothing outside what the server serves is reached.
+// Directory settings apply as they do to listings: dotfiles only show where
+// hidden files are shown (and hidden directories are then walked too), and
+// files of a type the directory does not serve are left out.
+use crate::config::Site;
+use crate::directory_config::{self, DirectoryConfig};
+use crate::glob::Glob;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::fs;
+
+pub enum Pattern {
+    // Lower case.
+    Substring(String),
+    Glob(Glob),
+}
+
+impl Pattern {
+    // A query with any of `*`, `?` or `[` in it is a glob matching whole
+    // names; anything else is looked for inside names. Both ignore case.
+    pub fn parse(query: &str) -> Result<Self, String> {
+        if query.contains(['*', '?', '[']) {
+            Glob::parse(query, true).map(Pattern::Glob)
+        } else {
+            Ok(Pattern::Substring(query.to_lowercase()))
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Substring(part) => name.to_lowercase().contains(part.as_str()),
+            Pattern::Glob(glob) => glob.matches(name),
+        }
+    }
+}
+
+// The tree under a directory, one entry at a time. Symbolic links to
+// directories are only entered with `follow_links`, and then every directory
+// is read once, by its canonical path, so that link cycles come to an end.
+pub struct Walk {
+    // `?hidden=`, which overrides each directory's `show_hidden`.
+    show_hidden: Option<bool>,
+    max_depth: usize,
+    follow_links: bool,
+    visited: HashSet<PathBuf>,
+    // URL paths of directories still to read, with their depth below the
+    // start.
+    pending: VecDeque<(PathBuf, usize)>,
+    reading: Option<Reading>,
+}
+
+struct Reading {
+    entries: fs::ReadDir,
+    url_dir: PathBuf,
+    depth: usize,
+    settings: Arc<DirectoryConfig>,
+}
+
+pub enum Step {
+    Entry(Entry),
+    // A directory was read to its end. Those that could not be read, or not
+    // to the end, never are.
+    Finished(PathBuf),
+}
+
+pub struct Entry {
+    pub url_path: PathBuf,
+    pub name: String,
+    // As read from its directory.
+    pub path: PathBuf,
+    // Of the entry itself, so a symbolic link is one whatever it points to.
+    pub file_type: std::fs::FileType,
+    // Of the directory the entry is in.
+    pub settings: Arc<DirectoryConfig>,
+}
+
+impl Walk {
+    // Directories are read down to `max_depth` levels below `start`, which
+    // is at 0.
+    pub fn new(start: PathBuf, show_hidden: Option<bool>, max_depth: usize, follow_links: bool) -> Self {
+        Walk {
+            show_hidden,
+            max_depth,
+            follow_links,
+            visited: HashSet::new(),
+            pending: VecDeque::from([(start, 0)]),
+            reading: None,
+        }
+    }
+
+    // None once every directory was read.
+    pub async fn next(&mut self, site: &Site<'_>) -> Option<Step> {
+        loop {
+            let Some(reading) = &mut self.reading else {
+                let (url_dir, depth) = self.pending.pop_front()?;
+                let path = site.resolve(&url_dir);
+                if self.follow_links && !fs::canonicalize(&path).await.is_ok_and(|canonical| self.visited.insert(canonical)) {
+                    continue;
+                }
+                if let Ok(entries) = fs::read_dir(path).await {
+                    let settings = Arc::new(DirectoryConfig::for_path(site.config, site, &url_dir).await);
+                    self.reading = Some(Reading { entries, url_dir, depth, settings });
+                }
+                continue;
+            };
+            let entry = match reading.entries.next_entry().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => return self.reading.take().map(|reading| Step::Finished(reading.url_dir)),
+                Err(_) => {
+                    self.reading = None;
+                    continue;
+                }
+            };
+            let file_name = entry.file_name();
+            let name = file_name.to_string_lossy().to_string();
+            let shown = self.show_hidden.unwrap_or(reading.settings.show_hidden) || !name.starts_with('.');
+            if name == directory_config::FILE_NAME || !shown {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type().await else { continue };
+            let url_path = reading.url_dir.join(&file_name);
+            if reading.depth < self.max_depth {
+                let is_dir = file_type.is_dir()
+                    || self.follow_links && file_type.is_symlink() && fs::metadata(entry.path()).await.is_ok_and(|metadata| metadata.is_dir());
+                if is_dir {
+                    self.pending.push_back((url_path.clone(), reading.depth + 1));
+                }
+            }
+            return Some(Step::Entry(Entry {
+                url_path,
+                name,
+                path: entry.path(),
+                file_type,
+                settings: reading.settings.clone(),
+            }));
+        }
+    }
+}
+
+pub struct Found {
+    // Of the match, from the root of the site.
+    pub url_path: PathBuf,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    // Of what a link points to; None for directories and when it cannot be
+    // read.
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+}
+
+// Symbolic links to directories are listed but never entered.
+pub struct Search {
+    walk: Walk,
+    pattern: Pattern,
+    max_results: usize,
+    found: usize,
+    // Set when a match past `max_results` was found and left out.
+    pub limited: bool,
+}
+
+impl Search {
+    pub fn new(start: PathBuf, pattern: Pattern, show_hidden: Option<bool>, max_depth: usize, max_results: usize) -> Self {
+        Search { walk: Walk::new(start, show_hidden, max_depth, false), pattern, max_results, found: 0, limited: false }
+    }
+
+    // The next match, or None when the tree is exhausted or enough were
+    // found. Directories that cannot be read are skipped.
+    pub async fn next(&mut self, site: &Site<'_>) -> Option<Found> {
+        loop {
+            let Step::Entry(entry) = self.walk.next(site).await? else { continue };
+            if !self.pattern.matches(&entry.name) {
+                continue;
+            }
+            let metadata = fs::metadata(&entry.path).await.ok();
+            let is_dir = metadata.as_ref().map_or(entry.file_type.is_dir(), |metadata| metadata.is_dir());
+            if !is_dir && !entry.settings.serves_extension(&entry.name) {
+                continue;
+            }
+            if self.found == self.max_results {
+                self.limited = true;
+                return None;
+            }
+            self.found += 1;
+            return Some(Found {
+                url_path: entry.url_path,
+                is_dir,
+                is_symlink: entry.file_type.is_symlink(),
+                size: metadata.as_ref().filter(|metadata| !metadata.is_dir()).map(|metadata| metadata.len()),
+                modified: metadata.and_then(|metadata| metadata.modified().ok()),
+            });
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Raw file downloads on Linux go from the page cache to the socket with
+// sendfile(2), rather than being read into a buffer and written from it.
+// The call is made when the socket is writable, so it does not wait for the
+// client; reading the file can still wait for the disk, as with mmap, which
+// for files that are read often (and so cached) it rarely does.
+use crate::listener::{Connection, Socket};
+use std::io;
+
+// Sends `len` bytes of `file` starting at `offset`, leaving the file's
+// position alone. Ok(false) when sendfile(2) is not available here, in which
+// case nothing was sent and the caller copies the bytes itself.
+#[cfg(target_os = "linux")]
+pub async fn send<S: Connection>(socket: &S, file: &tokio::fs::File, offset: u64, len: u64) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+
+    // Linux sends at most this much in one call.
+    const MAX_COUNT: u64 = 0x7fff_f000;
+    let Ok(mut position) = libc::off_t::try_from(offset) else {
+        return Ok(false);
+    };
+    let mut remaining = len;
+    while remaining > 0 {
+        let count = remaining.min(MAX_COUNT) as usize;
+        let mut send = |out_fd: i32| {
+            match unsafe { libc::sendfile(out_fd, file.as_raw_fd(), &mut position, count) } {
+                -1 => Err(io::Error::last_os_error()),
+                sent => Ok(sent as u64),
+            }
+        };
+        let sent = match socket.socket() {
+            Socket::Tcp(stream) => {
+                stream.writable().await?;
+                stream.try_io(Interest::WRITABLE, || send(stream.as_raw_fd()))
+            }
+            Socket::Unix(stream) => {
+                stream.writable().await?;
+                stream.try_io(Interest::WRITABLE, || send(stream.as_raw_fd()))
+            }
+        };
+        match sent {
+            // The file is shorter than it was when its length was sent.
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file shrank while being sent")),
+            Ok(sent) => remaining -= sent,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::Interrupted => {}
+            // Some filesystems cannot be read this way; only known before
+            // the first byte is sent.
+            Err(e) if remaining == len && matches!(e.raw_os_error(), Some(libc::EINVAL | libc::ENOSYS)) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn send<S: Connection>(_socket: &S, _file: &tokio::fs::File, _offset: u64, _len: u64) -> io::Result<bool> {
+    Ok(false)
+}
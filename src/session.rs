@@ -0,0 +1,65 @@
+// Signed session cookies for the `/_login` form, so that a browser signed in
+// once need not send a password with every request. A token names its user
+// and when it expires, carries a random nonce, and is signed with HMAC-SHA256
+// under a secret drawn at startup; nothing is stored per session, and a
+// restart signs everyone out.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const COOKIE_NAME: &str = "gredl_session";
+
+// How long a session lasts, whether or not it is used.
+pub const LIFETIME: Duration = Duration::from_secs(12 * 60 * 60);
+
+pub struct Sessions {
+    // A block of SHA-256, the longest key HMAC uses as it is.
+    secret: [u8; 64],
+}
+
+impl Sessions {
+    pub fn new() -> io::Result<Self> {
+        let mut secret = [0; 64];
+        getrandom::fill(&mut secret).map_err(io::Error::other)?;
+        Ok(Sessions { secret })
+    }
+
+    // A token for `name`, valid for LIFETIME: the user name, the expiry in
+    // Unix seconds and the nonce, then the signature of all three, joined by
+    // dots.
+    pub fn issue(&self, name: &str) -> io::Result<String> {
+        let mut nonce = [0; 16];
+        getrandom::fill(&mut nonce).map_err(io::Error::other)?;
+        let expires = (SystemTime::now() + LIFETIME).duration_since(UNIX_EPOCH).map_or(0, |expires| expires.as_secs());
+        let payload = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(name), expires, URL_SAFE_NO_PAD.encode(nonce));
+        let signature = self.mac(&payload).finalize().into_bytes();
+        Ok(format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    // The user a token was issued to, if this server signed it and it has not
+    // expired.
+    pub fn verify(&self, token: &str) -> Option<String> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+        let mut fields = payload.split('.');
+        let name = String::from_utf8(URL_SAFE_NO_PAD.decode(fields.next()?).ok()?).ok()?;
+        let expires: u64 = fields.next()?.parse().ok()?;
+        UNIX_EPOCH.checked_add(Duration::from_secs(expires)).is_some_and(|expires| expires > SystemTime::now()).then_some(name)
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as KeyInit>::new(&self.secret.into());
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+// The session token among the cookies of a `Cookie` header.
+pub fn token(cookies: Option<&str>) -> Option<&str> {
+    cookies?.split(';').find_map(|cookie| cookie.trim().strip_prefix(COOKIE_NAME)?.strip_prefix('='))
+}
@@ -0,0 +1,112 @@
+// systemd socket activation and readiness notification. Both are driven by
+// environment variables systemd sets; without them everything here is a
+// no-op and the server binds its own sockets.
+use crate::listener::Listener;
+use std::io;
+
+// Listeners passed in by systemd (`LISTEN_FDS`, starting at fd 3), or None
+// when the process was not socket-activated. The variables are removed so
+// that nothing spawned later mistakes the descriptors for its own.
+#[cfg(unix)]
+pub fn activated_listeners() -> io::Result<Option<Vec<Listener>>> {
+    use std::os::unix::io::FromRawFd;
+
+    const FIRST_FD: i32 = 3;
+
+    let pid = std::env::var("LISTEN_PID").ok().and_then(|pid| pid.parse::<u32>().ok());
+    if pid != Some(std::process::id()) {
+        return Ok(None);
+    }
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<i32>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "LISTEN_FDS is not a number"))?;
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let mut listeners = Vec::new();
+    for fd in FIRST_FD..FIRST_FD + count {
+        // SAFETY: systemd hands these descriptors to us and nothing else in
+        // the process has taken ownership of them.
+        let family = unsafe {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            let mut addr: libc::sockaddr_storage = std::mem::zeroed();
+            let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            if libc::getsockname(fd, &mut addr as *mut _ as *mut libc::sockaddr, &mut len) != 0 {
+                return Err(io::Error::other(format!("fd {} from systemd: {}", fd, io::Error::last_os_error())));
+            }
+            addr.ss_family as i32
+        };
+        let listener = match family {
+            libc::AF_INET | libc::AF_INET6 => Listener::Tcp(unsafe { std::net::TcpListener::from_raw_fd(fd) }),
+            libc::AF_UNIX => {
+                let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+                let path = listener
+                    .local_addr()?
+                    .as_pathname()
+                    .map(|path| path.to_path_buf())
+                    .unwrap_or_else(|| format!("systemd-fd-{}", fd).into());
+                Listener::Unix(listener, path)
+            }
+            _ => return Err(io::Error::other(format!("fd {} from systemd is not a TCP or unix socket", fd))),
+        };
+        match &listener {
+            Listener::Tcp(listener) => listener.set_nonblocking(true)?,
+            Listener::Unix(listener, _) => listener.set_nonblocking(true)?,
+        }
+        listeners.push(listener);
+    }
+    Ok(Some(listeners))
+}
+
+#[cfg(not(unix))]
+pub fn activated_listeners() -> io::Result<Option<Vec<Listener>>> {
+    Ok(None)
+}
+
+// Connection to the service manager's notification socket, opened before the
+// sandbox is entered since the socket usually lives outside the document root.
+pub struct Notifier {
+    #[cfg(unix)]
+    socket: Option<std::os::unix::net::UnixDatagram>,
+}
+
+impl Notifier {
+    #[cfg(unix)]
+    pub fn from_env() -> io::Result<Self> {
+        use std::os::unix::net::UnixDatagram;
+
+        let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+            return Ok(Notifier { socket: None });
+        };
+        std::env::remove_var("NOTIFY_SOCKET");
+        let socket = UnixDatagram::unbound()?;
+        let path = path.to_string_lossy().to_string();
+        match path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+                socket.connect_addr(&addr)?;
+            }
+            _ => socket.connect(&path)?,
+        }
+        Ok(Notifier { socket: Some(socket) })
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_env() -> io::Result<Self> {
+        Ok(Notifier {})
+    }
+
+    // Tells a `Type=notify` unit that the server is accepting connections.
+    pub fn ready(&self) {
+        #[cfg(unix)]
+        if let Some(socket) = &self.socket {
+            if let Err(e) = socket.send(b"READY=1") {
+                tracing::error!("Failed to notify systemd: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// OpenTelemetry traces. Every request is a span, with child spans for the
+// file I/O it does, and with `otel_endpoint` set they are exported over
+// OTLP/gRPC to a collector, Jaeger or Tempo. A request carrying W3C trace
+// context (`traceparent`, and `tracestate` with it) continues the client's
+// trace instead of starting a new one.
+use crate::log;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// Has to run inside the Tokio runtime, which the exporter's gRPC client
+// lives on. The provider that is returned flushes what is left when it is
+// shut down.
+pub fn start(endpoint: &str) -> Result<SdkTracerProvider, String> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| format!("OTLP exporter for {}: {}", endpoint, e))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("gredl_server").build())
+        .build();
+    log::export_spans(provider.tracer("gredl_server"));
+    Ok(provider)
+}
+
+// Header lookups in a request head, for the propagator.
+struct Head<'a>(&'a str);
+
+impl Extractor for Head<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        crate::request::extract_header(self.0, key)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':').map(|(name, _)| name.trim()))
+            .collect()
+    }
+}
+
+// The root span of a request. The status is recorded once it is known.
+pub fn request_span(request: &str) -> tracing::Span {
+    let method = crate::extract_method(request);
+    let target = crate::extract_target(request);
+    let span = tracing::info_span!(
+        "request",
+        otel.name = method,
+        otel.kind = "server",
+        http.request.method = method,
+        url.path = target.split('?').next().unwrap_or(target),
+        url.query = target.split_once('?').map(|(_, query)| query).unwrap_or(""),
+        http.response.status_code = tracing::field::Empty,
+    );
+    // A missing or malformed traceparent gives an empty context, which
+    // leaves the span a root.
+    let _ = span.set_parent(TraceContextPropagator::new().extract(&Head(request)));
+    span
+}
+
+pub fn record_status(status: u16) {
+    tracing::Span::current().record("http.response.status_code", status);
+}
@@ -0,0 +1,63 @@
+// How listings and file info pages show dates: in which time zone and with
+// which strftime format. Both are checked when the configuration is loaded,
+// so rendering never fails. The zone follows every timestamp, since readers
+// are often somewhere else than the server.
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use std::time::SystemTime;
+
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone)]
+pub struct Timestamps {
+    zone: Zone,
+    format: String,
+}
+
+#[derive(Clone, Copy)]
+enum Zone {
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+impl Default for Timestamps {
+    fn default() -> Self {
+        Timestamps { zone: Zone::Local, format: DEFAULT_FORMAT.to_string() }
+    }
+}
+
+impl Timestamps {
+    // `timezone` is "UTC", "local" or an IANA name such as "Europe/Berlin".
+    pub fn new(timezone: &str, format: &str) -> Result<Self, String> {
+        let zone = match timezone {
+            "UTC" | "utc" => Zone::Utc,
+            "local" => Zone::Local,
+            name => Zone::Named(name.parse().map_err(|_| {
+                format!("timezone: `{}` is not \"UTC\", \"local\" or an IANA time zone name like \"Europe/Berlin\"", name)
+            })?),
+        };
+        if format.trim().is_empty() {
+            return Err("date_format must not be empty".to_string());
+        }
+        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("date_format: `{}` is not a valid strftime format", format));
+        }
+        Ok(Timestamps { zone, format: format.to_string() })
+    }
+
+    // The local zone has no name to give, so its offset stands in for one.
+    pub fn format(&self, time: SystemTime) -> String {
+        match self.zone {
+            Zone::Utc => format!("{} UTC", DateTime::<Utc>::from(time).format(&self.format)),
+            Zone::Local => {
+                let time = DateTime::<Local>::from(time);
+                format!("{} {}", time.format(&self.format), time.format("UTC%:z"))
+            }
+            Zone::Named(zone) => {
+                format!("{} {}", DateTime::<Utc>::from(time).with_timezone(&zone).format(&self.format), zone.name())
+            }
+        }
+    }
+}
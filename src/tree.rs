@@ -0,0 +1,83 @@
+// The tree under a directory as nested JSON, for `/api/v1/tree`. It is built
+// from the walk name searches use, with the same entries left out, except
+// that symbolic links to directories are followed; the walk reads each
+// directory once, so a link back up the tree ends there. The whole tree is
+// held until it is sent, so its size is capped.
+use crate::api::{self, EntryType, TreeNode};
+use crate::config::Site;
+use crate::search::{Step, Walk};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::fs;
+
+struct Node {
+    name: String,
+    is_dir: bool,
+    metadata: Option<std::fs::Metadata>,
+    // Indexes into the list of nodes.
+    children: Vec<usize>,
+    // Whether the directory was read to its end.
+    complete: bool,
+}
+
+// `depth` levels of directories, the first being `start`, and at most
+// `max_nodes` nodes below it.
+pub async fn build(site: &Site<'_>, start: PathBuf, show_hidden: Option<bool>, depth: usize, max_nodes: usize) -> api::Tree {
+    let root_name = match start.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => "/".to_string(),
+    };
+    let metadata = fs::metadata(site.resolve(&start)).await.ok();
+    let mut nodes = vec![Node { name: root_name, is_dir: true, metadata, children: Vec::new(), complete: false }];
+    // URL paths of the directories among them.
+    let mut dirs = HashMap::from([(start.clone(), 0)]);
+    let mut walk = Walk::new(start.clone(), show_hidden, depth.saturating_sub(1), true);
+    while let Some(step) = walk.next(site).await {
+        let entry = match step {
+            Step::Entry(entry) => entry,
+            Step::Finished(url_dir) => {
+                if let Some(&index) = dirs.get(&url_dir) {
+                    nodes[index].complete = true;
+                }
+                continue;
+            }
+        };
+        let metadata = fs::metadata(&entry.path).await.ok();
+        let is_dir = metadata.as_ref().map_or(entry.file_type.is_dir(), |metadata| metadata.is_dir());
+        if !is_dir && !entry.settings.serves_extension(&entry.name) {
+            continue;
+        }
+        let Some(&parent) = entry.url_path.parent().and_then(|parent| dirs.get(parent)) else {
+            continue;
+        };
+        // Stopping here leaves this directory, and any not yet read,
+        // incomplete.
+        if nodes.len() > max_nodes {
+            break;
+        }
+        let index = nodes.len();
+        nodes.push(Node { name: entry.name, is_dir, metadata, children: Vec::new(), complete: false });
+        nodes[parent].children.push(index);
+        if is_dir {
+            dirs.insert(entry.url_path, index);
+        }
+    }
+    api::Tree { path: crate::directory_link(&start), depth, root: tree_node(&nodes, 0) }
+}
+
+fn tree_node(nodes: &[Node], index: usize) -> TreeNode {
+    let node = &nodes[index];
+    let children = node.is_dir.then(|| {
+        let mut children: Vec<TreeNode> = node.children.iter().map(|&child| tree_node(nodes, child)).collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        children
+    });
+    TreeNode {
+        name: node.name.clone(),
+        kind: if node.is_dir { EntryType::Directory } else { EntryType::File },
+        size: node.metadata.as_ref().filter(|metadata| !metadata.is_dir()).map(|metadata| metadata.len()),
+        mtime: node.metadata.as_ref().and_then(|metadata| metadata.modified().ok()).map(api::timestamp),
+        truncated: node.is_dir.then_some(!node.complete),
+        children,
+    }
+}
@@ -0,0 +1,98 @@
+// Turning the path of a request target into the path it names, and the
+// bytes of file names to and from what goes in URLs. Kept free of the rest of
+// the server so that the fuzz target can build it on its own; see
+// fuzz/README.md.
+use percent_encoding::percent_decode_str;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+// The path of a request target, percent-decoded and normalized: the query is
+// dropped, and a decoded `%2F` separates segments like a literal `/`.
+pub fn extract_path(target: &str) -> PathBuf {
+    let path = target.split_once('?').map(|(path, _)| path).unwrap_or(target);
+
+    let decoded_path = os_string(percent_decode_str(path.strip_prefix('/').unwrap_or(path)).collect());
+    normalize_path(Path::new(&decoded_path))
+}
+
+// An already decoded URL path, made absolute and free of `.` and `..`.
+pub fn normalize_path(decoded_path: &Path) -> PathBuf {
+    // Resolve `.` and `..` lexically so the result can never climb above "/",
+    // and therefore never above the document root it is later joined to.
+    let mut normalized = PathBuf::from("/");
+    for component in decoded_path.components() {
+        match component {
+            std::path::Component::Normal(part) => normalized.push(part),
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => {}
+        }
+    }
+    normalized
+}
+
+// File names are arbitrary bytes on unix and are kept that way from request
+// to disk and back. Elsewhere they are Unicode, and bytes that are not UTF-8
+// cannot name anything anyway.
+#[cfg(unix)]
+pub fn os_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    Cow::Borrowed(std::os::unix::ffi::OsStrExt::as_bytes(name))
+}
+
+#[cfg(not(unix))]
+pub fn os_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    Cow::Owned(name.to_string_lossy().into_owned().into_bytes())
+}
+
+#[cfg(unix)]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    std::os::unix::ffi::OsStringExt::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+pub fn os_string(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_path;
+    use std::path::PathBuf;
+
+    #[test]
+    fn missing_path_is_the_root() {
+        assert_eq!(extract_path(""), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn query_string_is_stripped() {
+        assert_eq!(extract_path("/docs/?sort=size&order=desc"), PathBuf::from("/docs"));
+        assert_eq!(extract_path("/a.txt?raw=1?again"), PathBuf::from("/a.txt"));
+        assert_eq!(extract_path("?raw=1"), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn repeated_slashes_collapse() {
+        assert_eq!(extract_path("//docs///notes.txt"), PathBuf::from("/docs/notes.txt"));
+        assert_eq!(extract_path("/docs//"), PathBuf::from("/docs"));
+    }
+
+    #[test]
+    fn percent_encoded_characters_are_decoded() {
+        assert_eq!(extract_path("/read%20me.txt"), PathBuf::from("/read me.txt"));
+        assert_eq!(extract_path("/caf%C3%A9"), PathBuf::from("/café"));
+        // A decoded `%2F` separates segments like a literal slash.
+        assert_eq!(extract_path("/a%2Fb"), PathBuf::from("/a/b"));
+        assert_eq!(extract_path("/100%25"), PathBuf::from("/100%"));
+    }
+
+    #[test]
+    fn dot_segments_never_climb_above_the_root() {
+        assert_eq!(extract_path("/a/./b/../c"), PathBuf::from("/a/c"));
+        assert_eq!(extract_path("/../../etc/passwd"), PathBuf::from("/etc/passwd"));
+        assert_eq!(extract_path("/%2e%2e/%2E%2E/etc"), PathBuf::from("/etc"));
+        assert_eq!(extract_path("/a/..%2F..%2Fsecret"), PathBuf::from("/secret"));
+    }
+}
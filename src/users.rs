@@ -0,0 +1,68 @@
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+// One account of the users file: a bcrypt hash of its password, and the URL
+// paths it may reach, each with everything below it.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct User {
+    password: String,
+    roots: Vec<PathBuf>,
+}
+
+// The accounts of `users_file`, keyed by user name. Like the audit log the
+// file is read at startup, before a sandbox would put it out of reach, and
+// it is read again on reload if it can still be.
+pub struct Users {
+    users: BTreeMap<String, User>,
+}
+
+impl Users {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let mut users: BTreeMap<String, User> = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (name, user) in &mut users {
+            // Basic credentials are `name:password`, so the name ends at the
+            // first colon.
+            if name.is_empty() || name.contains(':') {
+                return Err(format!("{}: `{}` cannot be used as a user name", path.display(), name));
+            }
+            if user.password.parse::<bcrypt::HashParts>().is_err() {
+                return Err(format!("{}: the password of {} is not a bcrypt hash", path.display(), name));
+            }
+            for root in &mut user.roots {
+                if !root.has_root() || root.components().any(|component| component == Component::ParentDir) {
+                    return Err(format!("{}: root `{}` of {} must be a URL path such as /photos", path.display(), root.display(), name));
+                }
+                *root = root.components().collect();
+            }
+        }
+        Ok(Users { users })
+    }
+
+    // Whether `password` is that of the account `name`. bcrypt is slow on
+    // purpose, so this blocks for a noticeable time.
+    pub fn verify(&self, name: &str, password: &str) -> bool {
+        self.users.get(name).is_some_and(|user| bcrypt::verify(password, &user.password).unwrap_or(false))
+    }
+
+    // Whether `name` may reach `url_path`, a normalized path as
+    // `extract_path` gives it.
+    pub fn may_access(&self, name: &str, url_path: &Path) -> bool {
+        self.users.get(name).is_some_and(|user| user.roots.iter().any(|root| url_path.starts_with(root)))
+    }
+}
+
+// The user name and password of a Basic `Authorization` header.
+pub fn credentials(authorization: Option<&str>) -> Option<(String, String)> {
+    let (scheme, credentials) = authorization?.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Basic") {
+        return None;
+    }
+    let credentials = base64::engine::general_purpose::STANDARD.decode(credentials.trim()).ok()?;
+    let credentials = String::from_utf8(credentials).ok()?;
+    let (name, password) = credentials.split_once(':')?;
+    Some((name.to_string(), password.to_string()))
+}
@@ -0,0 +1,258 @@
+use base64::Engine;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::io;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+// Appended to the client's key before hashing, per RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// Client frames are only read to notice pings and the close handshake, so
+// anything bigger than this is treated as a protocol error.
+const MAX_CLIENT_FRAME: usize = 64 * 1024;
+
+// An SSE comment is sent this often so that a client that went away is noticed
+// (by the failing write) even when the directory is quiet.
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+// `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(hasher.digest().bytes())
+}
+
+// Streams change events for the entries of `dir` to a client that has already
+// received the 101 response, until it closes the connection. The watcher
+// lives only as long as this call, so a departed client stops the watch. On
+// shutdown the server closes the stream itself.
+pub async fn stream_events<S: AsyncRead + AsyncWrite + Unpin>(socket: &mut S, dir: &Path, shutdown: &CancellationToken) -> io::Result<()> {
+    let (_watcher, mut events) = watch(dir)?;
+
+    let mut received = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                // 1001: going away.
+                return write_frame(socket, OPCODE_CLOSE, &1001u16.to_be_bytes()).await;
+            }
+            event = events.recv() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                for message in describe(&event) {
+                    write_frame(socket, OPCODE_TEXT, message.as_bytes()).await?;
+                }
+            }
+            bytes_read = socket.read(&mut chunk) => {
+                let bytes_read = bytes_read?;
+                if bytes_read == 0 {
+                    return Ok(());
+                }
+                received.extend_from_slice(&chunk[..bytes_read]);
+                while let Some((opcode, payload)) = take_frame(&mut received)? {
+                    match opcode {
+                        OPCODE_CLOSE => {
+                            write_frame(socket, OPCODE_CLOSE, &payload).await?;
+                            return Ok(());
+                        }
+                        OPCODE_PING => write_frame(socket, OPCODE_PONG, &payload).await?,
+                        // Data from the client means nothing here.
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Streams the same events as Server-Sent Events to a client that has already
+// received the response head. The client is only heard from through failing
+// writes, at which point the watcher is dropped, or ends with shutdown.
+pub async fn stream_server_sent_events<W: AsyncWrite + Unpin>(socket: &mut W, dir: &Path, shutdown: &CancellationToken) -> io::Result<()> {
+    let (_watcher, mut events) = watch(dir)?;
+
+    let mut keepalive = tokio::time::interval(SSE_KEEPALIVE);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            event = events.recv() => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                for message in describe(&event) {
+                    socket.write_all(format!("data: {}\n\n", message).as_bytes()).await?;
+                }
+            }
+            _ = keepalive.tick() => socket.write_all(b": keepalive\n\n").await?,
+        }
+        socket.flush().await?;
+    }
+}
+
+// A watcher on the entries of `dir` and the channel its events arrive on. The
+// watch ends when the watcher is dropped.
+fn watch(dir: &Path) -> io::Result<(notify::RecommendedWatcher, mpsc::UnboundedReceiver<notify::Event>)> {
+    let (sender, events) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = sender.send(event);
+        }
+    })
+    .map_err(io::Error::other)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive).map_err(io::Error::other)?;
+    Ok((watcher, events))
+}
+
+// One JSON message per affected entry. Renames are reported as the old name
+// being deleted and the new one created. Backends that report both halves of
+// a rename separately also send a combined event, which is skipped; those that
+// cannot tell the halves apart are resolved by checking which name exists.
+fn describe(event: &notify::Event) -> Vec<String> {
+    use notify::event::{ModifyKind, RenameMode};
+
+    let kinds: Vec<&str> = match event.kind {
+        EventKind::Create(_) => vec!["created"],
+        EventKind::Remove(_) => vec!["deleted"],
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => vec!["deleted"],
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => vec!["created"],
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => return Vec::new(),
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .iter()
+            .map(|path| if path.exists() { "created" } else { "deleted" })
+            .collect(),
+        EventKind::Modify(_) => vec!["modified"],
+        _ => return Vec::new(),
+    };
+
+    let mut messages = Vec::new();
+    for (index, path) in event.paths.iter().enumerate() {
+        let kind = kinds.get(index).or(kinds.last()).copied().unwrap_or("modified");
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        messages.push(serde_json::json!({ "event": kind, "name": name.to_string_lossy() }).to_string());
+    }
+    messages
+}
+
+// Server frames are never masked and never fragmented.
+async fn write_frame<W: AsyncWrite + Unpin>(socket: &mut W, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    socket.write_all(&frame).await
+}
+
+// Removes the first complete client frame from `buffer` and returns its opcode
+// and unmasked payload, or None if more bytes are needed.
+fn take_frame(buffer: &mut Vec<u8>) -> io::Result<Option<(u8, Vec<u8>)>> {
+    if buffer.len() < 2 {
+        return Ok(None);
+    }
+    let opcode = buffer[0] & 0x0F;
+    let masked = buffer[1] & 0x80 != 0;
+    let (len, mut offset) = match buffer[1] & 0x7F {
+        126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as u64, 4),
+        127 if buffer.len() >= 10 => (u64::from_be_bytes(buffer[2..10].try_into().unwrap()), 10),
+        126 | 127 => return Ok(None),
+        len => (len as u64, 2),
+    };
+    if !masked || len > MAX_CLIENT_FRAME as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid WebSocket frame"));
+    }
+    let len = len as usize;
+    if buffer.len() < offset + 4 + len {
+        return Ok(None);
+    }
+
+    let mask = [buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]];
+    offset += 4;
+    let payload = buffer[offset..offset + len]
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ mask[index % 4])
+        .collect();
+    buffer.drain(..offset + len);
+    Ok(Some((opcode, payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accept_key, take_frame, OPCODE_CLOSE, OPCODE_PING, OPCODE_TEXT};
+
+    // A client frame of `payload`, masked as clients must.
+    fn masked(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+        frame
+    }
+
+    #[test]
+    fn accept_key_is_the_one_from_rfc_6455() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(accept_key(" dGhlIHNhbXBsZSBub25jZQ== "), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn frames_are_unmasked_and_taken_one_at_a_time() {
+        let mut buffer = masked(OPCODE_PING, b"Hello");
+        buffer.extend(masked(OPCODE_CLOSE, &1000u16.to_be_bytes()));
+        assert_eq!(take_frame(&mut buffer).unwrap(), Some((OPCODE_PING, b"Hello".to_vec())));
+        assert_eq!(take_frame(&mut buffer).unwrap(), Some((OPCODE_CLOSE, vec![0x03, 0xe8])));
+        assert_eq!(take_frame(&mut buffer).unwrap(), None);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn partial_frames_wait_for_the_rest() {
+        let payload = vec![b'x'; 300];
+        let frame = masked(OPCODE_TEXT, &payload);
+        for cut in [1, 3, 7, frame.len() - 1] {
+            let mut buffer = frame[..cut].to_vec();
+            assert_eq!(take_frame(&mut buffer).unwrap(), None, "cut at {}", cut);
+            assert_eq!(buffer.len(), cut);
+        }
+        let mut buffer = frame;
+        assert_eq!(take_frame(&mut buffer).unwrap(), Some((OPCODE_TEXT, payload)));
+    }
+
+    #[test]
+    fn unmasked_and_oversized_frames_are_refused() {
+        let mut unmasked = vec![0x80 | OPCODE_TEXT, 2, b'h', b'i'];
+        assert!(take_frame(&mut unmasked).is_err());
+        let mut oversized = vec![0x80 | OPCODE_TEXT, 0x80 | 127];
+        oversized.extend_from_slice(&(1u64 << 20).to_be_bytes());
+        assert!(take_frame(&mut oversized).is_err());
+    }
+}
@@ -0,0 +1,427 @@
+// WebDAV (RFC 4918, class 1) under DAV_PATH, with `webdav`, so that file
+// managers and davfs2 can mount the site as a network drive. PROPFIND
+// describes a file, or a directory and its entries, which leave out what
+// listings leave out; GET sends files as `?raw=1` does. PUT, MKCOL, DELETE,
+// COPY and MOVE change the site, with `allow_write`, where it is not
+// read-only. A change to a whole tree goes on past members that fail, and
+// those are then reported in a 207 Multi-Status.
+//
+// The body of a PROPFIND, which may name the properties wanted, is not read.
+// The same five come back whatever was asked for, as they would for
+// `allprop`, which is what clients ask for anyway.
+use crate::config::{Config, Site};
+use crate::directory_config::{self, DirectoryConfig};
+use crate::request::Body;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+
+pub const ALLOW: &str = "OPTIONS, GET, HEAD, PROPFIND, PUT, MKCOL, DELETE, COPY, MOVE";
+
+// The methods a client may use, which are only those that read without
+// `allow_write`.
+pub fn allowed(config: &Config) -> &'static str {
+    if config.allow_write {
+        ALLOW
+    } else {
+        "OPTIONS, GET, HEAD, PROPFIND"
+    }
+}
+
+// Answers `Depth: infinity`, and PROPFIND without a Depth header, which
+// means the same. RFC 4918 lets servers refuse it with this precondition.
+pub const FINITE_DEPTH_ERROR: &str =
+    "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>\n";
+
+pub struct Resource {
+    href: String,
+    name: String,
+    is_dir: bool,
+    // Of files only; for a symbolic link, of what it points to.
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+    content_type: Option<String>,
+}
+
+// The resource at `url_path` and, with `children`, the entries of a directory
+// by name. `directory` is the configuration of `url_path`.
+pub async fn resources(site: &Site<'_>, directory: &DirectoryConfig, url_path: &Path, children: bool) -> io::Result<Vec<Resource>> {
+    let path = site.resolve(url_path);
+    let metadata = fs::metadata(&path).await?;
+    let name = match url_path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => "/".to_string(),
+    };
+    let mut resources = vec![resource(site, url_path, name, &metadata)];
+    if !children || !metadata.is_dir() {
+        return Ok(resources);
+    }
+    let mut entries = fs::read_dir(&path).await?;
+    let mut found = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == directory_config::FILE_NAME || !directory.show_hidden && name.starts_with('.') {
+            continue;
+        }
+        // Broken links have nothing to describe.
+        let Ok(metadata) = fs::metadata(entry.path()).await else { continue };
+        if !metadata.is_dir() && !directory.serves_extension(&name) {
+            continue;
+        }
+        // Listings stop at the same number of entries.
+        if found.len() == crate::listing::MAX_SORTED_ENTRIES {
+            break;
+        }
+        found.push(resource(site, &url_path.join(entry.file_name()), name, &metadata));
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    resources.extend(found);
+    Ok(resources)
+}
+
+fn resource(site: &Site<'_>, url_path: &Path, name: String, metadata: &std::fs::Metadata) -> Resource {
+    let is_dir = metadata.is_dir();
+    Resource {
+        href: href(url_path, is_dir),
+        name,
+        is_dir,
+        size: (!is_dir).then_some(metadata.len()),
+        modified: metadata.modified().ok(),
+        content_type: (!is_dir).then(|| crate::mime::content_type(url_path, &site.config.mime).to_string()),
+    }
+}
+
+// Where a site path is found under DAV_PATH.
+fn href(url_path: &Path, is_dir: bool) -> String {
+    let relative = url_path.strip_prefix("/").unwrap_or(url_path);
+    let dav_path = if relative.as_os_str().is_empty() { PathBuf::from(crate::DAV_PATH) } else { Path::new(crate::DAV_PATH).join(relative) };
+    if is_dir {
+        crate::directory_link(&dav_path)
+    } else {
+        crate::link(&dav_path)
+    }
+}
+
+// A 207 Multi-Status body describing `resources`.
+pub fn multistatus(resources: &[Resource]) -> String {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n".to_string();
+    for resource in resources {
+        let mut props = format!("<D:displayname>{}</D:displayname>", escape_xml(&resource.name));
+        if resource.is_dir {
+            props.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            props.push_str("<D:resourcetype/>");
+        }
+        if let Some(size) = resource.size {
+            props.push_str(&format!("<D:getcontentlength>{}</D:getcontentlength>", size));
+        }
+        if let Some(content_type) = &resource.content_type {
+            props.push_str(&format!("<D:getcontenttype>{}</D:getcontenttype>", escape_xml(content_type)));
+        }
+        if let Some(modified) = resource.modified {
+            props.push_str(&format!("<D:getlastmodified>{}</D:getlastmodified>", crate::range::http_date(modified)));
+        }
+        xml.push_str(&format!(
+            "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n",
+            escape_xml(&resource.href),
+            props
+        ));
+    }
+    xml.push_str("</D:multistatus>\n");
+    xml
+}
+
+// A member of a tree that could not be changed.
+pub struct Failure {
+    pub url_path: PathBuf,
+    is_dir: bool,
+    pub status: &'static str,
+}
+
+impl Failure {
+    fn new(url_path: &Path, is_dir: bool, error: &io::Error) -> Self {
+        tracing::error!("WebDAV change to {} failed: {}", url_path.display(), error);
+        Failure { url_path: url_path.to_path_buf(), is_dir, status: status(error) }
+    }
+
+    fn refused(url_path: &Path, is_dir: bool) -> Self {
+        tracing::debug!("WebDAV change refused at {}", url_path.display());
+        Failure { url_path: url_path.to_path_buf(), is_dir, status: "403 Forbidden" }
+    }
+}
+
+// What a change the filesystem refused with `error` is answered with.
+pub fn status(error: &io::Error) -> &'static str {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => "403 Forbidden",
+        io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => "507 Insufficient Storage",
+        _ => "500 Internal Server Error",
+    }
+}
+
+// A 207 Multi-Status body listing `failures`. Members that went well are left
+// out, as RFC 4918 has it.
+pub fn failures_multistatus(failures: &[Failure]) -> String {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n".to_string();
+    for failure in failures {
+        xml.push_str(&format!(
+            "<D:response><D:href>{}</D:href><D:status>HTTP/1.1 {}</D:status></D:response>\n",
+            escape_xml(&href(&failure.url_path, failure.is_dir)),
+            failure.status
+        ));
+    }
+    xml.push_str("</D:multistatus>\n");
+    xml
+}
+
+// Writes a request body to `path`: into a hidden file next to it first, which
+// is renamed over it once the whole body arrived, so that nobody ever reads
+// half an upload. Returns whether `path` is new.
+pub async fn put<R: AsyncRead + Unpin>(reader: &mut R, body: &mut Body, path: &Path) -> io::Result<bool> {
+    let temporary = temporary_path(path);
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&temporary).await?;
+    let written = async {
+        while let Some(chunk) = body.next_chunk(reader).await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        file.sync_all().await
+    }
+    .await;
+    drop(file);
+    let created = fs::symlink_metadata(path).await.is_err();
+    let renamed = match written {
+        Ok(()) => fs::rename(&temporary, path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = renamed {
+        let _ = fs::remove_file(&temporary).await;
+        return Err(e);
+    }
+    Ok(created)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let suffix = format!("{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+    path.with_file_name(format!(".{}.gredl-upload-{}", name, suffix))
+}
+
+// Removes the file or tree at `path`, known by `url_path`. A directory is
+// only removed once all that was in it is, so one whose members failed is
+// left in place without a failure of its own.
+pub async fn delete(url_path: PathBuf, path: PathBuf) -> Vec<Failure> {
+    tokio::task::spawn_blocking(move || {
+        let mut failures = Vec::new();
+        remove(&url_path, &path, &mut failures);
+        failures
+    })
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("WebDAV delete failed: {}", e);
+        Vec::new()
+    })
+}
+
+fn remove(url_path: &Path, path: &Path, failures: &mut Vec<Failure>) -> bool {
+    let is_dir = std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir());
+    let removed = if is_dir {
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                failures.push(Failure::new(url_path, true, &e));
+                return false;
+            }
+        };
+        let mut emptied = true;
+        for entry in entries {
+            match entry {
+                Ok(entry) => emptied &= remove(&url_path.join(entry.file_name()), &entry.path(), failures),
+                Err(e) => {
+                    failures.push(Failure::new(url_path, true, &e));
+                    return false;
+                }
+            }
+        }
+        if !emptied {
+            return false;
+        }
+        std::fs::remove_dir(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match removed {
+        // Already gone is as good as removed.
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            failures.push(Failure::new(url_path, is_dir, &e));
+            false
+        }
+        _ => true,
+    }
+}
+
+// What a change does to a whole tree, which decides what in it must be left
+// alone.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Copy,
+    Move,
+    // Of a tree, or of the one a COPY or MOVE overwrites.
+    Delete,
+}
+
+// The members of the tree at `path`, known by `url_path`, that `change` may
+// not touch, each as a 403:
+// - directory settings files, which WebDAV never changes;
+// - for a COPY or MOVE, files that are not served, by extension or size,
+//   since the copy would serve them under another name;
+// - for a MOVE or DELETE, read-only directories.
+// `directory` holds the settings of `path`; those of each directory below it
+// are read on the way down. Nothing is changed, so that a tree with any of
+// these can be refused whole.
+pub async fn refused(url_path: PathBuf, path: PathBuf, directory: DirectoryConfig, change: Change) -> Vec<Failure> {
+    let top = url_path.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut refused = Vec::new();
+        find_refused(&url_path, &path, &directory, change, &mut refused);
+        refused
+    })
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("WebDAV check of {} failed: {}", top.display(), e);
+        vec![Failure { url_path: top, is_dir: true, status: "500 Internal Server Error" }]
+    })
+}
+
+fn find_refused(url_path: &Path, path: &Path, directory: &DirectoryConfig, change: Change, refused: &mut Vec<Failure>) {
+    // What is gone, or cannot be looked at, the change itself fails on.
+    let Ok(metadata) = std::fs::symlink_metadata(path) else { return };
+    if !metadata.is_dir() {
+        let name = url_path.file_name().unwrap_or_default().to_string_lossy();
+        let too_large = metadata.is_file() && directory.max_file_size.is_some_and(|max_file_size| metadata.len() > max_file_size);
+        let unserved = change != Change::Delete && (!directory.serves_extension(&name) || too_large);
+        if name == directory_config::FILE_NAME || unserved {
+            refused.push(Failure::refused(url_path, false));
+        }
+        return;
+    }
+    if change != Change::Copy && directory.read_only {
+        return refused.push(Failure::refused(url_path, true));
+    }
+    let Ok(entries) = std::fs::read_dir(path) else { return };
+    for entry in entries.flatten() {
+        let (url_path, path) = (url_path.join(entry.file_name()), entry.path());
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            find_refused(&url_path, &path, &directory.for_subdirectory_blocking(&path), change, refused);
+        } else {
+            find_refused(&url_path, &path, directory, change, refused);
+        }
+    }
+}
+
+// Copies the file or tree at `from` to `to`, known by `url_path`, which must
+// not exist. Without `members` only a directory itself is created. Symbolic
+// links are copied as links, so a tree copies the way `cp -a` copies it and
+// a link back up the tree does not go on forever.
+pub async fn copy(from: PathBuf, to: PathBuf, url_path: PathBuf, members: bool) -> Vec<Failure> {
+    tokio::task::spawn_blocking(move || {
+        let mut failures = Vec::new();
+        copy_tree(&from, &to, &url_path, members, &mut failures);
+        failures
+    })
+    .await
+    .unwrap_or_else(|e| {
+        tracing::error!("WebDAV copy failed: {}", e);
+        Vec::new()
+    })
+}
+
+fn copy_tree(from: &Path, to: &Path, url_path: &Path, members: bool, failures: &mut Vec<Failure>) {
+    let metadata = match std::fs::symlink_metadata(from) {
+        Ok(metadata) => metadata,
+        Err(e) => return failures.push(Failure::new(url_path, false, &e)),
+    };
+    if metadata.is_symlink() {
+        if let Err(e) = copy_link(from, to) {
+            failures.push(Failure::new(url_path, false, &e));
+        }
+        return;
+    }
+    if !metadata.is_dir() {
+        if let Err(e) = std::fs::copy(from, to) {
+            failures.push(Failure::new(url_path, false, &e));
+        }
+        return;
+    }
+    if let Err(e) = std::fs::create_dir(to) {
+        return failures.push(Failure::new(url_path, true, &e));
+    }
+    if !members {
+        return;
+    }
+    let entries = match std::fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(e) => return failures.push(Failure::new(url_path, true, &e)),
+    };
+    for entry in entries {
+        match entry {
+            Ok(entry) => copy_tree(&entry.path(), &to.join(entry.file_name()), &url_path.join(entry.file_name()), true, failures),
+            Err(e) => return failures.push(Failure::new(url_path, true, &e)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+}
+
+// Elsewhere links are not something a server can make, so what one points to
+// is copied, when it is a file.
+#[cfg(not(unix))]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::copy(from, to).map(|_| ())
+}
+
+// Moves the file or tree at `from` to `to`, known by `url_path`, which must
+// not exist. Between filesystems, which aliases can put two paths of a site
+// on, that is a copy followed by a delete of what was copied.
+pub async fn rename(from: PathBuf, to: PathBuf, from_url_path: PathBuf, url_path: PathBuf) -> Vec<Failure> {
+    match fs::rename(&from, &to).await {
+        Ok(()) => Vec::new(),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let failures = copy(from.clone(), to, url_path, true).await;
+            if !failures.is_empty() {
+                return failures;
+            }
+            delete(from_url_path, from).await
+        }
+        Err(e) => {
+            let is_dir = fs::symlink_metadata(&from).await.is_ok_and(|metadata| metadata.is_dir());
+            vec![Failure::new(&from_url_path, is_dir, &e)]
+        }
+    }
+}
+
+// `text` made safe to place in XML. Control characters other than white space
+// cannot appear in XML 1.0 at all, even escaped, so they are replaced.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
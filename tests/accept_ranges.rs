@@ -0,0 +1,85 @@
+mod common;
+
+use common::{document_root, get, header, send, start_server};
+
+#[test]
+fn raw_files_advertise_byte_ranges() {
+    let root = document_root("accept-ranges-file");
+    std::fs::write(root.join("hello.txt"), "hello, world").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/hello.txt?raw=1");
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+    assert_eq!(header(&response, "Accept-Ranges"), Some("bytes"));
+}
+
+#[test]
+fn listings_and_error_pages_refuse_ranges() {
+    let root = document_root("accept-ranges-other");
+    std::fs::create_dir(root.join("sub")).unwrap();
+    std::fs::write(root.join("hello.txt"), "hello, world").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for target in ["/", "/sub/", "/hello.txt", "/missing"] {
+        let response = get(&server.addr, target);
+        assert_eq!(header(&response, "Accept-Ranges"), Some("none"), "for {}: {}", target, response);
+    }
+}
+
+#[test]
+fn ranges_of_uncached_files_are_sent_in_full() {
+    let root = document_root("accept-ranges-uncached");
+    let contents: String = (0..20_000).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+    std::fs::write(root.join("letters.txt"), &contents).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--file-cache-max-size", "0"]);
+
+    let response = get(&server.addr, "/letters.txt?raw=1");
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+    assert!(response.ends_with(&format!("\r\n\r\n{}", contents)));
+
+    let request = "GET /letters.txt?raw=1 HTTP/1.1\r\nHost: localhost\r\nRange: bytes=10000-10009\r\nConnection: close\r\n\r\n";
+    let response = send(&server.addr, request);
+    assert!(response.starts_with("HTTP/1.1 206"), "unexpected response: {}", response);
+    assert!(response.ends_with(&format!("\r\n\r\n{}", &contents[10000..10010])));
+
+    let request = "GET /letters.txt?raw=1 HTTP/1.1\r\nHost: localhost\r\nRange: bytes=0-2,19997-\r\nConnection: close\r\n\r\n";
+    let response = send(&server.addr, request);
+    assert!(response.starts_with("HTTP/1.1 206"), "unexpected response: {}", response);
+    assert!(response.contains("Content-Range: bytes 0-2/20000\r\n\r\nabc\r\n"), "unexpected response: {}", response);
+    assert!(response.contains(&format!("Content-Range: bytes 19997-19999/20000\r\n\r\n{}\r\n", &contents[19997..])));
+}
+
+#[test]
+fn if_range_decides_between_the_range_and_the_whole_file() {
+    let root = document_root("accept-ranges-if-range");
+    std::fs::write(root.join("hello.txt"), "hello, world").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/hello.txt?raw=1");
+    let etag = header(&response, "ETag").unwrap().to_string();
+    let last_modified = header(&response, "Last-Modified").unwrap().to_string();
+    let ranged = |if_range: &str| {
+        let request = format!("GET /hello.txt?raw=1 HTTP/1.1\r\nHost: localhost\r\nRange: bytes=0-4\r\nIf-Range: {}\r\n\r\n", if_range);
+        send(&server.addr, &request)
+    };
+
+    let response = ranged(&etag);
+    assert!(response.starts_with("HTTP/1.1 206"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello"));
+    let response = ranged(&last_modified);
+    assert!(response.starts_with("HTTP/1.1 206"), "{}", response);
+    // Weak tags never match, and a date must be the file's to the second.
+    let response = ranged(&format!("W/{}", etag));
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello, world"));
+    let response = ranged("Mon, 01 Jan 2001 00:00:00 GMT");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello, world"));
+
+    // Once the file changes, the old tag asks for a stale copy.
+    std::fs::write(root.join("hello.txt"), "hello again, world").unwrap();
+    let response = ranged(&etag);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello again, world"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn alias_prefixes_are_served_from_their_own_directory() {
+    let root = document_root("aliases");
+    for dir in ["site", "site/static", "assets", "assets/css"] {
+        std::fs::create_dir(root.join(dir)).unwrap();
+    }
+    std::fs::write(root.join("site/static/where.txt"), "root").unwrap();
+    std::fs::write(root.join("site/staticky.txt"), "root").unwrap();
+    std::fs::write(root.join("assets/where.txt"), "alias").unwrap();
+    std::fs::write(root.join("assets/css/main.css"), "body {}").unwrap();
+    let alias = format!("/static/={}", root.join("assets").display());
+    let server = start_server(&["--root", root.join("site").to_str().unwrap(), "--alias", &alias]);
+
+    assert!(get(&server.addr, "/static/where.txt?raw=1").ends_with("\r\n\r\nalias"));
+    assert!(get(&server.addr, "/static/css/main.css?raw=1").ends_with("\r\n\r\nbody {}"));
+    assert!(get(&server.addr, "/staticky.txt?raw=1").ends_with("\r\n\r\nroot"));
+    assert!(get(&server.addr, "/static").starts_with("HTTP/1.1 301"));
+    let listing = get(&server.addr, "/static/");
+    assert!(listing.contains("css"), "{}", listing);
+    // `..` is normalized away before the alias is applied.
+    assert!(get(&server.addr, "/static/../where.txt?raw=1").starts_with("HTTP/1.1 404"));
+    let _ = std::fs::remove_dir_all(&root);
+}
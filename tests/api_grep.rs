@@ -0,0 +1,95 @@
+mod common;
+
+use common::{document_root, get, header, send, start_server};
+
+// Answered over HTTP/1.0, so the streamed body comes unchunked. The matches
+// sorted by path and line, and the closing count.
+fn grep(addr: &str, target: &str) -> (Vec<(String, u64, String)>, serde_json::Value) {
+    let response = send(addr, &format!("GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", target));
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/x-ndjson"));
+    let mut lines: Vec<serde_json::Value> =
+        response.split_once("\r\n\r\n").unwrap().1.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let end = lines.pop().unwrap();
+    let mut matches: Vec<(String, u64, String)> = lines
+        .iter()
+        .map(|found| {
+            let field = |name: &str| found[name].as_str().unwrap().to_string();
+            (field("path"), found["line_number"].as_u64().unwrap(), field("line"))
+        })
+        .collect();
+    matches.sort();
+    (matches, end)
+}
+
+fn tree(name: &str) -> std::path::PathBuf {
+    let root = document_root(name);
+    std::fs::create_dir_all(root.join("etc").join("nginx")).unwrap();
+    std::fs::write(root.join("etc").join("hosts"), "127.0.0.1 localhost\n10.0.0.2 db.internal\n").unwrap();
+    std::fs::write(root.join("etc").join("nginx").join("site.conf"), "server_name example.org;\r\nproxy_pass http://db.internal:8080;\r\n").unwrap();
+    std::fs::write(root.join("etc").join("db.bin"), b"db.internal\0\x01\x02").unwrap();
+    std::fs::write(root.join("etc").join(".secret"), "db.internal password\n").unwrap();
+    std::fs::write(root.join("etc").join("big.log"), "db.internal\n".repeat(200)).unwrap();
+    std::fs::write(root.join("etc").join("keys.env"), "DB=db.internal\n").unwrap();
+    root
+}
+
+#[test]
+fn finds_lines_in_text_files() {
+    let root = tree("api-grep");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", "env", "--grep-max-file-size", "1000"]);
+
+    // Binary, hidden, denied and oversized files are all left out.
+    let (matches, end) = grep(&server.addr, "/api/v1/grep?q=db.internal&path=/etc");
+    assert_eq!(
+        matches,
+        [
+            ("/etc/hosts".to_string(), 2, "10.0.0.2 db.internal".to_string()),
+            ("/etc/nginx/site.conf".to_string(), 2, "proxy_pass http://db.internal:8080;".to_string()),
+        ]
+    );
+    assert_eq!(end, serde_json::json!({ "matches": 2, "truncated": false }));
+
+    // Literal patterns are not regexes.
+    assert!(grep(&server.addr, "/api/v1/grep?q=db.*8080&path=/").0.is_empty());
+    let (matches, _) = grep(&server.addr, "/api/v1/grep?q=%5Edb%5C.%7C%5Cd%2B%3B%24&path=/&regex=1");
+    assert_eq!(matches.len(), 1, "{:?}", matches);
+    assert_eq!(matches[0].0, "/etc/nginx/site.conf");
+
+    let (matches, _) = grep(&server.addr, "/api/v1/grep?q=password&path=/etc&hidden=1");
+    assert_eq!(matches, [("/etc/.secret".to_string(), 1, "db.internal password".to_string())]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn stops_at_the_match_limit() {
+    let root = tree("api-grep-limit");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--grep-max-matches", "5"]);
+
+    let (matches, end) = grep(&server.addr, "/api/v1/grep?q=db.internal&path=/etc");
+    assert_eq!(matches.len(), 5);
+    assert_eq!(end, serde_json::json!({ "matches": 5, "truncated": true }));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn refuses_bad_requests_as_json() {
+    let root = tree("api-grep-errors");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for (target, status) in [
+        ("/api/v1/grep?path=/etc", "400"),
+        ("/api/v1/grep?q=(unclosed&path=/etc&regex=1", "400"),
+        ("/api/v1/grep?q=a%7B1000%7D%7B1000%7D&path=/etc&regex=1", "400"),
+        ("/api/v1/grep?q=x&path=/missing", "404"),
+        ("/api/v1/grep?q=x&path=/etc/hosts", "404"),
+    ] {
+        let response = get(&server.addr, target);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}: {}", target, response);
+        assert_eq!(header(&response, "Content-Type"), Some("application/json"), "{}", target);
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
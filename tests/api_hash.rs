@@ -0,0 +1,77 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+fn body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+#[test]
+fn digests_files_with_each_algorithm() {
+    let root = document_root("api-hash");
+    std::fs::write(root.join("hello.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/api/v1/hash?path=/hello.txt");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    let checksum = body(&response);
+    assert_eq!(checksum["path"], "/hello.txt");
+    assert_eq!(checksum["algo"], "sha256");
+    assert_eq!(checksum["digest"], "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03");
+    assert_eq!(checksum["size"], 6);
+    assert!(checksum["mtime"].is_string());
+
+    let blake3 = blake3::hash(b"hello\n").to_hex().to_string();
+    for (algo, digest) in [
+        ("sha1", "f572d396fae9206628714fb2ce00f72e94f2258f"),
+        ("md5", "b1946ac92492d2347c6235b4d2611184"),
+        ("BLAKE3", blake3.as_str()),
+    ] {
+        let checksum = body(&get(&server.addr, &format!("/api/v1/hash?path=/hello.txt&algo={}", algo)));
+        assert_eq!(checksum["digest"], digest, "{}", algo);
+        assert_eq!(checksum["algo"], algo.to_lowercase());
+    }
+
+    // A changed file is hashed again rather than answered from the cache.
+    std::fs::write(root.join("hello.txt"), "hello, world\n").unwrap();
+    let checksum = body(&get(&server.addr, "/api/v1/hash?path=/hello.txt&algo=md5"));
+    assert_eq!(checksum["digest"], "22c3683b094136c3398391ae71b20f04");
+    assert_eq!(checksum["size"], 13);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn file_info_links_to_the_checksum() {
+    let root = document_root("api-hash-link");
+    std::fs::write(root.join("notes.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let page = get(&server.addr, "/notes.txt");
+    let href = page.split(r#"<a href=""#).map(|rest| rest.split('"').next().unwrap()).find(|href| href.starts_with("/api/v1/hash?")).unwrap();
+    let checksum = body(&get(&server.addr, &href.replace("&amp;", "&")));
+    assert_eq!(checksum["path"], "/notes.txt");
+    assert_eq!(checksum["digest"], "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn refusals_are_json() {
+    let root = document_root("api-hash-errors");
+    std::fs::create_dir(root.join("dir")).unwrap();
+    std::fs::write(root.join("server.pem"), "key").unwrap();
+    std::fs::write(root.join("notes.txt"), "notes").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", "pem"]);
+
+    for (target, status) in [
+        ("/api/v1/hash?path=/missing.txt", 404),
+        ("/api/v1/hash?path=/dir", 400),
+        ("/api/v1/hash?path=/notes.txt&algo=crc32", 400),
+        ("/api/v1/hash?path=/server.pem", 403),
+    ] {
+        let response = get(&server.addr, target);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}: {}", target, response);
+        assert_eq!(body(&response)["status"], status, "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+fn body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+fn names(listing: &serde_json::Value) -> Vec<&str> {
+    listing["entries"].as_array().unwrap().iter().map(|entry| entry["name"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn lists_the_directory_named_by_path() {
+    let root = document_root("api-ls");
+    std::fs::create_dir_all(root.join("docs").join("drafts")).unwrap();
+    std::fs::write(root.join("docs").join("b.txt"), "bb\n").unwrap();
+    std::fs::write(root.join("docs").join("a.txt"), "a\n").unwrap();
+    std::fs::write(root.join("docs").join(".secret"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/api/v1/ls?path=/docs");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    let listing = body(&response);
+    assert_eq!(listing["path"], "/docs/");
+    assert_eq!(listing["parent"], "/");
+    assert_eq!(names(&listing), ["drafts", "a.txt", "b.txt"]);
+    assert_eq!(listing["entries"][1]["href"], "/docs/a.txt");
+    assert_eq!(listing["entries"][1]["size"], 2);
+    assert_eq!((listing["offset"].clone(), listing["total"].clone(), listing["truncated"].clone()), (0.into(), 3.into(), false.into()));
+
+    // The same entries a request for the directory that prefers JSON gets.
+    let negotiated = body(&common::send(
+        &server.addr,
+        "GET /docs/ HTTP/1.1\r\nHost: localhost\r\nAccept: application/json\r\n\r\n",
+    ));
+    assert_eq!(negotiated["entries"], listing["entries"]);
+
+    let listing = body(&get(&server.addr, "/api/v1/ls?path=/docs/&hidden=1&sort=size&order=desc"));
+    assert_eq!(names(&listing), ["drafts", "b.txt", "a.txt", ".secret"]);
+    let listing = body(&get(&server.addr, "/api/v1/ls?path=/docs/&filter=*.txt"));
+    assert_eq!(names(&listing), ["a.txt", "b.txt"]);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn pages_with_offset_and_limit() {
+    let root = document_root("api-ls-pages");
+    for i in 0..5 {
+        std::fs::write(root.join(format!("{}.txt", i)), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = body(&get(&server.addr, "/api/v1/ls?path=/&offset=1&limit=2"));
+    assert_eq!(names(&listing), ["1.txt", "2.txt"]);
+    assert_eq!((listing["offset"].clone(), listing["total"].clone(), listing["truncated"].clone()), (1.into(), 5.into(), true.into()));
+    assert_eq!(listing["summary"]["files"], 2);
+
+    let listing = body(&get(&server.addr, "/api/v1/ls?path=/&offset=3&limit=2"));
+    assert_eq!(names(&listing), ["3.txt", "4.txt"]);
+    assert_eq!(listing["truncated"], false);
+    let listing = body(&get(&server.addr, "/api/v1/ls?path=/&offset=9"));
+    assert!(names(&listing).is_empty());
+    assert_eq!(listing["truncated"], false);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn errors_are_json() {
+    let root = document_root("api-ls-errors");
+    std::fs::write(root.join("file.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for (target, status) in [
+        ("/api/v1/ls?path=/missing", 404),
+        ("/api/v1/ls?path=/file.txt", 400),
+        ("/api/v1/ls?path=/&limit=ten", 400),
+        ("/api/v1/ls?path=/&filter=[a", 400),
+    ] {
+        let response = get(&server.addr, target);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}: {}", target, response);
+        assert_eq!(header(&response, "Content-Type"), Some("application/json"), "{}", target);
+        let error = body(&response);
+        assert_eq!(error["status"], status, "{}", target);
+        assert!(error["error"].as_str().is_some_and(|message| !message.is_empty()), "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
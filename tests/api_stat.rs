@@ -0,0 +1,79 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+fn body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+#[test]
+fn describes_files_and_directories() {
+    let root = document_root("api-stat");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("docs").join("notes.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/api/v1/stat?path=/docs/notes.txt");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    let stat = body(&response);
+    assert_eq!(stat["path"], "/docs/notes.txt");
+    assert_eq!(stat["type"], "file");
+    assert_eq!(stat["size"], 6);
+    assert_eq!(stat["target"], serde_json::Value::Null);
+    let mtime = stat["mtime"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(mtime).is_ok(), "{}", mtime);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(root.join("docs").join("notes.txt")).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(stat["mode"], mode);
+        assert!(stat["ctime"].is_string());
+    }
+
+    let stat = body(&get(&server.addr, "/api/v1/stat?path=/docs/../docs/"));
+    assert_eq!(stat["path"], "/docs");
+    assert_eq!(stat["type"], "directory");
+    assert_eq!(stat["size"], serde_json::Value::Null);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn symbolic_links_give_their_target() {
+    let root = document_root("api-stat-links");
+    std::fs::write(root.join("real.txt"), "abc").unwrap();
+    std::os::unix::fs::symlink("real.txt", root.join("link.txt")).unwrap();
+    std::os::unix::fs::symlink("missing.txt", root.join("broken.txt")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let stat = body(&get(&server.addr, "/api/v1/stat?path=/link.txt"));
+    assert_eq!(stat["type"], "symlink");
+    assert_eq!(stat["target"], "real.txt");
+    assert_eq!(stat["size"], 3);
+
+    let stat = body(&get(&server.addr, "/api/v1/stat?path=/broken.txt"));
+    assert_eq!(stat["type"], "symlink");
+    assert_eq!(stat["target"], "missing.txt");
+    assert_eq!(stat["size"], serde_json::Value::Null);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn refusals_are_json() {
+    let root = document_root("api-stat-errors");
+    std::fs::write(root.join("server.pem"), "key").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", "pem"]);
+
+    for (target, status) in [
+        ("/api/v1/stat?path=/missing.txt", 404),
+        ("/api/v1/stat?path=/../../etc/passwd", 404),
+        ("/api/v1/stat?path=/server.pem", 403),
+        ("/api/v1/stat?path=/.gredl.toml", 404),
+    ] {
+        let response = get(&server.addr, target);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}: {}", target, response);
+        assert_eq!(body(&response)["status"], status, "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
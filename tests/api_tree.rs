@@ -0,0 +1,112 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+fn tree(addr: &str, target: &str) -> serde_json::Value {
+    let response = get(addr, target);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+fn child<'a>(node: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+    node["children"].as_array().unwrap().iter().find(|child| child["name"] == name).unwrap_or_else(|| panic!("no {} in {}", name, node))
+}
+
+fn names(node: &serde_json::Value) -> Vec<&str> {
+    node["children"].as_array().unwrap().iter().map(|child| child["name"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn nests_directories_down_to_the_depth() {
+    let root = document_root("api-tree");
+    std::fs::create_dir_all(root.join("srv").join("www").join("assets")).unwrap();
+    std::fs::create_dir_all(root.join("srv").join("empty")).unwrap();
+    std::fs::write(root.join("srv").join("README.md"), "hello").unwrap();
+    std::fs::write(root.join("srv").join("www").join("index.html"), "<p>").unwrap();
+    std::fs::write(root.join("srv").join("www").join("assets").join("app.js"), "1").unwrap();
+    std::fs::write(root.join("srv").join(".env"), "KEY=1").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let tree1 = tree(&server.addr, "/api/v1/tree?path=/srv");
+    assert_eq!(tree1["path"], "/srv/");
+    assert_eq!(tree1["depth"], 1);
+    let srv = &tree1["root"];
+    assert_eq!(srv["name"], "srv");
+    assert_eq!(srv["type"], "directory");
+    assert_eq!(srv["truncated"], false);
+    assert_eq!(names(srv), ["README.md", "empty", "www"]);
+    let readme = child(srv, "README.md");
+    assert_eq!(readme["type"], "file");
+    assert_eq!(readme["size"], 5);
+    assert!(readme["mtime"].is_string());
+    assert!(readme.get("truncated").is_none() && readme.get("children").is_none());
+    // Not read at this depth.
+    assert_eq!(child(srv, "www")["truncated"], true);
+    assert_eq!(names(child(srv, "www")), Vec::<&str>::new());
+
+    let tree3 = tree(&server.addr, "/api/v1/tree?path=/srv&depth=3&hidden=1");
+    let srv = &tree3["root"];
+    assert_eq!(names(srv), [".env", "README.md", "empty", "www"]);
+    assert_eq!(child(srv, "empty")["truncated"], false);
+    let assets = child(child(srv, "www"), "assets");
+    assert_eq!(assets["truncated"], false);
+    assert_eq!(child(assets, "app.js")["size"], 1);
+
+    let whole = tree(&server.addr, "/api/v1/tree?depth=2");
+    assert_eq!(whole["path"], "/");
+    assert_eq!(whole["root"]["name"], "/");
+    assert_eq!(child(&whole["root"], "srv")["truncated"], false);
+    assert_eq!(child(child(&whole["root"], "srv"), "www")["truncated"], true);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn follows_links_once_and_caps_the_nodes() {
+    let root = document_root("api-tree-links");
+    std::fs::create_dir_all(root.join("a").join("b")).unwrap();
+    std::os::unix::fs::symlink("..", root.join("a").join("b").join("up")).unwrap();
+    std::fs::create_dir(root.join("target")).unwrap();
+    std::fs::write(root.join("target").join("file.txt"), "x").unwrap();
+    std::os::unix::fs::symlink("../target", root.join("a").join("linked")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--search-max-depth", "50"]);
+
+    // The link back up is listed but not read again, so the walk ends.
+    let a = tree(&server.addr, "/api/v1/tree?path=/a&depth=40");
+    let a = &a["root"];
+    let up = child(child(a, "b"), "up");
+    assert_eq!(up["type"], "directory");
+    assert_eq!(up["truncated"], true);
+    assert_eq!(child(child(a, "linked"), "file.txt")["size"], 1);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--tree-max-nodes", "2"]);
+    let capped = tree(&server.addr, "/api/v1/tree?depth=5");
+    assert_eq!(names(&capped["root"]), ["a", "target"]);
+    assert_eq!(capped["root"]["truncated"], false);
+    assert_eq!(child(&capped["root"], "a")["truncated"], true);
+    assert_eq!(names(child(&capped["root"], "a")), Vec::<&str>::new());
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn refuses_bad_requests_as_json() {
+    let root = document_root("api-tree-errors");
+    std::fs::write(root.join("notes.txt"), "n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for (target, status) in [
+        ("/api/v1/tree?depth=0", "400"),
+        ("/api/v1/tree?depth=deep", "400"),
+        ("/api/v1/tree?path=/missing", "404"),
+        ("/api/v1/tree?path=/notes.txt", "404"),
+    ] {
+        let response = get(&server.addr, target);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", status)), "{}: {}", target, response);
+        assert_eq!(header(&response, "Content-Type"), Some("application/json"), "{}", target);
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
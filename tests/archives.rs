@@ -0,0 +1,28 @@
+mod common;
+
+use common::{document_root, header, send_bytes, start_server};
+
+fn head(response: &[u8]) -> String {
+    let end = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap();
+    String::from_utf8(response[..end + 4].to_vec()).unwrap()
+}
+
+#[test]
+fn archive_names_cannot_inject_headers() {
+    let root = document_root("archive-names");
+    std::fs::create_dir(root.join("a\r\nX-Injected: 1")).unwrap();
+    std::fs::create_dir(root.join("résumé")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = head(&send_bytes(&server.addr, "GET /a%0D%0AX-Injected:%201/?download=zip HTTP/1.0\r\n\r\n"));
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "X-Injected"), None);
+    assert_eq!(header(&response, "Content-Disposition"), Some(r#"attachment; filename="a__X-Injected: 1.zip""#));
+
+    let response = head(&send_bytes(&server.addr, "GET /r%C3%A9sum%C3%A9/?download=tar.gz HTTP/1.0\r\n\r\n"));
+    assert_eq!(
+        header(&response, "Content-Disposition"),
+        Some(r#"attachment; filename="r_sum_.tar.gz"; filename*=UTF-8''r%C3%A9sum%C3%A9.tar.gz"#)
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use base64::Engine;
+use common::{document_root, get, send, send_bytes, start_server};
+
+#[test]
+fn audit_file_records_files_sent_and_failed_logins() {
+    let root = document_root("audit");
+    std::fs::write(root.join("report.txt"), "quarterly numbers\n").unwrap();
+    let private = document_root("audit-files");
+    let users_file = private.join("users.toml");
+    std::fs::write(&users_file, format!("[alice]\npassword = \"{}\"\nroots = [\"/\"]\n", bcrypt::hash("wonderland", 4).unwrap())).unwrap();
+    let audit_log = private.join("audit.log");
+    let server = start_server(&[
+        "--root",
+        root.to_str().unwrap(),
+        "--users-file",
+        users_file.to_str().unwrap(),
+        "--audit-log",
+        audit_log.to_str().unwrap(),
+    ]);
+
+    let login = |password: &str| {
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("alice:{}", password));
+        format!("GET /report.txt?raw=1 HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {}\r\n\r\n", credentials)
+    };
+    assert!(send(&server.addr, &login("wonderland")).ends_with("quarterly numbers\n"));
+    assert!(send(&server.addr, &login("guess")).starts_with("HTTP/1.1 401"));
+    assert!(get(&server.addr, "/").starts_with("HTTP/1.1 401"));
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3, "{:?}", records);
+    assert_eq!(records[0]["user"], "alice");
+    assert_eq!(records[0]["path"], "/report.txt?raw=1");
+    assert_eq!(records[0]["status"], 200);
+    assert_eq!(records[0]["bytes"], 18);
+    assert_eq!(records[0]["remote_ip"], "127.0.0.1");
+    assert!(records[0]["timestamp"].is_string());
+    assert_eq!((&records[1]["user"], &records[1]["status"]), (&"alice".into(), &401.into()));
+    assert_eq!((&records[2]["user"], &records[2]["status"]), (&serde_json::Value::Null, &401.into()));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&private);
+}
+
+#[test]
+fn audit_file_records_archives_sent() {
+    let root = document_root("audit-archive");
+    std::fs::create_dir(root.join("reports")).unwrap();
+    std::fs::write(root.join("reports").join("q1.txt"), "quarterly numbers\n").unwrap();
+    let private = document_root("audit-archive-files");
+    let audit_log = private.join("audit.log");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--audit-log", audit_log.to_str().unwrap()]);
+
+    let response = send_bytes(&server.addr, "GET /reports/?download=zip HTTP/1.0\r\n\r\n");
+    let body = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    // The record is written once the archive is, after the client has it.
+    for _ in 0..50 {
+        if std::fs::metadata(&audit_log).is_ok_and(|metadata| metadata.len() > 0) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&audit_log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 1, "{:?}", records);
+    assert_eq!(records[0]["path"], "/reports/?download=zip");
+    assert_eq!(records[0]["status"], 200);
+    assert_eq!(records[0]["bytes"], (response.len() - body) as u64);
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&private);
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn requests_are_served_under_the_base_url_only() {
+    let root = document_root("base-url");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("docs").join("read me.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--base-url", "/files/"]);
+
+    assert!(get(&server.addr, "/files/").starts_with("HTTP/1.1 200"));
+    assert!(get(&server.addr, "/files/docs/read%20me.txt?raw=1").ends_with("hello\n"));
+    assert!(get(&server.addr, "/docs/").starts_with("HTTP/1.1 404"));
+    assert!(get(&server.addr, "/filesystem/").starts_with("HTTP/1.1 404"));
+
+    let listing = get(&server.addr, "/files/docs/");
+    assert!(listing.contains(r#"href="/files/docs/read%20me.txt""#), "{}", listing);
+    assert!(listing.contains(r#"<a href="/files/">Root</a>"#));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::{send, start_server};
+
+#[test]
+fn declared_length_over_the_limit_is_refused_before_the_body() {
+    let server = start_server(&["--max-body-size", "1024"]);
+
+    // No body bytes follow the head; the answer must not wait for them.
+    let response = send(&server.addr, "PUT /upload.bin HTTP/1.1\r\nHost: x\r\nContent-Length: 4096\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 413"), "unexpected response: {}", response);
+}
+
+#[test]
+fn chunked_body_over_the_limit_is_refused() {
+    let server = start_server(&["--max-body-size", "1024"]);
+
+    let chunk = "x".repeat(800);
+    let request = format!(
+        "PUT /upload.bin HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n320\r\n{0}\r\n320\r\n{0}\r\n0\r\n\r\n",
+        chunk
+    );
+    let response = send(&server.addr, &request);
+    assert!(response.starts_with("HTTP/1.1 413"), "unexpected response: {}", response);
+}
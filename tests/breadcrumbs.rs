@@ -0,0 +1,53 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+// The href of the link with text `text`.
+fn href<'a>(page: &'a str, text: &str) -> &'a str {
+    let end = page.find(&format!(r#"">{}</a>"#, text)).unwrap_or_else(|| panic!("no link {}: {}", text, page));
+    let start = page[..end].rfind(r#"href=""#).unwrap() + 6;
+    &page[start..end]
+}
+
+#[test]
+fn every_breadcrumb_segment_links_to_its_directory() {
+    let root = document_root("breadcrumbs");
+    std::fs::create_dir_all(root.join("100% done").join("with space").join("ünï")).unwrap();
+    std::fs::write(root.join("100% done").join("marker.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let page = get(&server.addr, "/100%25%20done/with%20space/%C3%BCn%C3%AF/");
+    assert!(page.contains(r#"<a href="/">Root</a> / "#), "{}", page);
+    assert!(page.contains(" / ünï</div>"), "{}", page);
+    assert!(!page.contains(r#"">ünï</a>"#));
+
+    let first = href(&page, "100% done");
+    assert!(get(&server.addr, first).contains("📄 marker.txt"), "{}", first);
+    let second = href(&page, "with space");
+    assert!(get(&server.addr, second).contains("📁 ünï"), "{}", second);
+    assert_eq!(href(&page, "📁 .."), second);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn entry_links_round_trip_awkward_names() {
+    let root = document_root("link-encoding");
+    std::fs::create_dir(root.join("a&b [x]")).unwrap();
+    std::fs::write(root.join("a&b [x]").join("100% done #final?.txt"), "final").unwrap();
+    std::fs::write(root.join("a&b [x]").join("a&amp;b.txt"), "entity").unwrap();
+    std::fs::write(root.join("a&b [x]").join("a&b.txt"), "plain").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let dir = href(&get(&server.addr, "/"), "📁 a&amp;b [x]").to_string();
+    assert_eq!(dir, "/a%26b%20%5Bx%5D/");
+    let page = get(&server.addr, &dir);
+    let file = href(&page, "📄 100% done #final?.txt").to_string();
+    assert_eq!(file, "/a%26b%20%5Bx%5D/100%25%20done%20%23final%3F.txt");
+    assert!(get(&server.addr, &format!("{}?raw=1", file)).ends_with("\r\n\r\nfinal"));
+    // Left as is, the browser would read `&amp;` in the href as `&` and
+    // fetch the other file.
+    let file = href(&page, "📄 a&amp;amp;b.txt").to_string();
+    assert_eq!(file, "/a%26b%20%5Bx%5D/a%26amp;b.txt");
+    assert!(get(&server.addr, &format!("{}?raw=1", file)).ends_with("\r\n\r\nentity"));
+    let _ = std::fs::remove_dir_all(&root);
+}
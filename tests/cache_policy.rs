@@ -0,0 +1,75 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn files_get_the_cache_control_of_their_type() {
+    let root = document_root("cache-policy");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("app.JS"), "let a;").unwrap();
+    std::fs::write(root.join("page.html"), "<p>").unwrap();
+    std::fs::write(root.join("part.gcode"), "G28").unwrap();
+    std::fs::write(root.join("docs/index.html"), "<p>").unwrap();
+    let server = start_server(&[
+        "--root",
+        root.to_str().unwrap(),
+        "--cache-policy",
+        ".js=public, max-age=31536000, immutable",
+        "--cache-policy",
+        "html=no-store",
+    ]);
+
+    let cache_control = |target: &str| header(&get(&server.addr, target), "Cache-Control").map(str::to_string);
+    assert_eq!(cache_control("/app.JS?raw=1").as_deref(), Some("public, max-age=31536000, immutable"));
+    assert_eq!(cache_control("/page.html?raw=1").as_deref(), Some("no-store"));
+    // Index files are files too.
+    assert_eq!(cache_control("/docs/").as_deref(), Some("no-store"));
+    assert_eq!(cache_control("/part.gcode?raw=1").as_deref(), Some("no-cache"));
+    // Generated pages are not files of a type.
+    assert_eq!(cache_control("/"), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn cache_policy_wins_over_configured_headers_and_the_default_does_not() {
+    let root = document_root("cache-policy-headers");
+    std::fs::create_dir(root.join("assets")).unwrap();
+    std::fs::write(root.join("assets/app.js"), "let a;").unwrap();
+    std::fs::write(root.join("assets/logo.svg"), "<svg/>").unwrap();
+    std::fs::write(root.join("notes.txt"), "notes").unwrap();
+    let private = document_root("cache-policy-headers-config");
+    let config = private.join("gredl.toml");
+    std::fs::write(
+        &config,
+        "[cache_policy]\n\".js\" = \"public, max-age=31536000, immutable\"\n\n\
+        [headers]\n\"Cache-Control\" = \"private\"\n\n\
+        [[path_headers]]\npath_prefix = \"/assets\"\n[path_headers.headers]\n\"Cache-Control\" = \"public, max-age=3600\"\n",
+    )
+    .unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--config", config.to_str().unwrap()]);
+
+    for (target, expected) in [
+        ("/assets/app.js?raw=1", "public, max-age=31536000, immutable"),
+        ("/assets/logo.svg?raw=1", "public, max-age=3600"),
+        ("/notes.txt?raw=1", "private"),
+    ] {
+        let response = get(&server.addr, target);
+        assert_eq!(response.matches("Cache-Control:").count(), 1, "{}", target);
+        assert_eq!(header(&response, "Cache-Control"), Some(expected), "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&private);
+}
+
+#[test]
+fn cache_policy_keys_must_be_extensions() {
+    let root = document_root("cache-policy-invalid");
+    for policy in ["docs/app.js=no-cache", "=no-cache"] {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+            .args(["--port", "0", "--root", root.to_str().unwrap(), "--cache-policy", policy])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}", policy);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
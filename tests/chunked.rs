@@ -0,0 +1,53 @@
+mod common;
+
+use common::{document_root, header, send, send_bytes, start_server};
+
+// Reassembles a chunked body, checking the framing along the way.
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n").expect("chunk size line");
+        let size = usize::from_str_radix(std::str::from_utf8(&body[..line_end]).unwrap(), 16).unwrap();
+        body = &body[line_end + 2..];
+        if size == 0 {
+            assert_eq!(body, b"\r\n", "missing end of the chunked body");
+            return data;
+        }
+        data.extend_from_slice(&body[..size]);
+        assert_eq!(&body[size..size + 2], b"\r\n");
+        body = &body[size + 2..];
+    }
+}
+
+fn split_response(response: &[u8]) -> (String, &[u8]) {
+    let head_end = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    (String::from_utf8_lossy(&response[..head_end]).to_string(), &response[head_end..])
+}
+
+#[test]
+fn archive_is_sent_chunked_to_http_1_1_clients() {
+    let root = document_root("chunked-archive");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = send_bytes(&server.addr, "GET /?download=tar.gz HTTP/1.1\r\nHost: x\r\n\r\n");
+    let (head, body) = split_response(&response);
+    assert_eq!(header(&head, "Transfer-Encoding"), Some("chunked"));
+    assert_eq!(header(&head, "Content-Length"), None);
+    let archive = dechunk(body);
+    // gzip magic number.
+    assert_eq!(&archive[..2], &[0x1f, 0x8b]);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn http_1_0_clients_get_a_close_delimited_archive() {
+    let root = document_root("chunked-http10");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = send(&server.addr, "GET /?download=tar.gz HTTP/1.0\r\n\r\n");
+    assert_eq!(header(&response, "Transfer-Encoding"), None);
+    assert_eq!(header(&response, "Connection"), Some("close"));
+    let _ = std::fs::remove_dir_all(&root);
+}
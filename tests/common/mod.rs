@@ -0,0 +1,95 @@
+// Helpers shared by the integration tests. Not every test file uses all of
+// them.
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+pub struct Server {
+    child: Child,
+    pub addr: String,
+}
+
+impl Server {
+    #[cfg(unix)]
+    pub fn signal(&self, signal: i32) {
+        unsafe { libc::kill(self.child.id() as i32, signal) };
+    }
+
+    pub fn wait(&mut self) -> std::process::ExitStatus {
+        self.child.wait().unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Starts the server on an ephemeral port and waits for its `LISTENING` line.
+// `addr` is the first listener's `host:port`, or `unix:PATH` for a socket.
+pub fn start_server(args: &[&str]) -> Server {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--port", "0"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut addr = None;
+    let mut line = String::new();
+    while stdout.read_line(&mut line).unwrap() > 0 {
+        if let Some(url) = line.trim().strip_prefix("LISTENING ") {
+            addr = Some(url.strip_prefix("http://").unwrap_or(url).to_string());
+            break;
+        }
+        line.clear();
+    }
+    // Keep draining so the server never blocks on a full pipe.
+    thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::sink()));
+    Server { child, addr: addr.expect("server exited before listening") }
+}
+
+// Sends `request` as is and returns everything the server answers with.
+pub fn send(addr: &str, request: &str) -> String {
+    String::from_utf8_lossy(&send_bytes(addr, request)).to_string()
+}
+
+// Like `send`, for responses with binary bodies.
+pub fn send_bytes(addr: &str, request: &str) -> Vec<u8> {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    response
+}
+
+pub fn get(addr: &str, target: &str) -> String {
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target))
+}
+
+// A fresh, empty directory under the system temp dir, for use as a document
+// root. Named after the test so parallel tests do not collide.
+pub fn document_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("gredl-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
+// Value of the response header `name`, if present.
+pub fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    response
+        .split("\r\n\r\n")
+        .next()?
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
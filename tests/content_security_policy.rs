@@ -0,0 +1,30 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn generated_pages_carry_the_default_policy() {
+    let root = document_root("csp-default");
+    std::fs::write(root.join("page.html"), "<p>mine</p>").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let policy = Some("default-src 'self'; style-src 'unsafe-inline'");
+    assert_eq!(header(&get(&server.addr, "/"), "Content-Security-Policy"), policy);
+    assert_eq!(header(&get(&server.addr, "/missing"), "Content-Security-Policy"), policy);
+    // Served files are the site's own business.
+    assert_eq!(header(&get(&server.addr, "/page.html?raw=1"), "Content-Security-Policy"), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn policy_is_configurable_and_can_be_turned_off() {
+    let root = document_root("csp-custom");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--csp", "default-src 'none'"]);
+    assert_eq!(header(&get(&server.addr, "/"), "Content-Security-Policy"), Some("default-src 'none'"));
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--csp", ""]);
+    let response = get(&server.addr, "/");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(header(&response, "Content-Security-Policy"), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{document_root, header, send, start_server};
+
+fn put_directory(addr: &str, target: &str) -> String {
+    send(addr, &format!("PUT {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n", target))
+}
+
+#[test]
+fn put_with_a_trailing_slash_creates_directories() {
+    let root = document_root("create-directory");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--allow-write"]);
+
+    // Missing parents are created along the way.
+    let response = put_directory(&server.addr, "/new/dir/path/");
+    assert!(response.starts_with("HTTP/1.1 201 Created\r\n"), "{}", response);
+    assert_eq!(header(&response, "Location"), Some("/new/dir/path/"));
+    assert!(root.join("new/dir/path").is_dir());
+
+    let response = put_directory(&server.addr, "/new/dir/");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    let response = put_directory(&server.addr, "/notes.txt/");
+    assert!(response.starts_with("HTTP/1.1 409 Conflict\r\n"), "{}", response);
+    assert_eq!(std::fs::read_to_string(root.join("notes.txt")).unwrap(), "some notes\n");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn creating_directories_needs_allow_write() {
+    let root = document_root("create-directory-refused");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = put_directory(&server.addr, "/new/");
+    assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{}", response);
+    assert!(!root.join("new").exists());
+    let response = send(&server.addr, "OPTIONS / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(header(&response, "Allow"), Some("GET, HEAD, OPTIONS"));
+    let _ = std::fs::remove_dir_all(&root);
+}
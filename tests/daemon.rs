@@ -0,0 +1,38 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, get};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn daemon_detaches_writes_a_locked_pid_file_and_removes_it_on_shutdown() {
+    let root = document_root("daemon");
+    let pid_file = root.join("gredl.pid");
+    let args = ["--port", "0", "--root", root.to_str().unwrap(), "--pid-file", pid_file.to_str().unwrap()];
+
+    // The starting process exits once the server is up; its output ends when
+    // the child lets go of the terminal.
+    let output = Command::new(env!("CARGO_BIN_EXE_gredl_server")).args(args).arg("--daemon").output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let addr = stdout.lines().find_map(|line| line.strip_prefix("LISTENING http://")).unwrap();
+    let pid: i32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+    assert!(get(addr, "/").starts_with("HTTP/1.1 200"));
+
+    let second = Command::new(env!("CARGO_BIN_EXE_gredl_server")).args(args).output().unwrap();
+    assert!(!second.status.success());
+    assert!(String::from_utf8_lossy(&second.stderr).contains("another instance is running"));
+
+    unsafe { libc::kill(pid, libc::SIGTERM) };
+    for _ in 0..50 {
+        if !pid_file.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert!(!pid_file.exists(), "pid file left behind after shutdown");
+    let _ = std::fs::remove_dir_all(&root);
+}
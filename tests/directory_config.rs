@@ -0,0 +1,59 @@
+mod common;
+
+use common::{document_root, get, send, send_bytes, start_server};
+
+#[test]
+fn gredl_toml_overrides_settings_for_its_subtree() {
+    let root = document_root("directory-config");
+    std::fs::create_dir_all(root.join("docs").join("api")).unwrap();
+    std::fs::create_dir_all(root.join("other")).unwrap();
+    for dir in ["docs/api", "other"] {
+        std::fs::write(root.join(dir).join("index.html"), "<h1>index</h1>").unwrap();
+        std::fs::write(root.join(dir).join(".hidden"), "").unwrap();
+        std::fs::write(root.join(dir).join("notes.md"), "notes").unwrap();
+    }
+    std::fs::write(root.join("docs").join(".gredl.toml"), "serve_index = false\nshow_hidden = true\n").unwrap();
+    std::fs::write(root.join("docs").join("api").join(".gredl.toml"), "denied_extensions = [\".md\"]\nread_only = true\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--allow-write"]);
+
+    // Both files apply below docs/api, the inner one last.
+    let listing = get(&server.addr, "/docs/api/");
+    assert!(listing.contains("📜 index.html") && listing.contains("📄 .hidden"), "{}", listing);
+    assert!(!listing.contains(".gredl.toml"));
+    assert!(get(&server.addr, "/docs/api/notes.md?raw=1").starts_with("HTTP/1.1 403"));
+    let put = send(&server.addr, "PUT /docs/api/new/ HTTP/1.1\r\nHost: x\r\nContent-Length: 0\r\n\r\n");
+    assert!(put.starts_with("HTTP/1.1 403"), "{}", put);
+    assert!(get(&server.addr, "/docs/.gredl.toml?raw=1").starts_with("HTTP/1.1 404"));
+
+    // Elsewhere the global settings stay.
+    assert!(get(&server.addr, "/other/").ends_with("<h1>index</h1>"));
+    assert!(get(&server.addr, "/other/notes.md?raw=1").ends_with("\r\n\r\nnotes"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn broken_gredl_toml_is_ignored() {
+    let root = document_root("directory-config-broken");
+    std::fs::write(root.join(".gredl.toml"), "serve_index = \"sometimes\"\n").unwrap();
+    std::fs::write(root.join("index.html"), "<h1>index</h1>").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    assert!(get(&server.addr, "/").ends_with("<h1>index</h1>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn gredl_toml_is_left_out_of_archives() {
+    let root = document_root("directory-config-archive");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("docs/.gredl.toml"), "show_hidden = true\n").unwrap();
+    std::fs::write(root.join("docs/notes.md"), "notes").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    // Entry names are stored uncompressed in a zip.
+    let zip = send_bytes(&server.addr, "GET /?download=zip HTTP/1.0\r\n\r\n");
+    let has = |name: &str| zip.windows(name.len()).any(|window| window == name.as_bytes());
+    assert!(has("docs/notes.md"));
+    assert!(!has(".gredl.toml"));
+    let _ = std::fs::remove_dir_all(&root);
+}
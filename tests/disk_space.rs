@@ -0,0 +1,20 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn listings_show_the_free_space_of_their_filesystem() {
+    let root = document_root("disk-space");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    let start = listing.find(r#"<p class="disk-space">"#).expect("no disk space line") + r#"<p class="disk-space">"#.len();
+    let line = &listing[start..start + listing[start..].find("</p>").unwrap()];
+    let (free, total) = line.split_once(" free of ").unwrap_or_else(|| panic!("{}", line));
+    for size in [free, total] {
+        assert!(size.ends_with('B') && size.starts_with(|c: char| c.is_ascii_digit()), "{}", line);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
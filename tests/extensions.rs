@@ -0,0 +1,54 @@
+mod common;
+
+use common::{document_root, get, send_bytes, start_server};
+
+#[test]
+fn denied_extensions_are_refused() {
+    let root = document_root("extensions-deny");
+    std::fs::write(root.join(".env"), "SECRET=1").unwrap();
+    std::fs::write(root.join("server.PEM"), "key").unwrap();
+    std::fs::write(root.join("notes.txt"), "notes").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", ".env,pem"]);
+
+    assert!(get(&server.addr, "/.env?raw=1").starts_with("HTTP/1.1 403"));
+    assert!(get(&server.addr, "/server.PEM?raw=1").starts_with("HTTP/1.1 403"));
+    assert!(get(&server.addr, "/server.PEM").starts_with("HTTP/1.1 403"));
+    assert!(get(&server.addr, "/notes.txt?raw=1").ends_with("\r\n\r\nnotes"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn only_allowed_extensions_are_served() {
+    let root = document_root("extensions-allow");
+    std::fs::write(root.join("index.html"), "<h1>hi</h1>").unwrap();
+    std::fs::write(root.join("backup.tar.gz"), "archive").unwrap();
+    std::fs::write(root.join("README"), "readme").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--allow-ext", ".html", "--allow-ext", ".tar.gz"]);
+
+    assert!(get(&server.addr, "/").ends_with("\r\n\r\n<h1>hi</h1>"));
+    assert!(get(&server.addr, "/backup.tar.gz?raw=1").ends_with("\r\n\r\narchive"));
+    assert!(get(&server.addr, "/README?raw=1").starts_with("HTTP/1.1 403"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn archives_leave_out_what_is_not_served() {
+    let root = document_root("extensions-archive");
+    std::fs::create_dir_all(root.join("sub/inner")).unwrap();
+    std::fs::write(root.join("sub/key.pem"), "key").unwrap();
+    std::fs::write(root.join("sub/notes.txt"), "notes").unwrap();
+    std::fs::write(root.join("sub/inner/.gredl.toml"), "denied_extensions = [\".log\"]\n").unwrap();
+    std::fs::write(root.join("sub/inner/debug.log"), "log").unwrap();
+    std::fs::write(root.join("sub/inner/todo.md"), "todo").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", ".pem"]);
+
+    assert!(get(&server.addr, "/sub/key.pem?raw=1").starts_with("HTTP/1.1 403"));
+    // Entry names are stored uncompressed in a zip.
+    let zip = send_bytes(&server.addr, "GET /sub/?download=zip HTTP/1.0\r\n\r\n");
+    let has = |name: &str| zip.windows(name.len()).any(|window| window == name.as_bytes());
+    assert!(has("notes.txt") && has("inner/todo.md"));
+    assert!(!has("key.pem"));
+    // The inner directory's own list replaces the global one.
+    assert!(!has("inner/debug.log"));
+    let _ = std::fs::remove_dir_all(&root);
+}
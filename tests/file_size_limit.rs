@@ -0,0 +1,35 @@
+mod common;
+
+use common::{document_root, get, send_bytes, start_server};
+
+#[test]
+fn files_over_the_limit_are_refused() {
+    let root = document_root("file-size-limit");
+    std::fs::write(root.join("dump.sql"), vec![b'x'; 4096]).unwrap();
+    std::fs::write(root.join("small.txt"), "small").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--max-file-size", "1024"]);
+
+    let response = get(&server.addr, "/dump.sql?raw=1");
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(response.contains("The requested file is 4 KiB, larger than the 1 KiB"), "{}", response);
+    assert!(get(&server.addr, "/small.txt?raw=1").ends_with("\r\n\r\nsmall"));
+    // The file's page still shows, only its contents are withheld.
+    assert!(get(&server.addr, "/dump.sql").starts_with("HTTP/1.1 200"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn archives_leave_out_files_over_the_limit() {
+    let root = document_root("file-size-limit-archive");
+    std::fs::create_dir(root.join("sub")).unwrap();
+    std::fs::write(root.join("sub/dump.sql"), vec![b'x'; 4096]).unwrap();
+    std::fs::write(root.join("sub/small.txt"), "small").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--max-file-size", "1024"]);
+
+    // Entry names are stored uncompressed in a zip.
+    let zip = send_bytes(&server.addr, "GET /sub/?download=zip HTTP/1.0\r\n\r\n");
+    let has = |name: &str| zip.windows(name.len()).any(|window| window == name.as_bytes());
+    assert!(has("small.txt"));
+    assert!(!has("dump.sql"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn listing_is_narrowed_to_names_matching_the_filter() {
+    let root = document_root("filter");
+    for name in ["app.log", "APP.LOG", "db.log.1", "notes.txt", "a1.txt", "b2.txt"] {
+        std::fs::write(root.join(name), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/?filter=*.log&sort=size");
+    assert!(listing.contains("📄 app.log") && !listing.contains("APP.LOG") && !listing.contains("db.log.1"), "{}", listing);
+    assert!(listing.contains("<p>1 entries</p>"));
+    assert!(listing.contains(r#"<code>*.log</code> (<a href="?sort=size">show all</a>)"#), "{}", listing);
+
+    let listing = get(&server.addr, "/?filter=*.log&ci=1");
+    assert!(listing.contains("<p>2 entries</p>"), "{}", listing);
+    assert!(listing.contains("ignoring case"));
+
+    let listing = get(&server.addr, "/?filter=%5B!a%5D%3F.txt");
+    assert!(listing.contains("📄 b2.txt") && !listing.contains("a1.txt"), "{}", listing);
+    assert!(listing.contains("<p>1 entries</p>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn invalid_filter_is_reported_on_the_page() {
+    let root = document_root("filter-invalid");
+    std::fs::write(root.join("notes.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/?filter=%5B%3Cb%3E");
+    assert!(listing.starts_with("HTTP/1.1 200"), "{}", listing);
+    assert!(listing.contains("Invalid filter <code>[&lt;b&gt;</code>"), "{}", listing);
+    assert!(listing.contains("📄 notes.txt"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn grid_view_previews_images_and_shows_icons_for_the_rest() {
+    let root = document_root("grid-view");
+    std::fs::create_dir(root.join("albums")).unwrap();
+    std::fs::write(root.join("beach photo.jpg"), "").unwrap();
+    std::fs::write(root.join("scan.tiff"), "").unwrap();
+    std::fs::write(root.join("notes.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/albums/?view=grid");
+    assert!(listing.contains(r#"<div class="grid">"#) && !listing.contains("<table>"), "{}", listing);
+    assert!(listing.contains(r#"<a class="tile" href="/"><span class="preview">📁</span><span class="name">..</span></a>"#));
+    assert!(listing.contains(r#"<p class="empty">This directory is empty</p>"#));
+
+    let listing = get(&server.addr, "/?view=grid&sort=name&filter=*.*");
+    assert!(listing.contains(r#"<img src="/beach%20photo.jpg?raw=1" alt="" loading="lazy">"#), "{}", listing);
+    // Formats browsers do not show get their icon.
+    assert!(listing.contains(r#"<span class="preview">🖼</span><span class="name">scan.tiff</span>"#), "{}", listing);
+    assert!(listing.contains(r#"<span class="preview">📄</span><span class="name">notes.txt</span>"#));
+    assert!(listing.contains(r#"<a href="?sort=name&filter=*.*&view=table">Table view</a>"#), "{}", listing);
+
+    let listing = get(&server.addr, "/?sort=size");
+    assert!(listing.contains("<table>") && !listing.contains(r#"class="tile""#));
+    assert!(listing.contains(r#"<a href="?sort=size&view=grid">Grid view</a>"#), "{}", listing);
+    let _ = std::fs::remove_dir_all(&root);
+}
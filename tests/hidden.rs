@@ -0,0 +1,41 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn dotfiles_are_listed_only_on_request_but_always_served() {
+    let root = document_root("hidden");
+    std::fs::write(root.join(".env"), "SECRET=1").unwrap();
+    std::fs::write(root.join("visible.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/?sort=size");
+    assert!(!listing.contains(".env"), "{}", listing);
+    assert!(listing.contains("<p>1 entries</p>"));
+    assert!(listing.contains(r#"<a href="?sort=size&hidden=1">Show hidden files</a>"#), "{}", listing);
+
+    let listing = get(&server.addr, "/?sort=size&hidden=1");
+    assert!(listing.contains("📄 .env"), "{}", listing);
+    assert!(listing.contains(r#"<a href="?sort=size&hidden=0">Hide hidden files</a>"#));
+    assert!(get(&server.addr, "/.env?raw=1").ends_with("\r\n\r\nSECRET=1"));
+    drop(server);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--show-hidden"]);
+    assert!(get(&server.addr, "/").contains("📄 .env"));
+    assert!(!get(&server.addr, "/?hidden=0").contains(".env"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+// Hiding dotfiles must not get in the way of ACME HTTP-01 validation.
+#[test]
+fn acme_challenges_are_served_while_dotfiles_are_hidden() {
+    let root = document_root("hidden-acme");
+    let challenges = root.join(".well-known").join("acme-challenge");
+    std::fs::create_dir_all(&challenges).unwrap();
+    std::fs::write(challenges.join("token"), "token.thumbprint").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    assert!(!get(&server.addr, "/").contains(".well-known"));
+    assert!(get(&server.addr, "/.well-known/acme-challenge/token?raw=1").ends_with("\r\n\r\ntoken.thumbprint"));
+    let _ = std::fs::remove_dir_all(&root);
+}
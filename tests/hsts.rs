@@ -0,0 +1,17 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+// The server only speaks plain HTTP, where Strict-Transport-Security has no
+// meaning; a TLS proxy in front is the one to send it.
+#[test]
+fn plain_http_responses_carry_no_hsts_header() {
+    let root = document_root("hsts");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for target in ["/", "/notes.txt?raw=1", "/missing"] {
+        assert_eq!(header(&get(&server.addr, target), "Strict-Transport-Security"), None, "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn files_get_the_icon_of_their_extension_group() {
+    let root = document_root("icons");
+    for name in ["Photo.JPG", "song.flac", "clip.mkv", "backup.tar.gz", "main.rs", "manual.pdf", "notes.txt", "Makefile"] {
+        std::fs::write(root.join(name), "").unwrap();
+    }
+    std::fs::create_dir(root.join("docs.pdf")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    for row in ["🖼 Photo.JPG", "🎵 song.flac", "🎬 clip.mkv", "🗜 backup.tar.gz", "📜 main.rs", "📕 manual.pdf", "📄 notes.txt", "📄 Makefile", "📁 docs.pdf"] {
+        assert!(listing.contains(row), "{}: {}", row, listing);
+    }
+    assert!(get(&server.addr, "/manual.pdf").contains("<h2>📕 manual.pdf</h2>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn executables_and_links_get_their_own_markers() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let root = document_root("icons-unix");
+    std::fs::write(root.join("run"), "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(root.join("run"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    symlink("run", root.join("start")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains("⚙ run"), "{}", listing);
+    assert!(listing.contains("🔗 start"), "{}", listing);
+    assert!(get(&server.addr, "/run").contains("<h2>⚙ run</h2>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+const PAGE: &str = "<h1>Welcome</h1>\n";
+
+#[test]
+fn directory_with_index_html_serves_it() {
+    let root = document_root("index");
+    std::fs::create_dir(root.join("site")).unwrap();
+    std::fs::write(root.join("site").join("index.html"), PAGE).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/site/");
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("text/html; charset=utf-8"));
+    assert!(response.ends_with(PAGE));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn no_index_lists_the_directory() {
+    let root = document_root("no-index");
+    std::fs::write(root.join("index.html"), PAGE).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--no-index"]);
+
+    let response = get(&server.addr, "/");
+    assert!(response.contains("index.html</a>"), "unexpected response: {}", response);
+    assert!(!response.ends_with(PAGE));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn index_names_are_tried_in_order() {
+    let root = document_root("index-order");
+    std::fs::write(root.join("README.md"), "# Readme\n").unwrap();
+    std::fs::write(root.join("index.htm"), PAGE).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--index", "default.asp", "--index", "index.htm", "--index", "README.md"]);
+
+    assert!(get(&server.addr, "/").ends_with(PAGE));
+    std::fs::remove_file(root.join("index.htm")).unwrap();
+    assert!(get(&server.addr, "/").ends_with("# Readme\n"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn directory_is_listed() {
+    let root = document_root("integration-listing");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(header(&response, "Content-Type").unwrap().starts_with("text/html"));
+    assert!(response.contains(r#"href="/docs/""#) && response.contains(r#"href="/notes.txt""#), "{}", response);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn file_is_downloaded() {
+    let root = document_root("integration-download");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/notes.txt?raw=1");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert_eq!(header(&response, "Content-Length"), Some("11"));
+    assert!(header(&response, "Content-Type").unwrap().starts_with("text/plain"));
+    assert!(response.ends_with("\r\n\r\nsome notes\n"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn missing_path_is_not_found() {
+    let root = document_root("integration-missing");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    assert!(get(&server.addr, "/nowhere.txt").starts_with("HTTP/1.1 404"));
+    assert!(get(&server.addr, "/nowhere/").starts_with("HTTP/1.1 404"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn paths_cannot_leave_the_root() {
+    let parent = document_root("integration-traversal");
+    let root = parent.join("root");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(parent.join("secret.txt"), "secret\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for target in ["/../secret.txt?raw=1", "/%2e%2e/secret.txt?raw=1", "/a/../../secret.txt?raw=1", "/..%2fsecret.txt?raw=1"] {
+        let response = get(&server.addr, target);
+        assert!(!response.contains("secret\n"), "{} escaped the root: {}", target, response);
+        assert!(!response.starts_with("HTTP/1.1 200"), "{}: {}", target, response);
+    }
+    let _ = std::fs::remove_dir_all(&parent);
+}
+
+#[test]
+fn directory_without_trailing_slash_redirects() {
+    let root = document_root("integration-redirect");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/docs?sort=size");
+    assert!(response.starts_with("HTTP/1.1 301"), "{}", response);
+    assert_eq!(header(&response, "Location"), Some("/docs/?sort=size"));
+    let _ = std::fs::remove_dir_all(&root);
+}
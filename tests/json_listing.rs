@@ -0,0 +1,101 @@
+mod common;
+
+use common::{document_root, header, send, start_server};
+
+fn get_with_accept(addr: &str, target: &str, accept: &str) -> String {
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\nAccept: {}\r\n\r\n", target, accept))
+}
+
+fn body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+// Every key of `value` and the JSON type of its value, so that a change to
+// the document's shape fails here.
+fn shape(value: &serde_json::Value) -> Vec<(String, &'static str)> {
+    let object = value.as_object().unwrap();
+    let mut shape: Vec<_> = object
+        .iter()
+        .map(|(key, value)| {
+            let kind = match value {
+                serde_json::Value::Null => "null",
+                serde_json::Value::Bool(_) => "boolean",
+                serde_json::Value::Number(_) => "number",
+                serde_json::Value::String(_) => "string",
+                serde_json::Value::Array(_) => "array",
+                serde_json::Value::Object(_) => "object",
+            };
+            (key.clone(), kind)
+        })
+        .collect();
+    shape.sort();
+    shape
+}
+
+#[test]
+fn listing_follows_the_documented_schema() {
+    let root = document_root("json-listing");
+    std::fs::create_dir_all(root.join("docs").join("old drafts")).unwrap();
+    std::fs::write(root.join("docs").join("read me.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get_with_accept(&server.addr, "/docs/", "application/json");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    assert_eq!(header(&response, "Vary"), Some("Accept"));
+    let listing = body(&response);
+    let owned = |shape: &[(&str, &'static str)]| shape.iter().map(|(key, kind)| (key.to_string(), *kind)).collect::<Vec<_>>();
+    assert_eq!(
+        shape(&listing),
+        owned(&[("entries", "array"), ("parent", "string"), ("path", "string"), ("summary", "object"), ("truncated", "boolean")])
+    );
+    assert_eq!(shape(&listing["summary"]), owned(&[("bytes", "number"), ("directories", "number"), ("files", "number")]));
+    assert_eq!(listing["path"], "/docs/");
+    assert_eq!(listing["parent"], "/");
+
+    let entries = listing["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        shape(&entries[0]),
+        owned(&[("href", "string"), ("mtime", "string"), ("name", "string"), ("size", "null"), ("type", "string")])
+    );
+    assert_eq!(entries[0]["name"], "old drafts");
+    assert_eq!(entries[0]["type"], "directory");
+    assert_eq!(entries[0]["href"], "/docs/old%20drafts/");
+    assert_eq!(entries[1]["name"], "read me.txt");
+    assert_eq!(entries[1]["type"], "file");
+    assert_eq!(entries[1]["size"], 6);
+    assert_eq!(entries[1]["href"], "/docs/read%20me.txt");
+    let mtime = entries[1]["mtime"].as_str().unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(mtime).is_ok(), "{}", mtime);
+
+    let root_listing = body(&get_with_accept(&server.addr, "/", "application/json"));
+    assert_eq!(root_listing["parent"], serde_json::Value::Null);
+    assert_eq!(root_listing["path"], "/");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn html_stays_the_default_unless_json_ranks_higher() {
+    let root = document_root("json-negotiation");
+    std::fs::write(root.join("notes.txt"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let is_json = |accept: &str| {
+        let response = get_with_accept(&server.addr, "/", accept);
+        assert_eq!(header(&response, "Vary"), Some("Accept"));
+        header(&response, "Content-Type") == Some("application/json")
+    };
+    assert!(is_json("application/json"));
+    assert!(is_json("application/json, text/html;q=0.9"));
+    assert!(is_json("application/*, text/*;q=0.5"));
+    assert!(!is_json("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"));
+    assert!(!is_json("*/*"));
+    assert!(!is_json("application/json;q=0.5, text/html"));
+    assert!(!is_json("text/html;q=0.5, application/json;q=0.5"));
+
+    // Files are sent as they are whatever the client prefers.
+    let response = get_with_accept(&server.addr, "/notes.txt?raw=1", "application/json");
+    assert_eq!(header(&response, "Content-Type"), Some("text/plain; charset=utf-8"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::{document_root, get, start_server};
+use std::collections::HashMap;
+
+#[test]
+fn every_entry_is_listed_exactly_once() {
+    let root = document_root("listing-entries");
+    for i in 0..3000 {
+        std::fs::write(root.join(format!("file-{}.txt", i)), "x".repeat(i % 7)).unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for row in listing.split("<td><a href=").skip(1) {
+        let name = row.split("</a>").next().unwrap().rsplit(' ').next().unwrap();
+        *seen.entry(name).or_default() += 1;
+    }
+    assert_eq!(seen.len(), 3000);
+    assert!(seen.values().all(|&count| count == 1));
+    assert!((0..3000).all(|i| seen.contains_key(format!("file-{}.txt", i).as_str())));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn footer_counts_the_entries_shown() {
+    let root = document_root("listing-summary");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::create_dir(root.join("empty")).unwrap();
+    std::fs::write(root.join("a.txt"), "hello").unwrap();
+    std::fs::write(root.join("b.log"), "abc").unwrap();
+    std::fs::write(root.join(".hidden"), "0123456789").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains(r#"<p class="summary">2 directories, 2 files, 8 B total</p>"#), "{}", listing);
+    assert!(!listing.contains("This directory is empty"));
+
+    let listing = get(&server.addr, "/?filter=*.txt");
+    assert!(listing.contains(r#"<p class="summary">0 directories, 1 file, 5 B total</p>"#), "{}", listing);
+
+    let listing = get(&server.addr, "/?hidden=1&sort=name&per_page=1");
+    assert!(listing.contains(r#"<p class="summary">1 directory, 0 files, 0 B total</p>"#), "{}", listing);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn empty_directory_says_so() {
+    let root = document_root("listing-empty");
+    std::fs::create_dir(root.join("empty")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/empty/");
+    assert!(listing.contains(r#"<td colspan="3">This directory is empty</td>"#), "{}", listing);
+    assert!(listing.contains(r#"<p class="summary">0 directories, 0 files, 0 B total</p>"#), "{}", listing);
+    let _ = std::fs::remove_dir_all(&root);
+}
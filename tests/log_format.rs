@@ -0,0 +1,127 @@
+mod common;
+
+use common::{document_root, get};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+#[test]
+fn json_format_writes_access_lines_as_objects() {
+    let root = document_root("log-format-json");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--port", "0", "--root", root.to_str().unwrap(), "--verbose", "--log-format", "json"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut addr = None;
+    while stdout.read_line(&mut line).unwrap() > 0 {
+        match line.trim().strip_prefix("LISTENING http://") {
+            Some(url) => {
+                addr = Some(url.to_string());
+                break;
+            }
+            None => lines.push(line.clone()),
+        }
+        line.clear();
+    }
+    let addr = addr.expect("server exited before listening");
+    for startup in &lines {
+        let startup: serde_json::Value = serde_json::from_str(startup).unwrap();
+        assert_eq!(startup["kind"], "message");
+    }
+
+    assert!(get(&addr, "/?sort=name").starts_with("HTTP/1.1 200"));
+    let access = loop {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "no access line");
+        let object: serde_json::Value = serde_json::from_str(&line).unwrap();
+        if object["kind"] == "access" {
+            break object;
+        }
+    };
+    assert_eq!(access["level"], "info");
+    assert_eq!(access["method"], "GET");
+    assert_eq!(access["path"], "/?sort=name");
+    assert_eq!(access["status"], 200);
+    assert!(access["timestamp"].is_string());
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn rust_log_debug_shows_path_resolution() {
+    let root = document_root("log-format-debug");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--port", "0", "--root", root.to_str().unwrap()])
+        .env("RUST_LOG", "gredl_server=debug")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    let addr = loop {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "server exited before listening");
+        if let Some(url) = line.trim().strip_prefix("LISTENING http://") {
+            break url.to_string();
+        }
+    };
+
+    assert!(get(&addr, "/notes.txt?raw=1").ends_with("some notes\n"));
+    let resolved = format!("debug: /notes.txt resolves to {}", root.join("notes.txt").display());
+    loop {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "no path resolution logged");
+        if line.trim() == resolved {
+            break;
+        }
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+// Loopback addresses resolve to `localhost` without asking a name server.
+#[test]
+fn resolve_hostnames_names_clients_in_access_lines() {
+    let root = document_root("log-format-hostnames");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--port", "0", "--root", root.to_str().unwrap(), "--verbose", "--resolve-hostnames"])
+        .args(["--log-format", "json"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    let addr = loop {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "server exited before listening");
+        if let Some(url) = line.trim().strip_prefix("LISTENING http://") {
+            break url.to_string();
+        }
+    };
+
+    assert!(get(&addr, "/").starts_with("HTTP/1.1 200"));
+    let access = loop {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "no access line");
+        let object: serde_json::Value = serde_json::from_str(&line).unwrap();
+        if object["kind"] == "access" {
+            break object;
+        }
+    };
+    let peer = access["peer"].as_str().unwrap();
+    assert!(peer.strip_prefix("localhost:").is_some_and(|port| port.parse::<u16>().is_ok()), "peer {}", peer);
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{document_root, get, header, send, start_server};
+
+fn log_in(addr: &str, form: &str) -> String {
+    send(
+        addr,
+        &format!(
+            "POST /_login HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+            form.len(),
+            form
+        ),
+    )
+}
+
+fn get_with_cookie(addr: &str, target: &str, cookie: &str) -> String {
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\nCookie: theme=dark; {}\r\n\r\n", target, cookie))
+}
+
+#[test]
+fn the_login_form_hands_out_a_session_cookie() {
+    let root = document_root("login");
+    std::fs::create_dir(root.join("photos")).unwrap();
+    std::fs::create_dir(root.join("private")).unwrap();
+    std::fs::write(root.join("photos").join("cat.txt"), "meow\n").unwrap();
+    let accounts = document_root("login-users-file");
+    let users_file = accounts.join("users.toml");
+    std::fs::write(
+        &users_file,
+        format!("[alice]\npassword = \"{}\"\nroots = [\"/photos/\"]\n", bcrypt::hash("through the looking-glass", 4).unwrap()),
+    )
+    .unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--users-file", users_file.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/photos/");
+    assert!(response.starts_with("HTTP/1.1 401"), "{}", response);
+    assert!(response.contains(r#"<a href="/_login">Sign in</a>"#), "{}", response);
+    let response = get(&server.addr, "/_login");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains(r#"<form method="post" action="/_login">"#), "{}", response);
+
+    let response = log_in(&server.addr, "username=alice&password=wrong");
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert_eq!(header(&response, "Set-Cookie"), None);
+
+    let response = log_in(&server.addr, "username=alice&password=through+the+looking-glass");
+    assert!(response.starts_with("HTTP/1.1 303"), "{}", response);
+    assert_eq!(header(&response, "Location"), Some("/"));
+    let set_cookie = header(&response, "Set-Cookie").unwrap();
+    assert!(set_cookie.ends_with("; Path=/; Max-Age=43200; HttpOnly; SameSite=Lax"), "{}", set_cookie);
+    let cookie = set_cookie.split(';').next().unwrap();
+    assert!(cookie.starts_with("gredl_session="));
+
+    assert!(get_with_cookie(&server.addr, "/photos/cat.txt?raw=1", cookie).ends_with("\r\n\r\nmeow\n"));
+    // The account's roots still apply.
+    assert!(get_with_cookie(&server.addr, "/private/", cookie).starts_with("HTTP/1.1 403"));
+
+    // Any change to the token breaks its signature.
+    let forged = cookie.replacen("gredl_session=YWxpY2U", "gredl_session=YWRtaW4", 1);
+    assert_ne!(forged, cookie);
+    assert!(get_with_cookie(&server.addr, "/photos/cat.txt?raw=1", &forged).starts_with("HTTP/1.1 401"));
+    assert!(get_with_cookie(&server.addr, "/photos/cat.txt?raw=1", "gredl_session=nonsense").starts_with("HTTP/1.1 401"));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&accounts);
+}
+
+#[test]
+fn there_is_no_login_form_without_users() {
+    let root = document_root("login-without-users");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    assert!(get(&server.addr, "/_login").starts_with("HTTP/1.1 404"));
+    let response = log_in(&server.addr, "username=alice&password=wonderland");
+    assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+    assert_eq!(header(&response, "Set-Cookie"), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn request_latency_is_a_histogram_with_the_configured_buckets() {
+    let root = document_root("metrics");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--histogram-buckets", "0.5,1,5"]);
+
+    assert!(get(&server.addr, "/").starts_with("HTTP/1.1 200"));
+    assert!(get(&server.addr, "/missing").starts_with("HTTP/1.1 404"));
+    let metrics = get(&server.addr, "/_metrics");
+    assert!(header(&metrics, "Content-Type").unwrap().starts_with("text/plain; version=0.0.4"));
+    assert!(metrics.contains("# TYPE gredl_request_duration_seconds histogram\n"), "{}", metrics);
+    let buckets: Vec<&str> = metrics.lines().filter(|line| line.starts_with("gredl_request_duration_seconds_bucket")).collect();
+    assert_eq!(
+        buckets,
+        [
+            r#"gredl_request_duration_seconds_bucket{le="0.5"} 2"#,
+            r#"gredl_request_duration_seconds_bucket{le="1"} 2"#,
+            r#"gredl_request_duration_seconds_bucket{le="5"} 2"#,
+            r#"gredl_request_duration_seconds_bucket{le="+Inf"} 2"#,
+        ]
+    );
+    assert!(metrics.contains("gredl_request_duration_seconds_count 2\n"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn configured_types_extend_and_override_the_built_in_table() {
+    let root = document_root("mime");
+    std::fs::write(root.join("part.gcode"), "G28").unwrap();
+    std::fs::write(root.join("notes.TXT"), "notes").unwrap();
+    std::fs::write(root.join("page.html"), "<p>").unwrap();
+    let server = start_server(&[
+        "--root",
+        root.to_str().unwrap(),
+        "--mime",
+        ".gcode=text/x-gcode",
+        "--mime",
+        "txt=text/plain; charset=iso-8859-1",
+    ]);
+
+    let content_type = |target: &str| header(&get(&server.addr, target), "Content-Type").map(str::to_string);
+    assert_eq!(content_type("/part.gcode?raw=1").as_deref(), Some("text/x-gcode"));
+    assert_eq!(content_type("/notes.TXT?raw=1").as_deref(), Some("text/plain; charset=iso-8859-1"));
+    assert_eq!(content_type("/page.html?raw=1").as_deref(), Some("text/html; charset=utf-8"));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,30 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, get, start_server};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+#[test]
+fn names_that_are_not_utf8_are_listed_and_linked_by_their_bytes() {
+    let root = document_root("non-utf8-names");
+    let dir = root.join(OsStr::from_bytes(b"d\xe9p\xf4t"));
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(dir.join(OsStr::from_bytes(b"caf\xe9.txt")), "latin-1 name\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains(r#"href="/d%E9p%F4t/""#), "{}", listing);
+    assert!(listing.contains("d\u{fffd}p\u{fffd}t"));
+
+    let listing = get(&server.addr, "/d%E9p%F4t/");
+    assert!(listing.starts_with("HTTP/1.1 200"), "{}", listing);
+    assert!(listing.contains(r#"href="/d%E9p%F4t/caf%E9.txt""#), "{}", listing);
+    assert!(listing.contains("caf\u{fffd}.txt"));
+
+    assert!(get(&server.addr, "/d%E9p%F4t/caf%E9.txt?raw=1").ends_with("latin-1 name\n"));
+    // The replacement character's own encoding names a different file.
+    assert!(get(&server.addr, "/d%E9p%F4t/caf%EF%BF%BD.txt?raw=1").starts_with("HTTP/1.1 404"));
+    let _ = std::fs::remove_dir_all(&root);
+}
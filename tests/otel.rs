@@ -0,0 +1,36 @@
+mod common;
+
+use common::{document_root, send, start_server};
+use std::process::Command;
+
+#[test]
+fn requests_are_served_while_traces_are_exported() {
+    let root = document_root("otel");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    // Nothing listens there; failed exports must not get in the way.
+    let collector = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let endpoint = format!("http://{}", collector);
+    let server = start_server(&["--root", root.to_str().unwrap(), "--otel-endpoint", &endpoint]);
+
+    let response = send(
+        &server.addr,
+        "GET /notes.txt?raw=1 HTTP/1.1\r\nHost: x\r\n\
+        traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\n\
+        tracestate: vendor=value\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("some notes\n"));
+    let response = send(&server.addr, "GET / HTTP/1.1\r\nHost: x\r\ntraceparent: garbage\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn endpoints_must_be_plain_http() {
+    let output = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--port", "0", "--otel-endpoint", "https://collector:4317"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("otel_endpoint must be an http:// URL"));
+}
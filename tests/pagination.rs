@@ -0,0 +1,40 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn listing_is_split_into_pages_in_sorted_order() {
+    let root = document_root("pagination");
+    for i in 1..=25 {
+        std::fs::write(root.join(format!("file{}.txt", i)), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let page = get(&server.addr, "/?page=2&per_page=10");
+    assert!(page.contains("<p>25 entries, page 2 of 3</p>"), "{}", page);
+    assert!(page.contains("📄 file11.txt") && page.contains("📄 file20.txt"));
+    assert!(!page.contains("📄 file10.txt") && !page.contains("📄 file21.txt"));
+    assert!(page.contains(r#"<a href="?per_page=10&page=1">Previous</a> | <a href="?per_page=10&page=3">Next</a>"#));
+
+    // Past the end is the last page.
+    let page = get(&server.addr, "/?page=9&per_page=10");
+    assert!(page.starts_with("HTTP/1.1 200"));
+    assert!(page.contains("<p>25 entries, page 3 of 3</p>"), "{}", page);
+    assert!(page.contains("📄 file25.txt") && !page.contains("📄 file20.txt"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn unsorted_pages_of_a_huge_directory_clamp_too() {
+    let root = document_root("pagination-huge");
+    // One more than is buffered and sorted.
+    for i in 0..10_001 {
+        std::fs::write(root.join(format!("{:05}", i)), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let page = get(&server.addr, "/?page=500&per_page=1000");
+    assert!(page.contains("<p>10001 entries, page 11 of 11</p>"), "{}", page);
+    assert_eq!(page.matches("<td><a href=").count(), 1);
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,41 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, get, start_server};
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn details_show_mode_owner_and_group() {
+    let root = document_root("permissions");
+    std::fs::create_dir(root.join("shared")).unwrap();
+    std::fs::set_permissions(root.join("shared"), std::fs::Permissions::from_mode(0o1777)).unwrap();
+    std::fs::write(root.join("secret.txt"), "shh\n").unwrap();
+    std::fs::set_permissions(root.join("secret.txt"), std::fs::Permissions::from_mode(0o640)).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(!listing.contains("<th>Permissions</th>"), "{}", listing);
+
+    let listing = get(&server.addr, "/?details=1");
+    assert!(listing.contains("<th>Permissions</th><th>Owner</th><th>Group</th>"), "{}", listing);
+    assert!(listing.contains("<td><code>-rw-r-----</code></td><td>"), "{}", listing);
+    assert!(listing.contains("<td><code>drwxrwxrwt</code></td><td>"), "{}", listing);
+
+    let info = get(&server.addr, "/secret.txt?details=1");
+    assert!(info.contains("<p>Permissions: <code>-rw-r-----</code> (0640)</p>"), "{}", info);
+    assert!(info.contains("<p>Owner: "));
+    assert!(!get(&server.addr, "/secret.txt").contains("Permissions:"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn show_permissions_makes_details_the_default() {
+    let root = document_root("permissions-default");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--show-permissions"]);
+
+    assert!(get(&server.addr, "/").contains("<th>Permissions</th>"));
+    assert!(!get(&server.addr, "/?details=0").contains("<th>Permissions</th>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
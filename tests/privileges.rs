@@ -0,0 +1,52 @@
+//! Needs root: run with `sudo cargo test --features privdrop-test --test privileges`.
+// Reads the effective uid from /proc, hence Linux rather than any unix.
+#![cfg(all(target_os = "linux", feature = "privdrop-test"))]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::Duration;
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn get(path: &str) -> String {
+    let mut stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn drops_to_unprivileged_user_after_binding() {
+    assert_eq!(unsafe { libc::geteuid() }, 0, "this test must run as root");
+
+    let server = Server(
+        Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+            .args(["--root", "/", "--user", "nobody"])
+            .spawn()
+            .unwrap(),
+    );
+    for _ in 0..50 {
+        if TcpStream::connect("127.0.0.1:8080").is_ok() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let status = std::fs::read_to_string(format!("/proc/{}/status", server.0.id())).unwrap();
+    let uids = status.lines().find(|line| line.starts_with("Uid:")).unwrap();
+    let effective_uid: u32 = uids.split_whitespace().nth(2).unwrap().parse().unwrap();
+    assert_ne!(effective_uid, 0, "server still runs as root: {}", uids);
+
+    let response = get("/root/");
+    assert!(response.starts_with("HTTP/1.1 403"), "unexpected response: {}", response);
+}
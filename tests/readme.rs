@@ -0,0 +1,39 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn markdown_readme_is_rendered_and_sanitized_beneath_the_listing() {
+    let root = document_root("readme-markdown");
+    let readme = "# Project\n\nSome *notes*.\n\n<script>alert(1)</script>\n\n<img src=x onerror=alert(2)>\n\n[link](javascript:alert(3))\n";
+    std::fs::write(root.join("README.md"), readme).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    let panel = &listing[listing.find(r#"<div class="readme">"#).expect("README panel")..];
+    assert!(panel.contains("<h2>README.md</h2>") && panel.contains("<h1>Project</h1>"), "{}", panel);
+    assert!(panel.contains("<em>notes</em>"));
+    assert!(!panel.contains("<script") && !panel.contains("onerror") && !panel.contains("javascript:"), "{}", panel);
+    // Still listed, and still downloadable as written.
+    assert!(listing.contains(r#"href="/README.md""#));
+    assert!(get(&server.addr, "/README.md?raw=1").ends_with(readme));
+    drop(server);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--hide-readme"]);
+    assert!(!get(&server.addr, "/").contains(r#"<div class="readme">"#));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn text_readme_is_escaped_and_large_ones_are_left_out() {
+    let root = document_root("readme-text");
+    std::fs::create_dir(root.join("big")).unwrap();
+    std::fs::write(root.join("README.txt"), "a <b>bold</b> & plain claim\n").unwrap();
+    std::fs::write(root.join("big").join("README.md"), "x".repeat(256 * 1024 + 1)).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains("<pre>a &lt;b&gt;bold&lt;/b&gt; &amp; plain claim\n</pre>"), "{}", listing);
+    assert!(!get(&server.addr, "/big/").contains(r#"<div class="readme">"#));
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+// The Size cell of the row for `name`.
+fn size_cell<'a>(listing: &'a str, name: &str) -> &'a str {
+    let row = &listing[listing.find(&format!("{}</a>", name)).unwrap_or_else(|| panic!("no row {}", name))..];
+    let row = &row[..row.find("</tr>").unwrap()];
+    let cell = row.find("<td").unwrap();
+    row[cell..].lines().next().unwrap().trim()
+}
+
+#[test]
+fn subdirectories_can_show_their_total_size() {
+    let root = document_root("recursive-sizes");
+    let docs = root.join("docs");
+    std::fs::create_dir_all(docs.join("deeper")).unwrap();
+    std::fs::write(docs.join("a"), vec![0; 1000]).unwrap();
+    std::fs::write(docs.join("b"), vec![0; 500]).unwrap();
+    std::fs::write(docs.join("deeper").join("c"), vec![0; 250]).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--recursive-size-depth", "2"]);
+
+    assert_eq!(size_cell(&get(&server.addr, "/"), "docs"), "<td>3 items</td>");
+    let listing = get(&server.addr, "/?du=1");
+    assert_eq!(size_cell(&listing, "docs"), r#"<td title="1,750 bytes">1.71 KiB</td>"#);
+
+    // One level short of the file in deeper/.
+    let shallow = start_server(&["--root", root.to_str().unwrap(), "--recursive-sizes", "--recursive-size-depth", "1"]);
+    let listing = get(&shallow.addr, "/");
+    assert_eq!(size_cell(&listing, "docs"), r#"<td title="at least 1,500 bytes">≥ 1.46 KiB</td>"#);
+    assert_eq!(size_cell(&get(&shallow.addr, "/?du=0"), "docs"), "<td>3 items</td>");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn walks_do_not_leave_the_root_through_links() {
+    let root = document_root("recursive-sizes-links");
+    let outside = document_root("recursive-sizes-outside");
+    std::fs::write(outside.join("secret"), vec![0; 4096]).unwrap();
+    std::fs::create_dir(root.join("inside")).unwrap();
+    std::fs::write(root.join("inside").join("file"), vec![0; 10]).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("inside").join("escape")).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("away")).unwrap();
+    std::os::unix::fs::symlink(root.join("inside"), root.join("alias")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--recursive-sizes"]);
+
+    let listing = get(&server.addr, "/");
+    assert_eq!(size_cell(&listing, "inside"), r#"<td title="10 bytes">10 B</td>"#);
+    assert_eq!(size_cell(&listing, "alias"), r#"<td title="10 bytes">10 B</td>"#);
+    assert_eq!(size_cell(&listing, "away"), "<td>-</td>");
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&outside);
+}
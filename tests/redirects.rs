@@ -0,0 +1,33 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn directory_without_trailing_slash_is_redirected() {
+    let root = document_root("redirects");
+    std::fs::create_dir_all(root.join("some").join("dir")).unwrap();
+    std::fs::write(root.join("file.txt"), "hello\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/some/dir");
+    assert!(response.starts_with("HTTP/1.1 301"), "unexpected response: {}", response);
+    assert_eq!(header(&response, "Location"), Some("/some/dir/"));
+
+    let response = get(&server.addr, "/some/dir?page=2");
+    assert_eq!(header(&response, "Location"), Some("/some/dir/?page=2"));
+
+    assert!(get(&server.addr, "/some/dir/").starts_with("HTTP/1.1 200"));
+    assert!(get(&server.addr, "/file.txt").starts_with("HTTP/1.1 200"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn redirect_stays_under_the_base_url() {
+    let root = document_root("redirects-base-url");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--base-url", "/files"]);
+
+    assert_eq!(header(&get(&server.addr, "/files"), "Location"), Some("/files/"));
+    assert_eq!(header(&get(&server.addr, "/files/docs"), "Location"), Some("/files/docs/"));
+    let _ = std::fs::remove_dir_all(&root);
+}
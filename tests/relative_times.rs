@@ -0,0 +1,62 @@
+mod common;
+
+use common::{document_root, get, start_server};
+use std::time::{Duration, SystemTime};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+// The Modified cell of the row for `name`.
+fn modified_cell<'a>(listing: &'a str, name: &str) -> &'a str {
+    let row = &listing[listing.find(&format!("{}</a>", name)).unwrap_or_else(|| panic!("no row {}", name))..];
+    let row = &row[..row.find("</tr>").unwrap()];
+    let cell = row.match_indices("<td").nth(1).unwrap().0;
+    row[cell..].lines().next().unwrap().trim()
+}
+
+#[test]
+fn modification_times_can_read_as_how_long_ago() {
+    let root = document_root("relative-times");
+    let now = SystemTime::now();
+    // Far enough from each boundary that the time the test takes does not
+    // matter; src/relative_time.rs tests the boundaries themselves.
+    let files: &[(&str, SystemTime, &str)] = &[
+        ("fresh", now, "just now"),
+        ("minute", now - Duration::from_secs(90), "1 minute ago"),
+        ("hours", now - Duration::from_secs(3 * HOUR + 60), "3 hours ago"),
+        ("yesterday", now - Duration::from_secs(DAY + HOUR), "yesterday"),
+        ("days", now - Duration::from_secs(29 * DAY), "29 days ago"),
+        ("last-month", now - Duration::from_secs(45 * DAY), "last month"),
+        ("months", now - Duration::from_secs(200 * DAY), "6 months ago"),
+        ("last-year", now - Duration::from_secs(400 * DAY), "last year"),
+        ("years", now - Duration::from_secs(3 * 366 * DAY), "3 years ago"),
+        ("skewed", now + Duration::from_secs(30), "just now"),
+        ("future", now + Duration::from_secs(2 * HOUR), "in the future"),
+    ];
+    for (name, modified, _) in files {
+        let file = std::fs::File::create(root.join(name)).unwrap();
+        file.set_modified(*modified).unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/?times=relative");
+    for (name, _, expected) in files {
+        let cell = modified_cell(&listing, name);
+        assert!(cell.starts_with(r#"<td title=""#) && cell.ends_with(&format!(">{}</td>", expected)), "{}: {}", name, cell);
+    }
+
+    let listing = get(&server.addr, "/");
+    assert!(!modified_cell(&listing, "hours").contains("ago"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn relative_times_can_be_the_default() {
+    let root = document_root("relative-times-default");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--relative-times"]);
+
+    assert!(modified_cell(&get(&server.addr, "/"), "notes.txt").ends_with(">just now</td>"));
+    assert!(!get(&server.addr, "/?times=absolute").contains("just now"));
+    let _ = std::fs::remove_dir_all(&root);
+}
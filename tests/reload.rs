@@ -0,0 +1,70 @@
+#![cfg(unix)]
+
+mod common;
+
+use base64::Engine;
+use common::{document_root, header, send, start_server};
+use std::thread;
+use std::time::Duration;
+
+const ORIGIN: &str = "https://app.example";
+
+fn allowed_origin(addr: &str) -> Option<String> {
+    let response = send(addr, &format!("GET / HTTP/1.1\r\nHost: x\r\nOrigin: {}\r\n\r\n", ORIGIN));
+    header(&response, "Access-Control-Allow-Origin").map(str::to_string)
+}
+
+#[test]
+fn sighup_applies_a_valid_configuration_and_keeps_the_old_one_otherwise() {
+    let root = document_root("reload");
+    let config = root.join("gredl.toml");
+    std::fs::write(&config, "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--config", config.to_str().unwrap()]);
+    assert_eq!(allowed_origin(&server.addr), None);
+
+    std::fs::write(&config, format!("cors_origins = [\"{}\"]\n", ORIGIN)).unwrap();
+    server.signal(libc::SIGHUP);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(allowed_origin(&server.addr).as_deref(), Some(ORIGIN));
+
+    // Credentials with a wildcard origin fail validation, so nothing changes.
+    std::fs::write(&config, "cors_origins = [\"*\"]\ncors_credentials = true\n").unwrap();
+    server.signal(libc::SIGHUP);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(allowed_origin(&server.addr).as_deref(), Some(ORIGIN));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+fn status_as(addr: &str, user: &str, password: &str) -> String {
+    let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+    let response = send(addr, &format!("GET / HTTP/1.1\r\nHost: x\r\nAuthorization: Basic {}\r\n\r\n", credentials));
+    response.split("\r\n").next().unwrap().to_string()
+}
+
+#[test]
+fn sighup_reads_the_users_file_again() {
+    let root = document_root("reload-users");
+    let accounts = document_root("reload-users-file");
+    let users_file = accounts.join("users.toml");
+    let account = |name: &str, password: &str| format!("[{}]\npassword = \"{}\"\nroots = [\"/\"]\n", name, bcrypt::hash(password, 4).unwrap());
+    std::fs::write(&users_file, account("alice", "wonderland")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--users-file", users_file.to_str().unwrap()]);
+    assert_eq!(status_as(&server.addr, "alice", "wonderland"), "HTTP/1.1 200 OK");
+    assert_eq!(status_as(&server.addr, "bob", "builder"), "HTTP/1.1 401 Unauthorized");
+
+    std::fs::write(&users_file, account("bob", "builder")).unwrap();
+    server.signal(libc::SIGHUP);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(status_as(&server.addr, "bob", "builder"), "HTTP/1.1 200 OK");
+    assert_eq!(status_as(&server.addr, "alice", "wonderland"), "HTTP/1.1 401 Unauthorized");
+
+    // A file that does not load keeps the accounts there were.
+    std::fs::write(&users_file, "[carol]\npassword = \"plain text\"\nroots = [\"/\"]\n").unwrap();
+    server.signal(libc::SIGHUP);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(status_as(&server.addr, "bob", "builder"), "HTTP/1.1 200 OK");
+
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&accounts);
+}
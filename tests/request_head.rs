@@ -0,0 +1,22 @@
+mod common;
+
+use common::{send, start_server};
+
+#[test]
+fn malformed_heads_are_refused() {
+    let server = start_server(&[]);
+
+    for request in [
+        "GET / HTTP/1.1\r\nHost: localhost\nX-Smuggled: 1\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: localhost\rX-Smuggled: 1\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: localhost\r\nNo colon here\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost : localhost\r\n\r\n",
+        "GET / HTTP/1.1\r\n: localhost\r\n\r\n",
+        "GET / HTTP/1.1\r\nHo\u{1}st: localhost\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: localhost\r\nContent-Length: -5\r\n\r\n",
+    ] {
+        let response = send(&server.addr, request);
+        assert!(response.starts_with("HTTP/1.1 400"), "{:?}: {}", request, response);
+    }
+    assert!(send(&server.addr, "GET / HTTP/1.1\r\nHost: localhost\r\nX-Odd_Name!: 1\r\n\r\n").starts_with("HTTP/1.1 200"));
+}
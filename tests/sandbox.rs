@@ -0,0 +1,54 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{document_root, get, send, start_server};
+use std::os::unix::fs::symlink;
+
+fn status(response: &str) -> &str {
+    response.split("\r\n").next().unwrap()
+}
+
+#[test]
+fn webdav_writes_work_inside_the_sandbox() {
+    let root = document_root("sandbox-webdav");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--sandbox", "--webdav", "--allow-write"]);
+
+    let put = |target: &str, contents: &str| {
+        let request = format!("PUT {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", target, contents.len(), contents);
+        status(&send(&server.addr, &request)).to_string()
+    };
+    let request = |method: &str, target: &str, headers: &str| {
+        status(&send(&server.addr, &format!("{} {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", method, target, headers))).to_string()
+    };
+    assert_eq!(put("/_dav/new.txt", "new"), "HTTP/1.1 201 Created");
+    assert_eq!(put("/_dav/new.txt", "newer"), "HTTP/1.1 204 No Content");
+    assert_eq!(request("MKCOL", "/_dav/docs/", ""), "HTTP/1.1 201 Created");
+    assert_eq!(request("COPY", "/_dav/notes.txt", "Destination: /_dav/docs/copy.txt\r\n"), "HTTP/1.1 201 Created");
+    assert_eq!(request("MOVE", "/_dav/new.txt", "Destination: /_dav/docs/new.txt\r\n"), "HTTP/1.1 201 Created");
+    assert_eq!(request("DELETE", "/_dav/notes.txt", ""), "HTTP/1.1 204 No Content");
+    assert!(get(&server.addr, "/docs/new.txt?raw=1").ends_with("\r\n\r\nnewer"));
+    assert_eq!(request("DELETE", "/_dav/docs/", ""), "HTTP/1.1 204 No Content");
+    assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn the_sandbox_keeps_reads_inside_the_root() {
+    let root = document_root("sandbox-reads");
+    let outside = document_root("sandbox-reads-outside");
+    std::fs::write(outside.join("secret.txt"), "not for you\n").unwrap();
+    symlink(outside.join("secret.txt"), root.join("secret.txt")).unwrap();
+
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+    assert!(get(&server.addr, "/secret.txt?raw=1").ends_with("\r\n\r\nnot for you\n"));
+    drop(server);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--sandbox"]);
+    let response = get(&server.addr, "/secret.txt?raw=1");
+    assert!(!response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(!response.contains("not for you"), "{}", response);
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&outside);
+}
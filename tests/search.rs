@@ -0,0 +1,111 @@
+mod common;
+
+use common::{document_root, get, header, send, start_server};
+
+// Answered over HTTP/1.0, so the streamed body comes unchunked.
+fn search_json(addr: &str, target: &str) -> serde_json::Value {
+    let response = send(addr, &format!("GET {} HTTP/1.0\r\nHost: localhost\r\nAccept: application/json\r\n\r\n", target));
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+fn names(results: &serde_json::Value) -> Vec<&str> {
+    let mut names: Vec<&str> = results["results"].as_array().unwrap().iter().map(|entry| entry["name"].as_str().unwrap()).collect();
+    names.sort();
+    names
+}
+
+fn tree(name: &str) -> std::path::PathBuf {
+    let root = document_root(name);
+    std::fs::create_dir_all(root.join("docs").join("2024").join("q1")).unwrap();
+    std::fs::create_dir_all(root.join("docs").join(".private")).unwrap();
+    std::fs::write(root.join("docs").join("Report.txt"), "r").unwrap();
+    std::fs::write(root.join("docs").join("2024").join("report-final.pdf"), "rf").unwrap();
+    std::fs::write(root.join("docs").join("2024").join("q1").join("old report.txt"), "or").unwrap();
+    std::fs::write(root.join("docs").join(".private").join("report.txt"), "secret").unwrap();
+    std::fs::write(root.join("docs").join("report.env"), "KEY=1").unwrap();
+    std::fs::write(root.join("notes.txt"), "n").unwrap();
+    root
+}
+
+#[test]
+fn finds_names_under_the_directory() {
+    let root = tree("search");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--deny-ext", "env"]);
+
+    let response = get(&server.addr, "/search?q=REPORT&path=/docs");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(header(&response, "Transfer-Encoding"), Some("chunked"));
+    assert!(response.contains(r#"<a href="/docs/2024/q1/old%20report.txt">"#), "{}", response);
+    assert!(response.contains("2024/q1/old report.txt</a>"), "{}", response);
+    assert!(response.contains("3 matches"), "{}", response);
+    assert!(!response.contains(".private"));
+    assert!(!response.contains("report.env"));
+
+    let results = search_json(&server.addr, "/search?q=report&path=/docs/");
+    assert_eq!(results["path"], "/docs/");
+    assert_eq!(results["query"], "report");
+    assert_eq!(results["truncated"], false);
+    assert_eq!(names(&results), ["2024/q1/old report.txt", "2024/report-final.pdf", "Report.txt"]);
+    let pdf = results["results"].as_array().unwrap().iter().find(|entry| entry["name"] == "2024/report-final.pdf").unwrap();
+    assert_eq!(pdf["href"], "/docs/2024/report-final.pdf");
+    assert_eq!(pdf["type"], "file");
+    assert_eq!(pdf["size"], 2);
+
+    // Globs match whole names; hidden names are searched when asked for.
+    assert_eq!(names(&search_json(&server.addr, "/search?q=*.txt&path=/docs")), ["2024/q1/old report.txt", "Report.txt"]);
+    assert_eq!(names(&search_json(&server.addr, "/search?q=2024&path=/")), ["docs/2024"]);
+    assert_eq!(
+        names(&search_json(&server.addr, "/search?q=report.txt&path=/docs&hidden=1")),
+        [".private/report.txt", "2024/q1/old report.txt", "Report.txt"]
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn stops_at_the_limits() {
+    let root = tree("search-limits");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--search-max-depth", "1", "--search-max-results", "3"]);
+
+    let results = search_json(&server.addr, "/search?q=report&path=/docs");
+    assert_eq!(names(&results), ["2024/report-final.pdf", "Report.txt", "report.env"]);
+    assert_eq!(results["truncated"], false);
+
+    let results = search_json(&server.addr, "/search?q=*&path=/");
+    assert_eq!(results["results"].as_array().unwrap().len(), 3);
+    assert_eq!(results["truncated"], true);
+    let page = get(&server.addr, "/search?q=*&path=/");
+    assert!(page.contains("stopping at the first 3"), "{}", page);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn listings_have_a_search_box_and_bad_requests_are_refused() {
+    let root = tree("search-box");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/docs/");
+    assert!(listing.contains(r#"<form class="search" action="/search" method="get"><input type="hidden" name="path" value="/docs">"#), "{}", listing);
+    let page = get(&server.addr, "/search?path=/docs");
+    assert!(page.starts_with("HTTP/1.1 200") && page.contains(r#"name="q" value="""#), "{}", page);
+
+    assert!(get(&server.addr, "/search?q=x&path=/missing").starts_with("HTTP/1.1 404"));
+    assert!(get(&server.addr, "/search?q=x&path=/notes.txt").starts_with("HTTP/1.1 404"));
+    assert!(get(&server.addr, "/search?q=[x&path=/").starts_with("HTTP/1.1 400"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn links_to_directories_are_not_entered() {
+    let root = tree("search-links");
+    std::os::unix::fs::symlink(root.join("docs"), root.join("docs").join("2024").join("loop")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let results = search_json(&server.addr, "/search?q=o&path=/docs/2024");
+    assert_eq!(names(&results), ["loop", "q1/old report.txt", "report-final.pdf"]);
+    let link = results["results"].as_array().unwrap().iter().find(|entry| entry["name"] == "loop").unwrap();
+    assert_eq!(link["type"], "directory");
+    assert_eq!(link["href"], "/docs/2024/loop/");
+    let _ = std::fs::remove_dir_all(&root);
+}
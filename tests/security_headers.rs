@@ -0,0 +1,117 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+#[test]
+fn every_response_carries_the_security_headers() {
+    let root = document_root("security-headers");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    for target in ["/", "/notes.txt?raw=1", "/missing", "/?download=tar.gz"] {
+        let response = get(&server.addr, target);
+        assert_eq!(header(&response, "X-Content-Type-Options"), Some("nosniff"), "{}", target);
+        assert_eq!(header(&response, "X-Frame-Options"), Some("SAMEORIGIN"), "{}", target);
+        assert_eq!(header(&response, "Referrer-Policy"), Some("strict-origin-when-cross-origin"), "{}", target);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn security_headers_can_be_turned_off() {
+    let root = document_root("security-headers-off");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--no-security-headers"]);
+
+    let response = get(&server.addr, "/notes.txt?raw=1");
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(header(&response, "X-Content-Type-Options"), None);
+    assert_eq!(header(&response, "X-Frame-Options"), None);
+    assert_eq!(header(&response, "Referrer-Policy"), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn configured_headers_are_added_to_every_response() {
+    let root = document_root("configured-headers");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let private = document_root("configured-headers-config");
+    let config = private.join("gredl.toml");
+    std::fs::write(
+        &config,
+        "[headers]\n\"X-Powered-By\" = \"gredl\"\n\"Cache-Control\" = \"public, max-age=3600\"\n\"X-Frame-Options\" = \"DENY\"\n",
+    )
+    .unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--config", config.to_str().unwrap()]);
+
+    for target in ["/", "/notes.txt?raw=1", "/missing", "/?download=tar.gz"] {
+        let response = get(&server.addr, target);
+        assert_eq!(header(&response, "X-Powered-By"), Some("gredl"), "{}", target);
+        assert_eq!(header(&response, "Cache-Control"), Some("public, max-age=3600"), "{}", target);
+        // Replacing the security header rather than joining it.
+        assert_eq!(response.matches("X-Frame-Options:").count(), 1, "{}", target);
+        assert_eq!(header(&response, "X-Frame-Options"), Some("DENY"), "{}", target);
+        assert_eq!(header(&response, "X-Content-Type-Options"), Some("nosniff"), "{}", target);
+    }
+    // A header a response sets itself is kept.
+    let response = get(&server.addr, "/_metrics");
+    assert_eq!(response.matches("Cache-Control:").count(), 1);
+    assert_eq!(header(&response, "Cache-Control"), Some("no-store"));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&private);
+}
+
+#[test]
+fn path_headers_override_configured_headers_under_their_prefix() {
+    let root = document_root("path-headers");
+    std::fs::create_dir_all(root.join("docs/old")).unwrap();
+    std::fs::write(root.join("docs/old/notes.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("docsite.txt"), "elsewhere\n").unwrap();
+    let private = document_root("path-headers-config");
+    let config = private.join("gredl.toml");
+    std::fs::write(
+        &config,
+        "[headers]\n\"Cache-Control\" = \"public, max-age=3600\"\n\"X-Powered-By\" = \"gredl\"\n\n\
+        [[path_headers]]\npath_prefix = \"/api/\"\n[path_headers.headers]\n\"cache-control\" = \"no-store\"\n\n\
+        [[path_headers]]\npath_prefix = \"/docs\"\n[path_headers.headers]\n\"Cache-Control\" = \"max-age=60\"\n\n\
+        [[path_headers]]\npath_prefix = \"/docs/old\"\n[path_headers.headers]\n\"Cache-Control\" = \"max-age=86400\"\n\"X-Frame-Options\" = \"DENY\"\n",
+    )
+    .unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--config", config.to_str().unwrap()]);
+
+    for (target, cache_control) in [
+        ("/api/v1/ls?path=/", "no-store"),
+        ("/docs/", "max-age=60"),
+        ("/docs/old/notes.txt?raw=1", "max-age=86400"),
+        ("/docs/old/missing", "max-age=86400"),
+        // Not under `/docs`, only next to it.
+        ("/docsite.txt?raw=1", "public, max-age=3600"),
+        ("/", "public, max-age=3600"),
+    ] {
+        let response = get(&server.addr, target);
+        assert_eq!(response.to_ascii_lowercase().matches("cache-control:").count(), 1, "{}", target);
+        assert_eq!(header(&response, "Cache-Control"), Some(cache_control), "{}", target);
+        // Global headers the prefix does not name still apply.
+        assert_eq!(header(&response, "X-Powered-By"), Some("gredl"), "{}", target);
+    }
+    let response = get(&server.addr, "/docs/old/notes.txt?raw=1");
+    assert_eq!(header(&response, "X-Frame-Options"), Some("DENY"));
+    let response = get(&server.addr, "/docs/");
+    assert_eq!(header(&response, "X-Frame-Options"), Some("SAMEORIGIN"));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&private);
+}
+
+#[test]
+fn headers_that_frame_the_body_cannot_be_configured() {
+    let root = document_root("configured-headers-framing");
+    for header in ["Content-Length=0", "transfer-encoding=chunked", "Bad Name=x"] {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+            .args(["--port", "0", "--root", root.to_str().unwrap(), "--header", header])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}", header);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("headers: `"), "{}", header);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,56 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, start_server};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+const FILE_SIZE: usize = 64 * 1024 * 1024;
+
+#[test]
+fn sigterm_lets_a_running_download_finish_and_refuses_new_connections() {
+    let root = document_root("shutdown");
+    std::fs::write(root.join("big.bin"), vec![7u8; FILE_SIZE]).unwrap();
+    let mut server = start_server(&["--root", root.to_str().unwrap(), "--shutdown-grace", "30"]);
+
+    let mut download = TcpStream::connect(&server.addr).unwrap();
+    download.write_all(b"GET /big.bin?raw=1 HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+    // Take only the start of the response, so the server is still writing
+    // when the signal arrives.
+    let mut received = vec![0; 64 * 1024];
+    download.read_exact(&mut received).unwrap();
+
+    server.signal(libc::SIGTERM);
+    thread::sleep(Duration::from_millis(500));
+    assert!(TcpStream::connect(&server.addr).is_err(), "new connection accepted during shutdown");
+
+    download.read_to_end(&mut received).unwrap();
+    let head_end = received.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+    assert!(received.starts_with(b"HTTP/1.1 200"));
+    assert_eq!(received.len() - head_end, FILE_SIZE);
+    assert!(server.wait().success());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn connections_still_open_after_the_grace_period_are_closed() {
+    let root = document_root("shutdown-timeout");
+    std::fs::write(root.join("big.bin"), vec![7u8; FILE_SIZE]).unwrap();
+    let mut server = start_server(&["--root", root.to_str().unwrap(), "--shutdown-timeout", "1"]);
+
+    // A client that stops reading keeps its download from ever finishing.
+    let mut download = TcpStream::connect(&server.addr).unwrap();
+    download.write_all(b"GET /big.bin?raw=1 HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+    let mut received = vec![0; 64 * 1024];
+    download.read_exact(&mut received).unwrap();
+
+    server.signal(libc::SIGINT);
+    assert!(server.wait().success());
+    download.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let _ = download.read_to_end(&mut received);
+    assert!(received.len() < FILE_SIZE, "the download was not cut off");
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn sizes_come_in_the_configured_units_with_the_exact_count() {
+    let root = document_root("sizes");
+    std::fs::write(root.join("data.bin"), vec![0; 1_234_567]).unwrap();
+    std::fs::create_dir(root.join("empty")).unwrap();
+    std::fs::create_dir(root.join("one")).unwrap();
+    std::fs::write(root.join("one").join("file"), "").unwrap();
+    std::fs::create_dir(root.join("many")).unwrap();
+    for i in 0..1001 {
+        std::fs::write(root.join("many").join(i.to_string()), "").unwrap();
+    }
+
+    let binary = start_server(&["--root", root.to_str().unwrap()]);
+    let listing = get(&binary.addr, "/");
+    assert!(listing.contains(r#"<td title="1,234,567 bytes">1.18 MiB</td>"#), "{}", listing);
+    assert!(listing.contains("<td>0 items</td>"));
+    assert!(listing.contains("<td>1 item</td>"));
+    assert!(listing.contains("<td>1,000+ items</td>"));
+    let info = get(&binary.addr, "/data.bin");
+    assert!(info.contains("Size: 1.18 MiB (1,234,567 bytes)"), "{}", info);
+
+    let decimal = start_server(&["--root", root.to_str().unwrap(), "--size-units", "decimal"]);
+    assert!(get(&decimal.addr, "/").contains(r#"<td title="1,234,567 bytes">1.23 MB</td>"#));
+    assert!(get(&decimal.addr, "/data.bin").contains("Size: 1.23 MB (1,234,567 bytes)"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn entries_are_only_counted_in_small_listings() {
+    let root = document_root("sizes-many-directories");
+    for i in 0..51 {
+        std::fs::create_dir(root.join(format!("dir{}", i))).unwrap();
+    }
+    std::fs::write(root.join("dir0").join("file"), "").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    // Counting would read each of the 51 directories.
+    let listing = get(&server.addr, "/");
+    assert!(!listing.contains(" item"), "{}", listing);
+    std::fs::remove_dir(root.join("dir50")).unwrap();
+    assert!(get(&server.addr, "/").contains("<td>1 item</td>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
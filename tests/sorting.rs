@@ -0,0 +1,198 @@
+mod common;
+
+use common::{document_root, get, start_server};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+// Entry names in the order the listing shows them.
+fn names(listing: &str) -> Vec<String> {
+    listing
+        .split("<td><a href=")
+        .skip(1)
+        .filter_map(|row| row.split("</a>").next())
+        .filter_map(|link| link.rsplit(' ').next())
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn listing_sorts_by_size_numerically_with_directories_first() {
+    let root = document_root("sorting");
+    std::fs::create_dir(root.join("zdir")).unwrap();
+    std::fs::write(root.join("a.bin"), vec![0; 900]).unwrap();
+    std::fs::write(root.join("b.bin"), vec![0; 10_000]).unwrap();
+    std::fs::write(root.join("c.bin"), vec![0; 80]).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    assert_eq!(names(&get(&server.addr, "/")), ["zdir", "a.bin", "b.bin", "c.bin"]);
+    assert_eq!(names(&get(&server.addr, "/?sort=size")), ["zdir", "c.bin", "a.bin", "b.bin"]);
+    let listing = get(&server.addr, "/?sort=size&order=desc");
+    assert_eq!(names(&listing), ["zdir", "b.bin", "a.bin", "c.bin"]);
+    assert!(listing.contains(r#"<a href="?sort=size&order=asc">Size ▼</a>"#), "{}", listing);
+    // Nonsense falls back to the default order.
+    assert_eq!(names(&get(&server.addr, "/?sort=colour&order=sideways")), ["zdir", "a.bin", "b.bin", "c.bin"]);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+// A piece of a name as the natural order sees it. Text sorts below numbers
+// when it comes before the digits in Unicode order and above them otherwise.
+#[derive(PartialEq, Eq)]
+enum Token {
+    Char(char),
+    // Digits without leading zeros.
+    Number(String),
+}
+
+impl Token {
+    fn cmp(&self, other: &Token) -> Ordering {
+        match (self, other) {
+            (Token::Char(a), Token::Char(b)) => a.cmp(b),
+            (Token::Number(a), Token::Number(b)) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+            (Token::Char(a), Token::Number(_)) => a.cmp(&'0'),
+            (Token::Number(_), Token::Char(b)) => '0'.cmp(b),
+        }
+    }
+}
+
+fn tokens(name: &str) -> Vec<Token> {
+    let lower = name.to_lowercase();
+    let mut tokens = Vec::new();
+    let mut chars = lower.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            let mut digits = c.to_string();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            tokens.push(Token::Number(digits.trim_start_matches('0').to_string()));
+        } else {
+            tokens.push(Token::Char(c));
+        }
+    }
+    tokens
+}
+
+// Reference for the server's natural order: tokenize both names up front and
+// compare the token lists, then the names themselves.
+fn natural_reference(a: &str, b: &str) -> Ordering {
+    let (a_tokens, b_tokens) = (tokens(a), tokens(b));
+    a_tokens
+        .iter()
+        .zip(&b_tokens)
+        .map(|(x, y)| x.cmp(y))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a_tokens.len().cmp(&b_tokens.len()))
+        .then_with(|| a.cmp(b))
+}
+
+// Deterministic pseudo-random names built from pieces that exercise the
+// interesting cases: mixed case, leading zeros, runs of digits far longer
+// than any integer type and non-ASCII letters.
+fn random_names(count: usize, mut seed: u64) -> Vec<String> {
+    const PIECES: &[&str] = &[
+        "a", "B", "b", "file", "File", "chapter", "_", "-", ".", "é", "É", "0", "00", "007", "7", "1", "10", "2", "9",
+        "99999999999999999999999999999999999999", "000000000000000000000000000000000000001",
+    ];
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize
+    };
+    let mut names = BTreeSet::new();
+    while names.len() < count {
+        let name: String = (0..1 + next() % 5).map(|_| PIECES[next() % PIECES.len()]).collect();
+        if !name.trim_matches('.').is_empty() {
+            names.insert(name);
+        }
+    }
+    names.into_iter().collect()
+}
+
+#[test]
+fn listing_sorts_names_naturally_by_default() {
+    let root = document_root("sorting-natural");
+    for name in ["chapter10.md", "chapter2.md", "Chapter1.md", "file007", "file7", "file08"] {
+        std::fs::write(root.join(name), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+    assert_eq!(
+        names(&get(&server.addr, "/")),
+        ["Chapter1.md", "chapter2.md", "chapter10.md", "file007", "file7", "file08"]
+    );
+    drop(server);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--lexicographic-sort"]);
+    assert_eq!(
+        names(&get(&server.addr, "/")),
+        ["Chapter1.md", "chapter10.md", "chapter2.md", "file007", "file08", "file7"]
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn natural_order_matches_the_reference_on_random_names() {
+    for seed in [1, 2, 3] {
+        let root = document_root(&format!("sorting-random-{}", seed));
+        let mut expected = random_names(300, seed);
+        for name in &expected {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+        let server = start_server(&["--root", root.to_str().unwrap()]);
+        expected.sort_by(|a, b| natural_reference(a, b));
+        assert_eq!(names(&get(&server.addr, "/?hidden=1")), expected, "seed {}", seed);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
+
+#[test]
+fn case_and_directory_grouping_follow_the_config_and_the_query() {
+    let root = document_root("sorting-case");
+    std::fs::create_dir(root.join("Docs")).unwrap();
+    for name in ["README", "apple", "Straße", "strasse"] {
+        std::fs::write(root.join(name), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+    // Case folding makes `ß` equal to `ss`, leaving the bytes to decide.
+    assert_eq!(names(&get(&server.addr, "/")), ["Docs", "apple", "README", "Straße", "strasse"]);
+    assert_eq!(names(&get(&server.addr, "/?dirs_first=0")), ["apple", "Docs", "README", "Straße", "strasse"]);
+    assert_eq!(names(&get(&server.addr, "/?sort_ci=0")), ["Docs", "README", "Straße", "apple", "strasse"]);
+    drop(server);
+
+    let server = start_server(&["--root", root.to_str().unwrap(), "--mix-dirs", "--case-sensitive-sort", "--lexicographic-sort"]);
+    assert_eq!(names(&get(&server.addr, "/")), ["Docs", "README", "Straße", "apple", "strasse"]);
+    assert_eq!(names(&get(&server.addr, "/?sort_ci=1")), ["apple", "Docs", "README", "Straße", "strasse"]);
+    assert_eq!(names(&get(&server.addr, "/?sort_ci=1&dirs_first=1&order=desc")), ["Docs", "strasse", "Straße", "README", "apple"]);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn unicode_collation_sorts_accented_letters_with_their_base_letter() {
+    let root = document_root("sorting-unicode");
+    for name in ["Zebra", "äpfel", "apple", "Öl", "ole", "file10", "file9", "日本", "Ωmega"] {
+        std::fs::write(root.join(name), "").unwrap();
+    }
+    let server = start_server(&["--root", root.to_str().unwrap(), "--collation", "unicode"]);
+    assert_eq!(
+        names(&get(&server.addr, "/")),
+        ["äpfel", "apple", "file9", "file10", "Öl", "ole", "Zebra", "Ωmega", "日本"]
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn unicode_collation_is_stable_across_scripts_and_digits() {
+    let root = document_root("sorting-unicode-mixed");
+    // Digits of other scripts, which collation orders by value, next to
+    // ASCII digit runs, which natural ordering compares by value.
+    let pieces = ["9", "10", "٣", "٣9", "a", "Ä", "-", "日", "ß", "ss", "Ω", "0"];
+    for i in 0..pieces.len() {
+        for j in 0..pieces.len() {
+            std::fs::write(root.join(format!("{}{}", pieces[i], pieces[j])), "").unwrap();
+        }
+    }
+    let server = start_server(&["--root", root.to_str().unwrap(), "--collation", "unicode", "--listing-cache-ttl", "0"]);
+    let first = names(&get(&server.addr, "/"));
+    assert_eq!(first.len(), pieces.len() * pieces.len());
+    assert_eq!(names(&get(&server.addr, "/")), first);
+    assert_eq!(names(&get(&server.addr, "/?sort=name&order=desc")), first.iter().rev().cloned().collect::<Vec<_>>());
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{document_root, get, start_server};
+
+#[test]
+fn missing_paths_are_answered_with_the_fallback_file() {
+    let root = document_root("spa-fallback");
+    std::fs::write(root.join("app.html"), "<div id=app></div>").unwrap();
+    std::fs::write(root.join("style.css"), "body {}").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--spa-fallback", "app.html"]);
+
+    let response = get(&server.addr, "/users/42/settings");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\n<div id=app></div>"));
+    assert!(get(&server.addr, "/style.css?raw=1").ends_with("\r\n\r\nbody {}"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn a_missing_fallback_file_leaves_the_404() {
+    let root = document_root("spa-fallback-missing");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--spa-fallback", "app.html"]);
+
+    assert!(get(&server.addr, "/users/42").starts_with("HTTP/1.1 404"));
+    let _ = std::fs::remove_dir_all(&root);
+}
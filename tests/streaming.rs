@@ -0,0 +1,35 @@
+mod common;
+
+use common::{document_root, get, header, start_server};
+
+// One more entry than a listing buffers, so it is streamed.
+fn huge_directory(name: &str) -> std::path::PathBuf {
+    let root = document_root(name);
+    for i in 0..10_001 {
+        std::fs::write(root.join(format!("{:05}.txt", i)), "").unwrap();
+    }
+    std::fs::write(root.join("zz.log"), "").unwrap();
+    root
+}
+
+#[test]
+fn huge_directory_is_streamed_unsorted_then_sorted_on_request() {
+    let root = huge_directory("streaming");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert_eq!(header(&listing, "Transfer-Encoding"), Some("chunked"));
+    assert!(listing.contains("shown unsorted"), "{}", &listing[..2000]);
+    assert_eq!(listing.matches("<td><a href=").count(), 10_002);
+    assert!(listing.contains("</html>"));
+
+    let listing = get(&server.addr, "/?sort=name&order=desc");
+    assert!(listing.contains("<p>10002 entries</p>"));
+    let first = listing.find("📄 zz.log").unwrap();
+    assert!(first < listing.find("📄 10000.txt").unwrap());
+    assert!(listing.find("📄 10000.txt").unwrap() < listing.find("📄 00000.txt").unwrap());
+
+    let listing = get(&server.addr, "/?filter=*.log");
+    assert_eq!(listing.matches("<td><a href=").count(), 1);
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,34 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, get, start_server};
+use std::os::unix::fs::symlink;
+
+#[test]
+fn links_show_their_targets_and_broken_ones_are_kept() {
+    let root = document_root("symlinks");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    symlink("notes.txt", root.join("latest.txt")).unwrap();
+    symlink("docs", root.join("manual")).unwrap();
+    symlink("gone.txt", root.join("dangling.txt")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains(r#"<a href="/latest.txt">🔗 latest.txt</a> <span class="link-target">→ notes.txt</span>"#), "{}", listing);
+    assert!(listing.contains(r#"<a href="/manual/">🔗 manual</a> <span class="link-target">→ docs</span>"#), "{}", listing);
+    let dangling = listing.find(r#"<a href="/dangling.txt">🔗 dangling.txt</a> <span class="link-target">→ gone.txt</span>"#).unwrap();
+    assert!(listing[..dangling].ends_with("<tr class=\"broken\">\n                    <td>"), "{}", listing);
+    assert!(listing.contains("📄 notes.txt"));
+
+    let info = get(&server.addr, "/latest.txt");
+    assert!(info.contains("Symbolic link to: notes.txt"), "{}", info);
+    assert!(info.contains("Size: 11 B"), "{}", info);
+
+    let info = get(&server.addr, "/dangling.txt");
+    assert!(info.starts_with("HTTP/1.1 200"), "{}", info);
+    assert!(info.contains("Symbolic link to: gone.txt"));
+    assert!(info.contains("The link's target is missing."));
+    let _ = std::fs::remove_dir_all(&root);
+}
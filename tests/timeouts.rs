@@ -0,0 +1,40 @@
+mod common;
+
+use common::start_server;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn slow_request_head_gets_408_and_is_closed() {
+    let server = start_server(&["--header-timeout", "1"]);
+
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let started = Instant::now();
+    // Dribble the request line one byte at a time, never finishing the head.
+    for byte in b"GET / HTTP/1.1\r\nHost: x" {
+        if stream.write_all(&[*byte]).is_err() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 408"), "unexpected response: {}", response);
+    assert!(started.elapsed() < Duration::from_secs(4));
+}
+
+#[test]
+fn prompt_request_is_unaffected() {
+    let server = start_server(&["--header-timeout", "1"]);
+
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{document_root, get, start_server};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn dates_use_the_configured_zone_and_format() {
+    let root = document_root("timestamps");
+    let file = std::fs::File::create(root.join("notes.txt")).unwrap();
+    // 2024-01-15 12:30:00 UTC.
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_705_321_800)).unwrap();
+    drop(file);
+    let server = start_server(&[
+        "--root",
+        root.to_str().unwrap(),
+        "--timezone",
+        "Asia/Tokyo",
+        "--date-format",
+        "%d.%m.%Y %H:%M",
+    ]);
+
+    let listing = get(&server.addr, "/");
+    assert!(listing.contains("<td>15.01.2024 21:30 Asia/Tokyo</td>"), "{}", listing);
+    let info = get(&server.addr, "/notes.txt");
+    assert!(info.contains("Modified: 15.01.2024 21:30 Asia/Tokyo"), "{}", info);
+
+    let utc = start_server(&["--root", root.to_str().unwrap(), "--timezone", "UTC"]);
+    assert!(get(&utc.addr, "/").contains("<td>2024-01-15 12:30:00 UTC</td>"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn bad_zones_and_formats_are_refused_at_startup() {
+    for (flag, value, message) in [
+        ("--timezone", "Mars/Olympus_Mons", "timezone: `Mars/Olympus_Mons`"),
+        ("--date-format", "%Y-%Q", "date_format: `%Y-%Q` is not a valid strftime format"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_gredl_server")).args(["--port", "0", flag, value]).output().unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+}
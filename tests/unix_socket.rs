@@ -0,0 +1,51 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{document_root, start_server};
+use std::io::{Read, Write};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::process::Command;
+
+#[test]
+fn serves_over_a_unix_socket_with_its_mode() {
+    let root = document_root("unix-socket");
+    std::fs::write(root.join("hello.txt"), "hello").unwrap();
+    let sockets = document_root("unix-socket-dir");
+    let socket = sockets.join("gredl.sock");
+    // Left behind by a server that is gone: nothing answers on it.
+    drop(UnixListener::bind(&socket).unwrap());
+    let bind = format!("unix:{}", socket.display());
+    let server = start_server(&["--root", root.to_str().unwrap(), "--bind", &bind, "--unix-socket-mode", "0640"]);
+    assert_eq!(server.addr, bind);
+
+    let metadata = std::fs::symlink_metadata(&socket).unwrap();
+    assert!(metadata.file_type().is_socket());
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    stream.write_all(b"GET /hello.txt?raw=1 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nhello"));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&sockets);
+}
+
+#[test]
+fn refuses_a_path_that_is_not_a_socket() {
+    let root = document_root("unix-socket-file");
+    let path = root.join("notes.txt");
+    std::fs::write(&path, "keep me").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gredl_server"))
+        .args(["--root", root.to_str().unwrap(), "--bind", &format!("unix:{}", path.display())])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("path exists and is not a socket"), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    let _ = std::fs::remove_dir_all(&root);
+}
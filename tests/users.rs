@@ -0,0 +1,55 @@
+mod common;
+
+use base64::Engine;
+use common::{document_root, get, header, send, start_server};
+
+fn get_as(addr: &str, target: &str, user: &str, password: &str) -> String {
+    let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {}\r\n\r\n", target, credentials))
+}
+
+#[test]
+fn accounts_reach_only_their_roots() {
+    let root = document_root("users");
+    std::fs::create_dir(root.join("photos")).unwrap();
+    std::fs::create_dir(root.join("private")).unwrap();
+    std::fs::write(root.join("photos").join("cat.txt"), "meow\n").unwrap();
+    let accounts = document_root("users-file");
+    let users_file = accounts.join("users.toml");
+    std::fs::write(
+        &users_file,
+        format!(
+            "[alice]\npassword = \"{}\"\nroots = [\"/photos/\"]\n\n[admin]\npassword = \"{}\"\nroots = [\"/\"]\n",
+            bcrypt::hash("wonderland", 4).unwrap(),
+            bcrypt::hash("root pass", 4).unwrap()
+        ),
+    )
+    .unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--users-file", users_file.to_str().unwrap()]);
+
+    let response = get(&server.addr, "/photos/cat.txt?raw=1");
+    assert!(response.starts_with("HTTP/1.1 401"), "{}", response);
+    assert_eq!(header(&response, "WWW-Authenticate"), Some(r#"Basic realm="gredl_server", charset="UTF-8""#));
+    assert!(get_as(&server.addr, "/photos/cat.txt?raw=1", "alice", "wrong").starts_with("HTTP/1.1 401"));
+    assert!(get_as(&server.addr, "/photos/cat.txt?raw=1", "mallory", "wonderland").starts_with("HTTP/1.1 401"));
+
+    assert!(get_as(&server.addr, "/photos/cat.txt?raw=1", "alice", "wonderland").ends_with("meow\n"));
+    assert!(get_as(&server.addr, "/photos/", "alice", "wonderland").starts_with("HTTP/1.1 200"));
+    assert!(get_as(&server.addr, "/private/", "alice", "wonderland").starts_with("HTTP/1.1 403"));
+    assert!(get_as(&server.addr, "/photos/../private/", "alice", "wonderland").starts_with("HTTP/1.1 403"));
+    assert!(get_as(&server.addr, "/?download=zip", "alice", "wonderland").starts_with("HTTP/1.1 403"));
+    assert!(get_as(&server.addr, "/api/v1/ls?path=/photos/", "alice", "wonderland").starts_with("HTTP/1.1 200"));
+    let response = get_as(&server.addr, "/api/v1/ls?path=/private/", "alice", "wonderland");
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/json"));
+    assert!(get_as(&server.addr, "/api/v1/stat?path=/photos/cat.txt", "alice", "wonderland").starts_with("HTTP/1.1 200"));
+    let response = get_as(&server.addr, "/api/v1/stat?path=/photos/../private", "alice", "wonderland");
+    assert!(response.ends_with(r#"{"error":"Forbidden","status":403}"#), "{}", response);
+    let response = get(&server.addr, "/api/v1/ls?path=/photos/");
+    assert!(response.ends_with(r#"{"error":"Unauthorized","status":401}"#), "{}", response);
+    assert!(header(&response, "WWW-Authenticate").is_some());
+
+    assert!(get_as(&server.addr, "/private/", "admin", "root pass").starts_with("HTTP/1.1 200"));
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&accounts);
+}
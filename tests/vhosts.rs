@@ -0,0 +1,34 @@
+mod common;
+
+use common::{document_root, send, start_server};
+
+fn get_as(addr: &str, host: &str, target: &str) -> String {
+    send(addr, &format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, host))
+}
+
+#[test]
+fn each_host_gets_its_own_root() {
+    let root = document_root("vhosts");
+    for site in ["main", "photos"] {
+        std::fs::create_dir(root.join(site)).unwrap();
+        std::fs::write(root.join(site).join("site.txt"), site).unwrap();
+    }
+    let vhost = format!("photos.lan={}", root.join("photos").display());
+    let server = start_server(&["--root", root.join("main").to_str().unwrap(), "--vhost", &vhost]);
+
+    assert!(get_as(&server.addr, "photos.lan", "/site.txt?raw=1").ends_with("\r\n\r\nphotos"));
+    assert!(get_as(&server.addr, "Photos.LAN:8080", "/site.txt?raw=1").ends_with("\r\n\r\nphotos"));
+    assert!(get_as(&server.addr, "other.lan", "/site.txt?raw=1").ends_with("\r\n\r\nmain"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn strict_mode_refuses_unknown_hosts() {
+    let root = document_root("vhosts-strict");
+    let vhost = format!("docs.lan={}", root.display());
+    let server = start_server(&["--root", root.to_str().unwrap(), "--vhost", &vhost, "--strict-vhosts"]);
+
+    assert!(get_as(&server.addr, "docs.lan", "/").starts_with("HTTP/1.1 200"));
+    assert!(get_as(&server.addr, "other.lan", "/").starts_with("HTTP/1.1 421"));
+    let _ = std::fs::remove_dir_all(&root);
+}
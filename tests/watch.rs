@@ -0,0 +1,103 @@
+mod common;
+
+use common::{document_root, header, start_server};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+// Reads from `stream` until what has arrived contains `end`.
+fn read_until(stream: &mut TcpStream, received: &mut Vec<u8>, end: &[u8]) {
+    let mut chunk = [0; 4096];
+    while !received.windows(end.len()).any(|window| window == end) {
+        let read = stream.read(&mut chunk).unwrap();
+        assert!(read > 0, "connection closed after {:?}", String::from_utf8_lossy(received));
+        received.extend_from_slice(&chunk[..read]);
+    }
+}
+
+// Reads one unmasked server frame: its first byte and its payload.
+fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head).unwrap();
+    assert!(head[1] < 126, "unexpected frame length {}", head[1]);
+    let mut payload = vec![0; head[1] as usize];
+    stream.read_exact(&mut payload).unwrap();
+    (head[0], payload)
+}
+
+#[test]
+fn websocket_watch_handshakes_and_streams_changes() {
+    let root = document_root("watch-websocket");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream
+        .write_all(
+            b"GET /_ws/watch?path=/ HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        )
+        .unwrap();
+    let mut received = Vec::new();
+    read_until(&mut stream, &mut received, b"\r\n\r\n");
+    let response = String::from_utf8(received).unwrap();
+    assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"), "{}", response);
+    assert_eq!(header(&response, "Sec-WebSocket-Accept"), Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+    // The watch starts just after the handshake is sent.
+    thread::sleep(Duration::from_millis(200));
+    std::fs::write(root.join("new.txt"), "new").unwrap();
+    let (first, payload) = read_frame(&mut stream);
+    assert_eq!(first, 0x81);
+    let event: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!((&event["event"], &event["name"]), (&"created".into(), &"new.txt".into()));
+
+    // A masked ping with "hi" is answered with a pong carrying it back, and
+    // a close with a close.
+    stream.write_all(&[0x89, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2]).unwrap();
+    loop {
+        let (first, payload) = read_frame(&mut stream);
+        if first == 0x8A {
+            assert_eq!(payload, b"hi");
+            break;
+        }
+        // Any further events of the write come first.
+        assert_eq!(first, 0x81);
+    }
+    stream.write_all(&[0x88, 0x82, 1, 2, 3, 4, 0x03 ^ 1, 0xe8 ^ 2]).unwrap();
+    loop {
+        let (first, payload) = read_frame(&mut stream);
+        if first == 0x88 {
+            assert_eq!(payload, [0x03, 0xe8]);
+            break;
+        }
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn server_sent_events_stream_changes() {
+    let root = document_root("watch-events");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(b"GET /_events/watch?path=/ HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut received = Vec::new();
+    read_until(&mut stream, &mut received, b"\r\n\r\n");
+    let response = String::from_utf8_lossy(&received).to_string();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("text/event-stream"));
+    assert_eq!(header(&response, "Cache-Control"), Some("no-cache"));
+
+    thread::sleep(Duration::from_millis(200));
+    std::fs::write(root.join("new.txt"), "new").unwrap();
+    // Events are JSON objects; keepalive comments may come before them.
+    read_until(&mut stream, &mut received, b"}\n\n");
+    let body = String::from_utf8_lossy(&received).to_string();
+    let event = body.split("\n\n").find_map(|message| message.strip_prefix("data: ")).unwrap();
+    let event: serde_json::Value = serde_json::from_str(event).unwrap();
+    assert_eq!((&event["event"], &event["name"]), (&"created".into(), &"new.txt".into()));
+    let _ = std::fs::remove_dir_all(&root);
+}
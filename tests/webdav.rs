@@ -0,0 +1,380 @@
+mod common;
+
+use base64::Engine;
+use common::{document_root, get, header, send, start_server};
+
+fn propfind(addr: &str, target: &str, depth: Option<&str>) -> String {
+    let depth = depth.map(|depth| format!("Depth: {}\r\n", depth)).unwrap_or_default();
+    send(addr, &format!("PROPFIND {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 0\r\n\r\n", target, depth))
+}
+
+// Sends `method` for `target` with `headers`, each ending in CRLF, and no body.
+fn request(addr: &str, method: &str, target: &str, headers: &str) -> String {
+    send(addr, &format!("{} {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", method, target, headers))
+}
+
+fn put(addr: &str, target: &str, contents: &str) -> String {
+    send(addr, &format!("PUT {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", target, contents.len(), contents))
+}
+
+fn status(response: &str) -> &str {
+    response.split("\r\n").next().unwrap()
+}
+
+fn body(response: &str) -> &str {
+    response.split_once("\r\n\r\n").unwrap().1
+}
+
+#[test]
+fn options_advertises_class_1() {
+    let root = document_root("webdav-options");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav"]);
+
+    let response = send(&server.addr, "OPTIONS /_dav/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert_eq!(header(&response, "DAV"), Some("1"));
+    assert_eq!(header(&response, "Allow"), Some("OPTIONS, GET, HEAD, PROPFIND"));
+    let response = send(&server.addr, "LOCK /_dav/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"), "{}", response);
+
+    let writable = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+    let response = send(&writable.addr, "OPTIONS /_dav/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(header(&response, "Allow"), Some("OPTIONS, GET, HEAD, PROPFIND, PUT, MKCOL, DELETE, COPY, MOVE"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn propfind_describes_a_directory_and_its_entries() {
+    let root = document_root("webdav-propfind");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("docs/a & b.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("docs/.hidden"), "x").unwrap();
+    std::fs::create_dir(root.join("docs/sub")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav"]);
+
+    let response = propfind(&server.addr, "/_dav/docs/", Some("1"));
+    assert!(response.starts_with("HTTP/1.1 207 Multi-Status\r\n"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("application/xml; charset=utf-8"));
+    let xml = body(&response);
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">"), "{}", xml);
+    assert_eq!(xml.matches("<D:response>").count(), 3, "{}", xml);
+    assert!(xml.contains("<D:href>/_dav/docs/</D:href>"), "{}", xml);
+    assert!(xml.contains("<D:displayname>docs</D:displayname><D:resourcetype><D:collection/></D:resourcetype>"), "{}", xml);
+    assert!(xml.contains("<D:href>/_dav/docs/sub/</D:href>"), "{}", xml);
+    assert!(xml.contains("<D:href>/_dav/docs/a%20%26%20b.txt</D:href>"), "{}", xml);
+    assert!(xml.contains(
+        "<D:displayname>a &amp; b.txt</D:displayname><D:resourcetype/>\
+        <D:getcontentlength>11</D:getcontentlength><D:getcontenttype>text/plain; charset=utf-8</D:getcontenttype>\
+        <D:getlastmodified>"
+    ), "{}", xml);
+    assert!(xml.contains("GMT</D:getlastmodified>"), "{}", xml);
+    assert!(xml.contains("<D:status>HTTP/1.1 200 OK</D:status>"), "{}", xml);
+    assert!(!xml.contains(".hidden"), "{}", xml);
+
+    let xml = body(&propfind(&server.addr, "/_dav/docs/", Some("0"))).to_string();
+    assert_eq!(xml.matches("<D:response>").count(), 1, "{}", xml);
+    let xml = body(&propfind(&server.addr, "/_dav/", Some("1"))).to_string();
+    assert!(xml.contains("<D:href>/_dav/</D:href>"), "{}", xml);
+    assert!(xml.contains("<D:href>/_dav/docs/</D:href>"), "{}", xml);
+
+    let response = propfind(&server.addr, "/_dav/missing", Some("0"));
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", response);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn infinite_depth_is_refused() {
+    let root = document_root("webdav-depth");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav"]);
+
+    for depth in [Some("infinity"), None] {
+        let response = propfind(&server.addr, "/_dav/", depth);
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{}", response);
+        assert!(body(&response).contains("<D:propfind-finite-depth/>"), "{}", response);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn files_are_sent_as_they_are() {
+    let root = document_root("webdav-get");
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav"]);
+
+    let response = get(&server.addr, "/_dav/notes.txt");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert_eq!(header(&response, "Content-Type"), Some("text/plain; charset=utf-8"));
+    assert_eq!(body(&response), "some notes\n");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn webdav_is_off_by_default() {
+    let root = document_root("webdav-off");
+    let server = start_server(&["--root", root.to_str().unwrap()]);
+
+    let response = propfind(&server.addr, "/_dav/", Some("1"));
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"), "{}", response);
+    let response = get(&server.addr, "/_dav/");
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", response);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn changes_need_allow_write() {
+    let root = document_root("webdav-no-writes");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    std::fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav"]);
+
+    assert_eq!(status(&put(&server.addr, "/_dav/new.txt", "x")), "HTTP/1.1 403 Forbidden");
+    assert_eq!(status(&request(&server.addr, "MKCOL", "/_dav/sub/", "")), "HTTP/1.1 403 Forbidden");
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/docs/", "")), "HTTP/1.1 403 Forbidden");
+    for method in ["COPY", "MOVE"] {
+        let response = request(&server.addr, method, "/_dav/notes.txt", "Destination: /_dav/copied.txt\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 403 Forbidden");
+    }
+    let response = send(&server.addr, "PUT /made/ HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 403 Forbidden");
+    let mut names: Vec<_> = std::fs::read_dir(&root).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["docs", "notes.txt"]);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn put_writes_files_in_place() {
+    let root = document_root("webdav-put");
+    std::fs::create_dir(root.join("docs")).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    assert_eq!(status(&put(&server.addr, "/_dav/docs/new.txt", "first\n")), "HTTP/1.1 201 Created");
+    assert_eq!(std::fs::read_to_string(root.join("docs/new.txt")).unwrap(), "first\n");
+    assert_eq!(status(&put(&server.addr, "/_dav/docs/new.txt", "second\n")), "HTTP/1.1 204 No Content");
+    assert_eq!(body(&get(&server.addr, "/_dav/docs/new.txt")), "second\n");
+    // Nothing is left of the uploads but the file.
+    assert_eq!(std::fs::read_dir(root.join("docs")).unwrap().count(), 1);
+
+    let response = send(
+        &server.addr,
+        "PUT /_dav/docs/chunked.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nExpect: 100-continue\r\n\r\n\
+        6\r\nin two\r\n7\r\n pieces\r\n0\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 201 Created\r\n"), "{}", response);
+    assert_eq!(std::fs::read_to_string(root.join("docs/chunked.txt")).unwrap(), "in two pieces");
+
+    assert_eq!(status(&put(&server.addr, "/_dav/missing/new.txt", "x")), "HTTP/1.1 409 Conflict");
+    assert_eq!(status(&put(&server.addr, "/_dav/docs", "x")), "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(status(&put(&server.addr, "/_dav/docs/.gredl.toml", "read_only = false")), "HTTP/1.1 404 Not Found");
+    assert!(!root.join("docs/.gredl.toml").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn uploads_are_limited_by_max_body_size() {
+    let root = document_root("webdav-put-limit");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write", "--max-body-size", "4"]);
+
+    assert_eq!(status(&put(&server.addr, "/_dav/big.txt", "too large")), "HTTP/1.1 413 Content Too Large");
+    let response = send(
+        &server.addr,
+        "PUT /_dav/big.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n9\r\ntoo large\r\n0\r\n\r\n",
+    );
+    assert_eq!(status(&response), "HTTP/1.1 413 Content Too Large");
+    assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn mkcol_creates_directories() {
+    let root = document_root("webdav-mkcol");
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    assert_eq!(status(&request(&server.addr, "MKCOL", "/_dav/docs/", "")), "HTTP/1.1 201 Created");
+    assert!(root.join("docs").is_dir());
+    assert_eq!(status(&request(&server.addr, "MKCOL", "/_dav/docs/", "")), "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(status(&request(&server.addr, "MKCOL", "/_dav/a/b/", "")), "HTTP/1.1 409 Conflict");
+    assert!(!root.join("a").exists());
+    let response = send(&server.addr, "MKCOL /_dav/c/ HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n{}");
+    assert_eq!(status(&response), "HTTP/1.1 415 Unsupported Media Type");
+    assert!(!root.join("c").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn delete_removes_files_and_trees() {
+    let root = document_root("webdav-delete");
+    std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+    std::fs::write(root.join("docs/sub/notes.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("docs/.hidden"), "x").unwrap();
+    std::fs::write(root.join("todo.txt"), "x").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/todo.txt", "")), "HTTP/1.1 204 No Content");
+    assert!(!root.join("todo.txt").exists());
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/docs/", "")), "HTTP/1.1 204 No Content");
+    assert!(!root.join("docs").exists());
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/docs/", "")), "HTTP/1.1 404 Not Found");
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/", "")), "HTTP/1.1 403 Forbidden");
+    assert!(root.is_dir());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn copy_and_move_follow_destination_and_overwrite() {
+    let root = document_root("webdav-copy");
+    std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+    std::fs::write(root.join("docs/notes.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("docs/sub/deep.txt"), "deep\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    let copy = |target: &str, headers: &str| status(&request(&server.addr, "COPY", target, headers)).to_string();
+    assert_eq!(copy("/_dav/docs/notes.txt", "Destination: /_dav/copy.txt\r\n"), "HTTP/1.1 201 Created");
+    assert_eq!(std::fs::read_to_string(root.join("copy.txt")).unwrap(), "some notes\n");
+    assert_eq!(copy("/_dav/docs/sub/deep.txt", "Destination: http://localhost/_dav/copy.txt\r\n"), "HTTP/1.1 204 No Content");
+    assert_eq!(std::fs::read_to_string(root.join("copy.txt")).unwrap(), "deep\n");
+    assert_eq!(copy("/_dav/docs/notes.txt", "Destination: /_dav/copy.txt\r\nOverwrite: F\r\n"), "HTTP/1.1 412 Precondition Failed");
+    assert_eq!(std::fs::read_to_string(root.join("copy.txt")).unwrap(), "deep\n");
+
+    assert_eq!(copy("/_dav/docs/", "Destination: /_dav/tree/\r\n"), "HTTP/1.1 201 Created");
+    assert_eq!(std::fs::read_to_string(root.join("tree/sub/deep.txt")).unwrap(), "deep\n");
+    assert_eq!(copy("/_dav/docs/", "Destination: /_dav/shallow/\r\nDepth: 0\r\n"), "HTTP/1.1 201 Created");
+    assert_eq!(std::fs::read_dir(root.join("shallow")).unwrap().count(), 0);
+    assert_eq!(copy("/_dav/docs/", "Destination: /_dav/docs/sub/docs/\r\n"), "HTTP/1.1 403 Forbidden");
+    assert_eq!(copy("/_dav/docs/notes.txt", "Destination: /_dav/missing/notes.txt\r\n"), "HTTP/1.1 409 Conflict");
+    assert_eq!(copy("/_dav/docs/notes.txt", ""), "HTTP/1.1 400 Bad Request");
+
+    let moved = |target: &str, headers: &str| status(&request(&server.addr, "MOVE", target, headers)).to_string();
+    assert_eq!(moved("/_dav/tree/", "Destination: /_dav/moved/\r\n"), "HTTP/1.1 201 Created");
+    assert!(!root.join("tree").exists());
+    assert_eq!(std::fs::read_to_string(root.join("moved/sub/deep.txt")).unwrap(), "deep\n");
+    assert_eq!(moved("/_dav/copy.txt", "Destination: /_dav/moved/notes.txt\r\n"), "HTTP/1.1 204 No Content");
+    assert!(!root.join("copy.txt").exists());
+    assert_eq!(std::fs::read_to_string(root.join("moved/notes.txt")).unwrap(), "deep\n");
+    assert_eq!(moved("/_dav/moved/", "Destination: /_dav/\r\n"), "HTTP/1.1 403 Forbidden");
+    assert_eq!(moved("/_dav/moved/", "Destination: /_dav/elsewhere/\r\nDepth: 0\r\n"), "HTTP/1.1 400 Bad Request");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn copies_take_only_what_is_served() {
+    let root = document_root("webdav-copy-refused");
+    std::fs::create_dir_all(root.join("keys/sub")).unwrap();
+    std::fs::write(root.join("key.pem"), "secret\n").unwrap();
+    std::fs::write(root.join("dump.sql"), vec![b'x'; 4096]).unwrap();
+    std::fs::write(root.join("keys/notes.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("keys/sub/key.pem"), "secret\n").unwrap();
+    let args = ["--webdav", "--allow-write", "--deny-ext", ".pem", "--max-file-size", "1024"];
+    let server = start_server(&[&["--root", root.to_str().unwrap()], &args[..]].concat());
+
+    for method in ["COPY", "MOVE"] {
+        let response = request(&server.addr, method, "/_dav/key.pem", "Destination: /_dav/key.txt\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 403 Forbidden", "{}", method);
+        let response = request(&server.addr, method, "/_dav/dump.sql", "Destination: /_dav/dump.txt\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 403 Forbidden", "{}", method);
+        // A tree with such a file anywhere in it is refused whole.
+        let response = request(&server.addr, method, "/_dav/keys/", "Destination: /_dav/copied/\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 207 Multi-Status", "{}", method);
+        assert!(body(&response).contains("<D:href>/_dav/keys/sub/key.pem</D:href><D:status>HTTP/1.1 403 Forbidden</D:status>"), "{}", response);
+    }
+    assert!(!root.join("key.txt").exists() && !root.join("dump.txt").exists() && !root.join("copied").exists());
+    assert!(root.join("key.pem").exists() && root.join("keys/sub/key.pem").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn destinations_stay_inside_the_site() {
+    let root = document_root("webdav-destination");
+    let site = root.join("site");
+    std::fs::create_dir(&site).unwrap();
+    std::fs::write(site.join("notes.txt"), "some notes\n").unwrap();
+    let server = start_server(&["--root", site.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    for destination in ["http://elsewhere.example/_dav/notes2.txt", "/notes2.txt", "/_dav/../../escaped.txt", "/_dav/%2e%2e/escaped.txt"] {
+        let response = request(&server.addr, "COPY", "/_dav/notes.txt", &format!("Destination: {}\r\n", destination));
+        assert_eq!(status(&response), "HTTP/1.1 502 Bad Gateway", "{}", destination);
+    }
+    // Climbing out and back in lands inside the root.
+    let response = request(&server.addr, "COPY", "/_dav/notes.txt", "Destination: /_dav/a/../../../_dav/escaped.txt\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 201 Created");
+    assert!(site.join("escaped.txt").exists());
+    assert!(!root.join("escaped.txt").exists());
+    let response = request(&server.addr, "MOVE", "/_dav/notes.txt", "Destination: /_dav/.gredl.toml\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 403 Forbidden");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn read_only_directories_refuse_changes() {
+    let root = document_root("webdav-read-only");
+    std::fs::create_dir(root.join("locked")).unwrap();
+    std::fs::write(root.join("locked/.gredl.toml"), "read_only = true\n").unwrap();
+    std::fs::write(root.join("locked/notes.txt"), "some notes\n").unwrap();
+    std::fs::write(root.join("free.txt"), "free\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    assert_eq!(status(&put(&server.addr, "/_dav/locked/new.txt", "x")), "HTTP/1.1 403 Forbidden");
+    assert_eq!(status(&request(&server.addr, "MKCOL", "/_dav/locked/sub/", "")), "HTTP/1.1 403 Forbidden");
+    assert_eq!(status(&request(&server.addr, "DELETE", "/_dav/locked/notes.txt", "")), "HTTP/1.1 403 Forbidden");
+    let response = request(&server.addr, "MOVE", "/_dav/locked/notes.txt", "Destination: /_dav/notes.txt\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 403 Forbidden");
+    let response = request(&server.addr, "COPY", "/_dav/free.txt", "Destination: /_dav/locked/free.txt\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 403 Forbidden");
+    // Reading from there is fine.
+    let response = request(&server.addr, "COPY", "/_dav/locked/notes.txt", "Destination: /_dav/notes.txt\r\n");
+    assert_eq!(status(&response), "HTTP/1.1 201 Created");
+    assert_eq!(std::fs::read_dir(root.join("locked")).unwrap().count(), 2);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn trees_with_read_only_directories_or_settings_are_kept() {
+    let root = document_root("webdav-read-only-tree");
+    std::fs::create_dir_all(root.join("p/locked")).unwrap();
+    std::fs::write(root.join("p/locked/.gredl.toml"), "read_only = true\n").unwrap();
+    std::fs::write(root.join("p/locked/notes.txt"), "some notes\n").unwrap();
+    std::fs::create_dir(root.join("shown")).unwrap();
+    std::fs::write(root.join("shown/.gredl.toml"), "show_hidden = true\n").unwrap();
+    std::fs::write(root.join("free.txt"), "free\n").unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write"]);
+
+    for method in ["DELETE", "MOVE"] {
+        let response = request(&server.addr, method, "/_dav/p/", "Destination: /_dav/q/\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 207 Multi-Status", "{}", method);
+        assert!(body(&response).contains("<D:href>/_dav/p/locked/</D:href><D:status>HTTP/1.1 403 Forbidden</D:status>"), "{}", response);
+        let response = request(&server.addr, method, "/_dav/shown/", "Destination: /_dav/q/\r\n");
+        assert_eq!(status(&response), "HTTP/1.1 207 Multi-Status", "{}", method);
+        assert!(body(&response).contains("<D:href>/_dav/shown/.gredl.toml</D:href>"), "{}", response);
+    }
+    // Nor is either overwritten by a COPY.
+    for destination in ["/_dav/p/", "/_dav/shown/"] {
+        let response = request(&server.addr, "COPY", "/_dav/free.txt", &format!("Destination: {}\r\n", destination));
+        assert_eq!(status(&response), "HTTP/1.1 207 Multi-Status", "{}", destination);
+    }
+    assert!(root.join("p/locked/.gredl.toml").exists() && root.join("p/locked/notes.txt").exists());
+    assert!(root.join("shown/.gredl.toml").exists() && !root.join("q").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn destinations_are_checked_against_user_roots() {
+    let root = document_root("webdav-users");
+    std::fs::create_dir(root.join("photos")).unwrap();
+    std::fs::create_dir(root.join("private")).unwrap();
+    std::fs::write(root.join("photos/cat.txt"), "meow\n").unwrap();
+    let accounts = document_root("webdav-users-file");
+    let users_file = accounts.join("users.toml");
+    std::fs::write(&users_file, format!("[alice]\npassword = \"{}\"\nroots = [\"/photos/\"]\n", bcrypt::hash("wonderland", 4).unwrap())).unwrap();
+    let server = start_server(&["--root", root.to_str().unwrap(), "--webdav", "--allow-write", "--users-file", users_file.to_str().unwrap()]);
+
+    let credentials = base64::engine::general_purpose::STANDARD.encode("alice:wonderland");
+    let copy_to = |destination: &str| {
+        let headers = format!("Authorization: Basic {}\r\nDestination: {}\r\n", credentials, destination);
+        status(&request(&server.addr, "COPY", "/_dav/photos/cat.txt", &headers)).to_string()
+    };
+    assert_eq!(copy_to("/_dav/private/cat.txt"), "HTTP/1.1 403 Forbidden");
+    assert!(!root.join("private/cat.txt").exists());
+    assert_eq!(copy_to("/_dav/photos/kitten.txt"), "HTTP/1.1 201 Created");
+    let _ = std::fs::remove_dir_all(&root);
+    let _ = std::fs::remove_dir_all(&accounts);
+}